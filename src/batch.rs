@@ -0,0 +1,368 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use youtube_summary::cache::Cache;
+use youtube_summary::collection::{self, Collection, Expanded};
+use youtube_summary::config::Config;
+use youtube_summary::error::{Error, Result};
+use youtube_summary::output;
+use youtube_summary::pipeline;
+use youtube_summary::provider::AnyProvider;
+use youtube_summary::subtitles;
+use youtube_summary::summarize;
+use youtube_summary::video_id::VideoId;
+
+/// Number of videos processed at once when not configured
+pub const DEFAULT_JOBS: usize = 4;
+
+/// Outcome of one video in a batch
+pub struct BatchResult {
+    pub input: String,
+    pub outcome: Result<Written>,
+}
+
+/// A summary written to the output directory
+pub struct Written {
+    /// Video ID, or `<collection>-overview` for overviews
+    pub name: String,
+    pub path: PathBuf,
+    pub summary: String,
+}
+
+/// Batch inputs after playlists and channels have been replaced by their videos
+pub struct Expansion {
+    pub videos: Vec<String>,
+    pub collections: Vec<Expanded>,
+    /// Collections that couldn't be expanded
+    pub failures: Vec<BatchResult>,
+}
+
+/// Reads video URLs or IDs from a file, or from stdin if `source` is "-".
+/// Blank lines, `#` comments and repeated lines are skipped.
+pub fn read_inputs(source: &str) -> Result<Vec<String>> {
+    let content = if source == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| Error::Config(format!("Failed to read URLs from stdin: {}", e)))?;
+        content
+    } else {
+        fs::read_to_string(source)
+            .map_err(|e| Error::Config(format!("Failed to read batch file {}: {}", source, e)))?
+    };
+
+    let mut inputs: Vec<String> = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !inputs.iter().any(|input| input == line) {
+            inputs.push(line.to_string());
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// Replaces playlist and channel URLs among `inputs` by their member videos.
+/// Videos listed more than once, even by different links, are kept only at
+/// their first position.
+pub async fn expand(inputs: Vec<String>, config: &Config) -> Expansion {
+    let mut expansion = Expansion {
+        videos: Vec::new(),
+        collections: Vec::new(),
+        failures: Vec::new(),
+    };
+    let mut seen = HashSet::new();
+
+    for input in inputs {
+        let Some(target) = Collection::parse(&input) else {
+            if seen.insert(video_key(&input)) {
+                expansion.videos.push(input);
+            }
+            continue;
+        };
+
+        if config.verbose {
+            eprintln!("[verbose] Expanding {}", target.page_url());
+        }
+
+        match collection::expand(&target, &config.expand).await {
+            Ok(expanded) => {
+                if config.verbose {
+                    eprintln!(
+                        "[verbose] Found {} videos in {}",
+                        expanded.videos.len(),
+                        expanded.title.as_deref().unwrap_or(&input)
+                    );
+                }
+                for video in &expanded.videos {
+                    if seen.insert(video_key(&video.video_id)) {
+                        expansion.videos.push(video.video_id.clone());
+                    }
+                }
+                expansion.collections.push(expanded);
+            }
+            Err(e) => expansion.failures.push(BatchResult {
+                input,
+                outcome: Err(e),
+            }),
+        }
+    }
+
+    expansion
+}
+
+/// Identifies the video an input stands for, so that `youtu.be/ID` and
+/// `watch?v=ID` count as the same video. Local files and inputs that are
+/// not videos are identified by themselves.
+fn video_key(input: &str) -> String {
+    if subtitles::is_local(input) {
+        return input.to_string();
+    }
+    VideoId::parse(input).map_or_else(|_| input.to_string(), String::from)
+}
+
+/// Names the output file of each input after its video ID, or its file
+/// name for local files. Names taken by an earlier input get a numeric
+/// suffix, so that `a/talk.srt` and `b/talk.srt` don't overwrite each other.
+fn output_names(inputs: &[String]) -> Vec<String> {
+    let mut taken = HashSet::new();
+    inputs
+        .iter()
+        .map(|input| {
+            let name = if subtitles::is_local(input) {
+                subtitles::input_name(input)
+            } else {
+                video_key(input)
+            };
+            let mut unique = name.clone();
+            let mut suffix = 1;
+            while !taken.insert(unique.clone()) {
+                suffix += 1;
+                unique = format!("{}-{}", name, suffix);
+            }
+            unique
+        })
+        .collect()
+}
+
+/// Summarizes every input with at most `config.jobs` videos in flight, writing
+/// each summary to `<output_dir>/<name>.md`, named as by `output_names`.
+///
+/// A failing video never aborts the others; every outcome is returned in
+/// input order.
+pub async fn run(
+    inputs: Vec<String>,
+    config: Arc<Config>,
+    output_dir: &Path,
+) -> Result<Vec<BatchResult>> {
+    fs::create_dir_all(output_dir).map_err(|e| {
+        Error::Output(format!(
+            "Failed to create output directory {}: {}",
+            output_dir.display(),
+            e
+        ))
+    })?;
+
+    let provider = Arc::new(AnyProvider::new(&config.provider));
+    let cache = Arc::new(Cache::new(config.cache));
+    let semaphore = Arc::new(Semaphore::new(config.jobs.max(1)));
+    let output_dir = Arc::new(output_dir.to_path_buf());
+
+    let mut tasks = JoinSet::new();
+    let names = output_names(&inputs);
+    for (index, (input, file_name)) in inputs.iter().zip(names).enumerate() {
+        let input = input.clone();
+        let provider = Arc::clone(&provider);
+        let cache = Arc::clone(&cache);
+        let config = Arc::clone(&config);
+        let output_dir = Arc::clone(&output_dir);
+        let semaphore = Arc::clone(&semaphore);
+
+        tasks.spawn(async move {
+            // The semaphore is never closed, so acquiring can't fail
+            let _permit = semaphore.acquire_owned().await.ok();
+
+            if config.verbose {
+                eprintln!("[verbose] Processing {}", input);
+            }
+
+            let outcome =
+                match pipeline::summarize_video(&input, &config, &cache, &*provider).await {
+                    Ok(result) => output::render(&result, &config).and_then(|rendered| {
+                        write_summary(&output_dir, &file_name, config.format.extension(), rendered)
+                            .map(|path| Written {
+                                name: result.video_id,
+                                path,
+                                summary: result.summary,
+                            })
+                    }),
+                    Err(e) => Err(e),
+                };
+
+            (index, outcome)
+        });
+    }
+
+    let mut outcomes: Vec<Option<Result<Written>>> = inputs.iter().map(|_| None).collect();
+    while let Some(joined) = tasks.join_next().await {
+        let (index, outcome) = joined
+            .map_err(|e| Error::ApiRequest(format!("Batch task failed unexpectedly: {}", e)))?;
+        outcomes[index] = Some(outcome);
+    }
+
+    Ok(inputs
+        .into_iter()
+        .zip(outcomes)
+        .map(|(input, outcome)| BatchResult {
+            input,
+            outcome: outcome.unwrap_or_else(|| {
+                Err(Error::ApiRequest("Batch task did not complete".to_string()))
+            }),
+        })
+        .collect())
+}
+
+/// Writes an overview of each expanded collection to
+/// `<output_dir>/<collection>-overview.md`, built from the summaries of its
+/// videos that succeeded
+pub async fn write_overviews(
+    collections: &[Expanded],
+    results: &[BatchResult],
+    config: &Config,
+    output_dir: &Path,
+) -> Vec<BatchResult> {
+    let provider = AnyProvider::new(&config.provider);
+    let mut overviews = Vec::new();
+
+    for expanded in collections {
+        let summaries: Vec<String> = expanded
+            .videos
+            .iter()
+            .filter_map(|video| {
+                results.iter().find_map(|result| match &result.outcome {
+                    Ok(written) if written.name == video.video_id => Some(match &video.title {
+                        Some(title) => format!("## {}\n\n{}", title, written.summary),
+                        None => written.summary.clone(),
+                    }),
+                    _ => None,
+                })
+            })
+            .collect();
+
+        let name = format!("{}-overview", expanded.collection.id());
+        let outcome =
+            if summaries.is_empty() {
+                Err(Error::Output(
+                    "No video summaries to build an overview from".to_string(),
+                ))
+            } else {
+                let title = expanded
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| expanded.collection.id());
+                match summarize::overview(&provider, config, &title, summaries).await {
+                    Ok(overview) => write_summary(output_dir, &name, "md", overview.text.clone())
+                        .map(|path| Written {
+                            name,
+                            path,
+                            summary: overview.text,
+                        }),
+                    Err(e) => Err(e),
+                }
+            };
+
+        overviews.push(BatchResult {
+            input: expanded.collection.page_url(),
+            outcome,
+        });
+    }
+
+    overviews
+}
+
+/// Prints the final batch report to stderr
+pub fn print_report(results: &[BatchResult]) {
+    let failed = results.iter().filter(|r| r.outcome.is_err()).count();
+
+    eprintln!();
+    eprintln!(
+        "Batch finished: {} succeeded, {} failed",
+        results.len() - failed,
+        failed
+    );
+
+    for result in results {
+        match &result.outcome {
+            Ok(written) => eprintln!("  OK    {} -> {}", result.input, written.path.display()),
+            Err(e) => eprintln!("  FAIL  {} [{}] {}", result.input, e.kind(), e),
+        }
+    }
+}
+
+fn write_summary(
+    output_dir: &Path,
+    name: &str,
+    extension: &str,
+    content: String,
+) -> Result<PathBuf> {
+    let path = output_dir.join(format!("{}.{}", name, extension));
+    fs::write(&path, format!("{}\n", content))
+        .map_err(|e| Error::Output(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(inputs: &[&str]) -> Vec<String> {
+        inputs.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn identifies_videos_by_id() {
+        let links = [
+            "dQw4w9WgXcQ",
+            "https://youtu.be/dQw4w9WgXcQ",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42",
+            "youtube.com/shorts/dQw4w9WgXcQ",
+        ];
+        for link in links {
+            assert_eq!(video_key(link), "dQw4w9WgXcQ", "{:?}", link);
+        }
+        assert_eq!(video_key("./dQw4w9WgXcQ"), "./dQw4w9WgXcQ");
+        assert_eq!(video_key("not a video"), "not a video");
+    }
+
+    #[test]
+    fn names_outputs_uniquely() {
+        let inputs = strings(&[
+            "a/talk.srt",
+            "https://youtu.be/dQw4w9WgXcQ",
+            "b/talk.srt",
+            "talk-2.vtt",
+            "c/talk.txt",
+            "./dQw4w9WgXcQ.srt",
+        ]);
+        assert_eq!(
+            output_names(&inputs),
+            [
+                "talk",
+                "dQw4w9WgXcQ",
+                "talk-2",
+                "talk-2-2",
+                "talk-3",
+                "dQw4w9WgXcQ-2"
+            ]
+        );
+    }
+}
@@ -0,0 +1,840 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::metadata::VideoMetadata;
+use crate::provider::ModelInfo;
+use crate::timestamps;
+use crate::transcript::{Transcript, TranscriptOptions};
+
+const TRANSCRIPTS_DIR: &str = "transcripts";
+const SUMMARIES_DIR: &str = "summaries";
+const MODELS_DIR: &str = "models";
+const METADATA_DIR: &str = "metadata";
+
+/// Cached model lists (pricing and context lengths) are refetched after
+/// this long
+const MODELS_MAX_AGE: Duration = Duration::from_secs(24 * 3600);
+
+/// How the cache is used for a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Read cached entries and store new ones
+    Use,
+    /// Ignore cached entries but store fresh results
+    Refresh,
+    /// Neither read nor write the cache
+    Bypass,
+}
+
+/// On-disk cache of transcripts and summaries under the XDG cache dir
+pub struct Cache {
+    dir: PathBuf,
+    policy: CachePolicy,
+}
+
+/// Everything that influences a summary, used to build its cache key
+pub struct SummaryKey<'a> {
+    pub video_id: &'a str,
+    pub language: &'a str,
+    /// The provider and its base URL ("" for the default endpoint), since
+    /// the same model name can mean different models on different servers
+    pub provider: &'a str,
+    pub base_url: &'a str,
+    pub model: &'a str,
+    pub prompt: &'a str,
+    pub timestamps: bool,
+    pub output_language: Option<&'a str>,
+    /// Where the chapters come from, for summaries written chapter by
+    /// chapter
+    pub chapters: Option<&'a str>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TranscriptEntry {
+    created_at: u64,
+    transcript: Transcript,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SummaryEntry {
+    created_at: u64,
+    video_id: String,
+    language: String,
+    #[serde(default)]
+    provider: String,
+    #[serde(default)]
+    base_url: String,
+    model: String,
+    /// The fallback model that produced the summary, if not `model`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    produced_by: Option<String>,
+    prompt: String,
+    timestamps: bool,
+    output_language: Option<String>,
+    chapters: Option<String>,
+    summary: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MetadataEntry {
+    created_at: u64,
+    metadata: VideoMetadata,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ModelsEntry {
+    created_at: u64,
+    provider: String,
+    base_url: String,
+    models: Vec<ModelInfo>,
+}
+
+/// A file in the cache, as shown by `cache list`
+#[derive(Debug)]
+pub struct CacheEntry {
+    pub kind: &'static str,
+    pub key: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// Outcome of pruning the cache
+#[derive(Debug, Default)]
+pub struct PruneStats {
+    pub removed: usize,
+    pub freed: u64,
+}
+
+impl Cache {
+    pub fn new(policy: CachePolicy) -> Self {
+        Cache::in_dir(cache_dir(), policy)
+    }
+
+    /// A cache kept in `dir` rather than the user's cache directory
+    pub fn in_dir(dir: impl Into<PathBuf>, policy: CachePolicy) -> Self {
+        Cache {
+            dir: dir.into(),
+            policy,
+        }
+    }
+
+    pub fn get_transcript(
+        &self,
+        video_id: &str,
+        options: &TranscriptOptions,
+    ) -> Option<Transcript> {
+        if self.policy != CachePolicy::Use {
+            return None;
+        }
+
+        let path = self.transcript_path(video_id, options);
+        read_json::<TranscriptEntry>(&path).map(|entry| entry.transcript)
+    }
+
+    pub fn put_transcript(
+        &self,
+        transcript: &Transcript,
+        options: &TranscriptOptions,
+    ) -> Result<()> {
+        if self.policy == CachePolicy::Bypass {
+            return Ok(());
+        }
+
+        let path = self.transcript_path(&transcript.video_id, options);
+        write_json(
+            &path,
+            &TranscriptEntry {
+                created_at: now(),
+                transcript: transcript.clone(),
+            },
+        )
+    }
+
+    pub fn get_metadata(&self, video_id: &str) -> Option<VideoMetadata> {
+        if self.policy != CachePolicy::Use {
+            return None;
+        }
+
+        read_json::<MetadataEntry>(&self.metadata_path(video_id)).map(|entry| entry.metadata)
+    }
+
+    pub fn put_metadata(&self, metadata: &VideoMetadata) -> Result<()> {
+        if self.policy == CachePolicy::Bypass {
+            return Ok(());
+        }
+
+        write_json(
+            &self.metadata_path(&metadata.video_id),
+            &MetadataEntry {
+                created_at: now(),
+                metadata: metadata.clone(),
+            },
+        )
+    }
+
+    /// Returns a cached summary and the model that produced it
+    pub fn get_summary(&self, key: &SummaryKey) -> Option<(String, String)> {
+        if self.policy != CachePolicy::Use {
+            return None;
+        }
+
+        let entry = read_json::<SummaryEntry>(&self.summary_path(key))?;
+        Some((entry.summary, entry.produced_by.unwrap_or(entry.model)))
+    }
+
+    /// Stores a summary produced by `model`, which differs from the key's
+    /// model if a fallback model was used
+    pub fn put_summary(&self, key: &SummaryKey, summary: &str, model: &str) -> Result<()> {
+        if self.policy == CachePolicy::Bypass {
+            return Ok(());
+        }
+
+        write_json(
+            &self.summary_path(key),
+            &SummaryEntry {
+                created_at: now(),
+                video_id: key.video_id.to_string(),
+                language: key.language.to_string(),
+                provider: key.provider.to_string(),
+                base_url: key.base_url.to_string(),
+                model: key.model.to_string(),
+                produced_by: (model != key.model).then(|| model.to_string()),
+                prompt: key.prompt.to_string(),
+                timestamps: key.timestamps,
+                output_language: key.output_language.map(str::to_string),
+                chapters: key.chapters.map(str::to_string),
+                summary: summary.to_string(),
+            },
+        )
+    }
+
+    /// Returns the cached model list of a provider, unless it is outdated
+    pub fn get_models(&self, provider: &str, base_url: &str) -> Option<Vec<ModelInfo>> {
+        if self.policy != CachePolicy::Use {
+            return None;
+        }
+
+        let entry = read_json::<ModelsEntry>(&self.models_path(provider, base_url))?;
+        let age = Duration::from_secs(now().saturating_sub(entry.created_at));
+        (age <= MODELS_MAX_AGE).then_some(entry.models)
+    }
+
+    pub fn put_models(&self, provider: &str, base_url: &str, models: &[ModelInfo]) -> Result<()> {
+        if self.policy == CachePolicy::Bypass {
+            return Ok(());
+        }
+
+        write_json(
+            &self.models_path(provider, base_url),
+            &ModelsEntry {
+                created_at: now(),
+                provider: provider.to_string(),
+                base_url: base_url.to_string(),
+                models: models.to_vec(),
+            },
+        )
+    }
+
+    /// Returns the models of every cached model list, however old, without
+    /// duplicates. Used for shell completion.
+    pub fn cached_models(&self) -> Vec<ModelInfo> {
+        let Ok(read_dir) = fs::read_dir(self.dir.join(MODELS_DIR)) else {
+            return Vec::new();
+        };
+
+        let mut models: Vec<ModelInfo> = read_dir
+            .flatten()
+            .filter_map(|item| read_json::<ModelsEntry>(&item.path()))
+            .flat_map(|entry| entry.models)
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        models.dedup_by(|a, b| a.id == b.id);
+        models
+    }
+
+    /// Lists all cache entries, oldest first
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+
+        for (kind, subdir) in [
+            ("transcript", TRANSCRIPTS_DIR),
+            ("metadata", METADATA_DIR),
+            ("summary", SUMMARIES_DIR),
+            ("models", MODELS_DIR),
+        ] {
+            let dir = self.dir.join(subdir);
+            if !dir.exists() {
+                continue;
+            }
+
+            let read_dir = fs::read_dir(&dir)
+                .map_err(|e| Error::Cache(format!("Failed to read {}: {}", dir.display(), e)))?;
+
+            for item in read_dir.flatten() {
+                let path = item.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                    continue;
+                }
+                let Ok(metadata) = item.metadata() else {
+                    continue;
+                };
+                let key = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or_default()
+                    .to_string();
+
+                entries.push(CacheEntry {
+                    kind,
+                    key,
+                    path,
+                    size: metadata.len(),
+                    modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+                });
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.modified);
+        Ok(entries)
+    }
+
+    /// Finds an entry by its key
+    pub fn find(&self, key: &str) -> Result<CacheEntry> {
+        self.entries()?
+            .into_iter()
+            .find(|entry| entry.key == key)
+            .ok_or_else(|| Error::Cache(format!("No cache entry named '{}'", key)))
+    }
+
+    /// Renders an entry's metadata and content for `cache show`
+    pub fn describe(&self, entry: &CacheEntry) -> Result<String> {
+        let invalid = || Error::Cache(format!("Cache entry '{}' is corrupt", entry.key));
+
+        let text = match entry.kind {
+            "transcript" => {
+                let cached: TranscriptEntry = read_json(&entry.path).ok_or_else(invalid)?;
+                let transcript = cached.transcript;
+                format!(
+                    "Kind:       transcript\nVideo:      {}\nLanguage:   {}\nSnippets:   {}\nDuration:   {}\nCreated:    {} ago\n\n{}",
+                    transcript.video_id,
+                    transcript.language,
+                    transcript.snippets.len(),
+                    timestamps::format_timestamp(transcript.duration() as u64),
+                    format_age(UNIX_EPOCH + Duration::from_secs(cached.created_at)),
+                    transcript.pieces(true).join("\n")
+                )
+            }
+            "metadata" => {
+                let cached: MetadataEntry = read_json(&entry.path).ok_or_else(invalid)?;
+                let metadata = cached.metadata;
+                format!(
+                    "Kind:       metadata\nVideo:      {}\nTitle:      {}\nChannel:    {}\nUploaded:   {}\nDuration:   {}\nChapters:   {}\nCreated:    {} ago\n\n{}",
+                    metadata.video_id,
+                    metadata.title,
+                    metadata.channel,
+                    metadata.upload_date.as_deref().unwrap_or("-"),
+                    timestamps::format_timestamp(metadata.duration),
+                    metadata.chapters.len(),
+                    format_age(UNIX_EPOCH + Duration::from_secs(cached.created_at)),
+                    metadata.description
+                )
+            }
+            "models" => {
+                let cached: ModelsEntry = read_json(&entry.path).ok_or_else(invalid)?;
+                let mut text = format!(
+                    "Kind:       models\nProvider:   {}\nBase URL:   {}\nModels:     {}\nCreated:    {} ago\n",
+                    cached.provider,
+                    if cached.base_url.is_empty() {
+                        "-"
+                    } else {
+                        &cached.base_url
+                    },
+                    cached.models.len(),
+                    format_age(UNIX_EPOCH + Duration::from_secs(cached.created_at)),
+                );
+                for model in &cached.models {
+                    text.push_str(&format!("\n{}", model.id));
+                }
+                text
+            }
+            _ => {
+                let cached: SummaryEntry = read_json(&entry.path).ok_or_else(invalid)?;
+                let model = match cached.produced_by {
+                    Some(ref fallback) => format!("{} (fallback for {})", fallback, cached.model),
+                    None => cached.model.clone(),
+                };
+                format!(
+                    "Kind:       summary\nVideo:      {}\nLanguage:   {}\nProvider:   {}\nBase URL:   {}\nModel:      {}\nTimestamps: {}\nOutput:     {}\nChapters:   {}\nCreated:    {} ago\nPrompt:     {}\n\n{}",
+                    cached.video_id,
+                    cached.language,
+                    if cached.provider.is_empty() {
+                        "-"
+                    } else {
+                        &cached.provider
+                    },
+                    if cached.base_url.is_empty() {
+                        "-"
+                    } else {
+                        &cached.base_url
+                    },
+                    model,
+                    cached.timestamps,
+                    cached.output_language.as_deref().unwrap_or("-"),
+                    cached.chapters.as_deref().unwrap_or("-"),
+                    format_age(UNIX_EPOCH + Duration::from_secs(cached.created_at)),
+                    cached.prompt,
+                    cached.summary
+                )
+            }
+        };
+
+        Ok(text)
+    }
+
+    /// Removes entries older than `max_age`, then the oldest remaining
+    /// entries until the cache is no larger than `max_size` bytes
+    pub fn prune(&self, max_age: Option<Duration>, max_size: Option<u64>) -> Result<PruneStats> {
+        let mut stats = PruneStats::default();
+        let now = SystemTime::now();
+        let mut kept = Vec::new();
+
+        for entry in self.entries()? {
+            let age = now.duration_since(entry.modified).unwrap_or_default();
+            if max_age.is_some_and(|max_age| age > max_age) {
+                remove_entry(&entry, &mut stats)?;
+            } else {
+                kept.push(entry);
+            }
+        }
+
+        if let Some(max_size) = max_size {
+            let mut total: u64 = kept.iter().map(|entry| entry.size).sum();
+            // Entries are sorted oldest first
+            for entry in &kept {
+                if total <= max_size {
+                    break;
+                }
+                remove_entry(entry, &mut stats)?;
+                total -= entry.size;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Removes every cache entry
+    pub fn clear(&self) -> Result<PruneStats> {
+        let mut stats = PruneStats::default();
+        for entry in self.entries()? {
+            remove_entry(&entry, &mut stats)?;
+        }
+        Ok(stats)
+    }
+
+    fn transcript_path(&self, video_id: &str, options: &TranscriptOptions) -> PathBuf {
+        let mut key = format!("{}.{}", video_id, options.languages.join(","));
+        if let Some(ref target) = options.translate_to {
+            key.push_str(&format!(".to-{}", target));
+        }
+
+        self.dir
+            .join(TRANSCRIPTS_DIR)
+            .join(format!("{}.json", sanitize(&key)))
+    }
+
+    fn metadata_path(&self, video_id: &str) -> PathBuf {
+        self.dir
+            .join(METADATA_DIR)
+            .join(format!("{}.json", sanitize(video_id)))
+    }
+
+    fn models_path(&self, provider: &str, base_url: &str) -> PathBuf {
+        let name = format!("{}.{:016x}", provider, fnv1a(base_url.as_bytes()));
+
+        self.dir
+            .join(MODELS_DIR)
+            .join(format!("{}.json", sanitize(&name)))
+    }
+
+    fn summary_path(&self, key: &SummaryKey) -> PathBuf {
+        let mut fingerprint = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            key.language,
+            key.provider,
+            key.base_url,
+            key.model,
+            key.prompt,
+            key.timestamps,
+            key.output_language.unwrap_or_default()
+        );
+        // Only appended when set, keeping the keys of plain summaries stable
+        if let Some(chapters) = key.chapters {
+            fingerprint.push_str(&format!("\nchapters:{}", chapters));
+        }
+        let name = format!("{}.{:016x}", key.video_id, fnv1a(fingerprint.as_bytes()));
+
+        self.dir
+            .join(SUMMARIES_DIR)
+            .join(format!("{}.json", sanitize(&name)))
+    }
+}
+
+/// Returns `$XDG_CACHE_HOME/youtube-summary`, or `~/.cache/youtube-summary`
+pub fn cache_dir() -> PathBuf {
+    let base = env::var("XDG_CACHE_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+            PathBuf::from(home).join(".cache")
+        });
+
+    base.join("youtube-summary")
+}
+
+/// Parses an age such as "30m", "12h" or "7d"
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let invalid = || {
+        Error::Config(format!(
+            "Invalid age: '{}' (expected a number followed by s, m, h or d)",
+            value
+        ))
+    };
+
+    let (index, unit) = value.char_indices().last().ok_or_else(invalid)?;
+    let number: u64 = value[..index].parse().map_err(|_| invalid())?;
+    let scale = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86_400,
+        _ => return Err(invalid()),
+    };
+    let seconds = number.checked_mul(scale).ok_or_else(invalid)?;
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parses a size such as "500K", "100M" or "1G" into bytes
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let invalid = || {
+        Error::Config(format!(
+            "Invalid size: '{}' (expected a number optionally followed by K, M or G)",
+            value
+        ))
+    };
+
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((index, unit)) => match unit.to_ascii_uppercase() {
+            'K' => (&value[..index], 1024),
+            'M' => (&value[..index], 1024 * 1024),
+            'G' => (&value[..index], 1024 * 1024 * 1024),
+            _ => (value, 1),
+        },
+        None => (value, 1),
+    };
+
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(invalid)
+}
+
+/// Formats a byte count for display
+pub fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Formats the age of a timestamp for display
+pub fn format_age(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+
+    if secs >= 86_400 {
+        format!("{}d", secs / 86_400)
+    } else if secs >= 3600 {
+        format!("{}h", secs / 3600)
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    // A corrupt entry is treated like a miss and overwritten later
+    serde_json::from_str(&content).ok()
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| Error::Cache(format!("Failed to create cache directory: {}", e)))?;
+    }
+
+    let content = serde_json::to_string(value)
+        .map_err(|e| Error::Cache(format!("Failed to serialize cache entry: {}", e)))?;
+
+    fs::write(path, content)
+        .map_err(|e| Error::Cache(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+fn remove_entry(entry: &CacheEntry, stats: &mut PruneStats) -> Result<()> {
+    fs::remove_file(&entry.path)
+        .map_err(|e| Error::Cache(format!("Failed to remove {}: {}", entry.path.display(), e)))?;
+    stats.removed += 1;
+    stats.freed += entry.size;
+    Ok(())
+}
+
+/// Keeps cache file names to a safe character set
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ',') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// 64-bit FNV-1a hash. Unlike `DefaultHasher` it is stable across Rust
+/// releases, so cache keys survive toolchain upgrades.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh cache directory in the temp directory, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!(
+                "youtube-summary-cache-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            TempDir(dir)
+        }
+
+        fn cache(&self, policy: CachePolicy) -> Cache {
+            Cache::in_dir(&self.0, policy)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn key<'a>(video_id: &'a str, provider: &'a str, base_url: &'a str) -> SummaryKey<'a> {
+        SummaryKey {
+            video_id,
+            language: "en",
+            provider,
+            base_url,
+            model: "llama3",
+            prompt: "Summarize",
+            timestamps: false,
+            output_language: None,
+            chapters: None,
+        }
+    }
+
+    #[test]
+    fn parses_ages() {
+        let cases: &[(&str, Option<u64>)] = &[
+            ("30s", Some(30)),
+            ("30m", Some(1800)),
+            ("12h", Some(43_200)),
+            ("7d", Some(604_800)),
+            (" 7d ", Some(604_800)),
+            ("0d", Some(0)),
+            ("213503982334601d", Some(213_503_982_334_601 * 86_400)),
+            ("", None),
+            ("d", None),
+            ("7", None),
+            ("7w", None),
+            ("7D", None),
+            ("-7d", None),
+            ("1.5h", None),
+            ("7 d", None),
+            ("7é", None),
+            ("é", None),
+            ("7dé", None),
+            ("99999999999999999d", None),
+            ("18446744073709551615m", None),
+            ("18446744073709551616s", None),
+        ];
+        for &(value, expected) in cases {
+            let parsed = parse_age(value).ok().map(|age| age.as_secs());
+            assert_eq!(parsed, expected, "age {:?}", value);
+        }
+    }
+
+    #[test]
+    fn parses_sizes() {
+        let cases: &[(&str, Option<u64>)] = &[
+            ("0", Some(0)),
+            ("512", Some(512)),
+            ("500K", Some(512_000)),
+            ("500k", Some(512_000)),
+            ("100M", Some(104_857_600)),
+            ("1G", Some(1_073_741_824)),
+            (" 2g ", Some(2_147_483_648)),
+            ("18446744073709551615", Some(u64::MAX)),
+            ("", None),
+            ("K", None),
+            ("1T", None),
+            ("1KB", None),
+            ("-1K", None),
+            ("1.5M", None),
+            ("5é", None),
+            ("é", None),
+            ("17179869184G", None),
+            ("18446744073709551615K", None),
+        ];
+        for &(value, expected) in cases {
+            assert_eq!(parse_size(value).ok(), expected, "size {:?}", value);
+        }
+    }
+
+    #[test]
+    fn stores_summaries() {
+        let dir = TempDir::new("summaries");
+        let cache = dir.cache(CachePolicy::Use);
+        let openrouter = key("dQw4w9WgXcQ", "openrouter", "");
+        assert_eq!(cache.get_summary(&openrouter), None);
+
+        cache.put_summary(&openrouter, "Summary", "llama3").unwrap();
+        assert_eq!(
+            cache.get_summary(&openrouter),
+            Some(("Summary".to_string(), "llama3".to_string()))
+        );
+
+        // The same model name elsewhere is a different model
+        for other in [
+            key("dQw4w9WgXcQ", "ollama", ""),
+            key("dQw4w9WgXcQ", "openrouter", "http://localhost:8080/v1"),
+            key("jNQXAC9IVRw", "openrouter", ""),
+        ] {
+            assert_eq!(cache.get_summary(&other), None);
+        }
+
+        cache
+            .put_summary(&openrouter, "Fallback summary", "mistral")
+            .unwrap();
+        assert_eq!(
+            cache.get_summary(&openrouter),
+            Some(("Fallback summary".to_string(), "mistral".to_string()))
+        );
+    }
+
+    #[test]
+    fn follows_the_policy() {
+        let dir = TempDir::new("policy");
+        let key = key("dQw4w9WgXcQ", "openrouter", "");
+        dir.cache(CachePolicy::Use)
+            .put_summary(&key, "Old", "llama3")
+            .unwrap();
+
+        // Refresh ignores the cached summary but stores the new one
+        let refresh = dir.cache(CachePolicy::Refresh);
+        assert_eq!(refresh.get_summary(&key), None);
+        refresh.put_summary(&key, "New", "llama3").unwrap();
+        assert_eq!(
+            dir.cache(CachePolicy::Use).get_summary(&key),
+            Some(("New".to_string(), "llama3".to_string()))
+        );
+
+        // Bypass neither reads nor writes
+        let bypass = dir.cache(CachePolicy::Bypass);
+        assert_eq!(bypass.get_summary(&key), None);
+        bypass.put_summary(&key, "Ignored", "llama3").unwrap();
+        assert_eq!(
+            dir.cache(CachePolicy::Use).get_summary(&key),
+            Some(("New".to_string(), "llama3".to_string()))
+        );
+    }
+
+    #[test]
+    fn prunes_old_and_oversized_entries() {
+        let dir = TempDir::new("prune");
+        let cache = dir.cache(CachePolicy::Use);
+        let now = SystemTime::now();
+        let ages = [
+            ("aaaaaaaaaaa", 3 * 86_400),
+            ("bbbbbbbbbbb", 7200),
+            ("ccccccccccc", 0),
+        ];
+        for (video_id, age) in ages {
+            let key = key(video_id, "openrouter", "");
+            cache.put_summary(&key, "Summary", "llama3").unwrap();
+            fs::File::options()
+                .write(true)
+                .open(cache.summary_path(&key))
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+        let sizes: Vec<u64> = cache.entries().unwrap().iter().map(|e| e.size).collect();
+
+        let stats = cache
+            .prune(Some(Duration::from_secs(86_400)), None)
+            .unwrap();
+        assert_eq!((stats.removed, stats.freed), (1, sizes[0]));
+
+        let stats = cache.prune(None, Some(sizes[2])).unwrap();
+        assert_eq!((stats.removed, stats.freed), (1, sizes[1]));
+
+        let keys: Vec<String> = cache
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].starts_with("ccccccccccc."), "{:?}", keys);
+
+        assert_eq!(cache.prune(None, None).unwrap().removed, 0);
+    }
+}
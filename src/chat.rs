@@ -0,0 +1,377 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use youtube_summary::chunking;
+use youtube_summary::config::Config;
+use youtube_summary::cost::{self, Estimate};
+use youtube_summary::error::{Error, Result};
+use youtube_summary::provider::{ChatMessage, ChatRequest, Provider};
+use youtube_summary::summarize;
+use youtube_summary::transcript::Transcript;
+
+/// Maximum number of tokens requested for each answer
+const MAX_TOKENS: u32 = 2048;
+
+/// Share of the context budget given to the transcript; the rest is kept
+/// for the conversation history
+const TRANSCRIPT_SHARE: f64 = 0.75;
+
+/// Number of sections a transcript that doesn't fit is split into per
+/// transcript budget, so that several relevant sections fit at once
+const SECTIONS_PER_BUDGET: usize = 4;
+
+const INSTRUCTIONS: &str = "You are answering questions about a YouTube video using its \
+     transcript. Base your answers on the transcript only; if it doesn't cover a question, say \
+     so. The transcript contains [mm:ss] markers giving the time at which each passage starts; \
+     cite them when pointing to a part of the video.";
+
+const HELP: &str = "Commands:
+  /save [PATH]    Save the conversation as Markdown (default: <video_id>-chat.md)
+  /reset          Forget the conversation so far
+  /model [MODEL]  Show or switch the model
+  /help           Show this help
+  /quit           Leave the chat (or press Ctrl-D)";
+
+/// An interactive conversation about one video
+struct Session<'a, P: Provider> {
+    provider: &'a P,
+    config: Config,
+    transcript: Transcript,
+    /// Transcript sections, sized to the current model's context
+    sections: Vec<String>,
+    transcript_budget: usize,
+    context_length: u64,
+    history: Vec<ChatMessage>,
+    /// Questions and answers for `/save`, never trimmed
+    log: Vec<(String, String, String)>,
+    /// Dollars spent on the questions so far, counted against `--max-cost`
+    spent: f64,
+}
+
+/// Runs the chat REPL on stdin until `/quit` or end of input
+pub async fn run<P: Provider>(provider: &P, config: &Config, transcript: Transcript) -> Result<()> {
+    let mut session = Session {
+        provider,
+        config: config.clone(),
+        transcript,
+        sections: Vec::new(),
+        transcript_budget: 0,
+        context_length: 0,
+        history: Vec::new(),
+        log: Vec::new(),
+        spent: 0.0,
+    };
+    session.fit_to_model().await;
+
+    println!(
+        "Chatting about video {} with {}. Type /help for commands.",
+        session.transcript.video_id, session.config.model
+    );
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("\n> ");
+        io::stdout()
+            .flush()
+            .map_err(|e| Error::Output(format!("Failed to write prompt: {}", e)))?;
+
+        let Some(line) = lines.next() else {
+            println!();
+            return Ok(());
+        };
+        let line = line.map_err(|e| Error::Output(format!("Failed to read input: {}", e)))?;
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(command) = line.strip_prefix('/') {
+            let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+            let argument = argument.trim();
+
+            match name {
+                "quit" | "exit" | "q" => return Ok(()),
+                "help" => println!("{}", HELP),
+                "reset" => {
+                    session.history.clear();
+                    println!("Conversation reset.");
+                }
+                "save" => match session.save(argument) {
+                    Ok(path) => println!("Saved to {}", path),
+                    Err(e) => eprintln!("Error: {}", e),
+                },
+                "model" if argument.is_empty() => println!("Model: {}", session.config.model),
+                "model" => {
+                    session.config.model = argument.to_string();
+                    session.fit_to_model().await;
+                    println!("Switched to {}", session.config.model);
+                }
+                _ => eprintln!("Unknown command: /{} (type /help for commands)", name),
+            }
+            continue;
+        }
+
+        // A failed question leaves the session usable
+        if let Err(e) = session.ask(line).await {
+            eprintln!("Error: {}", e);
+        }
+    }
+}
+
+impl<P: Provider> Session<'_, P> {
+    /// Sizes the transcript sections to the context of the current model,
+    /// using the same budgeting as summaries
+    async fn fit_to_model(&mut self) {
+        self.context_length = summarize::fetch_context_length(self.provider, &self.config).await;
+        let budget =
+            chunking::chunk_budget(self.context_length, &self.instructions(true), MAX_TOKENS);
+        self.transcript_budget = (budget as f64 * TRANSCRIPT_SHARE) as usize;
+
+        let pieces = self.transcript.pieces(true);
+        let whole = chunking::pack(
+            pieces.iter().map(String::as_str),
+            "\n",
+            self.transcript_budget,
+        );
+
+        self.sections = if whole.len() <= 1 {
+            whole
+        } else {
+            chunking::pack(
+                pieces.iter().map(String::as_str),
+                "\n",
+                (self.transcript_budget / SECTIONS_PER_BUDGET).max(1),
+            )
+        };
+
+        if self.config.verbose {
+            eprintln!(
+                "[verbose] Transcript budget: {} chars in {} section(s)",
+                self.transcript_budget,
+                self.sections.len()
+            );
+        }
+    }
+
+    fn instructions(&self, partial: bool) -> String {
+        let mut instructions = INSTRUCTIONS.to_string();
+        if partial {
+            instructions.push_str(
+                " The transcript is too long to include in full, so only the passages most \
+                 relevant to the question are given; they may leave out parts of the video.",
+            );
+        }
+        if let Some(ref language) = self.config.output_language {
+            instructions.push_str(&format!(" Answer in {}.", language));
+        }
+        instructions
+    }
+
+    async fn ask(&mut self, question: &str) -> Result<()> {
+        let partial = self.sections.len() > 1;
+        let excerpt = if partial {
+            self.relevant_sections(question)
+        } else {
+            self.sections.first().cloned().unwrap_or_default()
+        };
+
+        self.trim_history();
+
+        let mut messages = vec![ChatMessage::system(format!(
+            "{}\n\n---\n\nTranscript:\n{}",
+            self.instructions(partial),
+            excerpt
+        ))];
+        messages.extend(self.history.iter().cloned());
+        messages.push(ChatMessage::user(question.to_string()));
+
+        let request = ChatRequest {
+            model: self.config.model.clone(),
+            fallback_models: Vec::new(),
+            messages,
+            max_tokens: MAX_TOKENS,
+            context_length: self.context_length,
+        };
+
+        // The cost limit covers the whole chat, like all requests of a summary
+        let mut budget = self.config.clone();
+        budget.max_cost = self
+            .config
+            .max_cost
+            .map(|max_cost| (max_cost - self.spent).max(0.0));
+        let prices =
+            summarize::check_budget(self.provider, &budget, &Estimate::for_request(&request))
+                .await?;
+
+        println!();
+        let completion = self.provider.complete(&request, true).await?;
+        println!();
+
+        if let (Some(prices), Some(usage)) = (prices, completion.usage) {
+            let spent = prices.usage_cost(&usage);
+            self.spent += spent;
+            if self.config.verbose || self.config.max_cost.is_some() {
+                eprintln!(
+                    "Cost: {} (chat total {})",
+                    cost::format_cost(spent),
+                    cost::format_cost(self.spent)
+                );
+            }
+        }
+
+        self.history.push(ChatMessage::user(question.to_string()));
+        self.history
+            .push(ChatMessage::assistant(completion.text.clone()));
+        self.log.push((
+            question.to_string(),
+            self.config.model.clone(),
+            completion.text,
+        ));
+        Ok(())
+    }
+
+    /// Picks the sections sharing the most words with the question (and
+    /// the previous question, for follow-ups) that fit into the budget,
+    /// in the order they appear in the video
+    fn relevant_sections(&self, question: &str) -> String {
+        let mut query = question.to_string();
+        if let Some(previous) = self.history.iter().rev().find(|m| m.role == "user") {
+            query.push(' ');
+            query.push_str(&previous.content);
+        }
+        let terms = terms(&query);
+
+        let mut ranked: Vec<(usize, usize)> = self
+            .sections
+            .iter()
+            .enumerate()
+            .map(|(index, section)| {
+                let words = terms_list(section);
+                let score = words.iter().filter(|word| terms.contains(*word)).count();
+                (index, score)
+            })
+            .collect();
+        // Stable sort keeps earlier sections first among equal scores
+        ranked.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+
+        let mut chosen = Vec::new();
+        let mut used = 0;
+        for (index, _) in ranked {
+            let len = self.sections[index].chars().count();
+            if used + len > self.transcript_budget {
+                continue;
+            }
+            used += len;
+            chosen.push(index);
+        }
+        chosen.sort_unstable();
+
+        if self.config.verbose {
+            eprintln!(
+                "[verbose] Using transcript sections {:?} of {}",
+                chosen.iter().map(|i| i + 1).collect::<Vec<_>>(),
+                self.sections.len()
+            );
+        }
+
+        chosen
+            .iter()
+            .map(|&index| self.sections[index].as_str())
+            .collect::<Vec<_>>()
+            .join("\n[...]\n")
+    }
+
+    /// Drops the oldest question and answer pairs until the history fits
+    /// into the part of the budget not reserved for the transcript
+    fn trim_history(&mut self) {
+        let budget =
+            (self.transcript_budget as f64 * (1.0 - TRANSCRIPT_SHARE) / TRANSCRIPT_SHARE) as usize;
+
+        while self.history.len() >= 2
+            && self
+                .history
+                .iter()
+                .map(|m| m.content.chars().count())
+                .sum::<usize>()
+                > budget
+        {
+            self.history.drain(..2);
+            if self.config.verbose {
+                eprintln!("[verbose] Dropped the oldest question from the conversation");
+            }
+        }
+    }
+
+    fn save(&self, path: &str) -> Result<String> {
+        let path = if path.is_empty() {
+            format!("{}-chat.md", self.transcript.video_id)
+        } else {
+            path.to_string()
+        };
+
+        let mut text = format!("# Chat about {}\n", source(&self.transcript));
+        for (question, model, answer) in &self.log {
+            text.push_str(&format!(
+                "\n**You:** {}\n\n**{}:** {}\n",
+                question, model, answer
+            ));
+        }
+
+        fs::write(&path, text)
+            .map_err(|e| Error::Output(format!("Failed to write {}: {}", path, e)))?;
+        Ok(path)
+    }
+}
+
+/// Where the transcript came from: the video's link, or the name of the
+/// local subtitle file
+fn source(transcript: &Transcript) -> String {
+    match (transcript.youtube_id(), transcript.file.as_deref()) {
+        (Some(video_id), _) => format!("https://www.youtube.com/watch?v={}", video_id),
+        (None, Some(file)) if file != "-" => file.to_string(),
+        (None, _) => transcript.video_id.clone(),
+    }
+}
+
+/// Lowercase words of at least four letters, which skips most filler words
+fn terms_list(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 4)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn terms(text: &str) -> HashSet<String> {
+    terms_list(text).into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_the_source() {
+        let cases = [
+            (
+                "dQw4w9WgXcQ",
+                None,
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            ),
+            ("talk", Some("talks/talk.srt"), "talks/talk.srt"),
+            ("stdin", Some("-"), "stdin"),
+        ];
+        for (video_id, file, expected) in cases {
+            let transcript = Transcript {
+                video_id: video_id.to_string(),
+                language: "en".to_string(),
+                snippets: Vec::new(),
+                file: file.map(str::to_string),
+            };
+            assert_eq!(source(&transcript), expected, "{:?}", file);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+/// Rough number of characters per token used when sizing chunks.
+/// Deliberately conservative so chunks stay below the real token count.
+pub const CHARS_PER_TOKEN: usize = 3;
+
+/// Context length assumed when the model's real context length is unknown
+pub const DEFAULT_CONTEXT_LENGTH: u64 = 32_768;
+
+/// Tokens kept free for message framing and the model's chat template
+const OVERHEAD_TOKENS: u64 = 512;
+
+/// Smallest chunk we are willing to send if the context has room for it,
+/// even at the expense of the tokens reserved for the completion
+const MIN_CHUNK_CHARS: usize = 2_000;
+
+/// Computes how many characters of input text fit into a single request,
+/// given the model's context length, the prompt sent alongside the text and
+/// the number of tokens reserved for the completion.
+///
+/// The budget is never below one character, so that packing always makes
+/// progress, and never above what the context holds besides the prompt.
+pub fn chunk_budget(context_length: u64, prompt: &str, max_tokens: u32) -> usize {
+    let prompt_tokens = estimate_tokens(prompt) as u64;
+    let fixed = prompt_tokens + OVERHEAD_TOKENS;
+
+    let available = context_length.saturating_sub(fixed + u64::from(max_tokens)) as usize;
+    let room = context_length.saturating_sub(fixed) as usize;
+    (available * CHARS_PER_TOKEN)
+        .max(MIN_CHUNK_CHARS.min(room * CHARS_PER_TOKEN))
+        .max(1)
+}
+
+/// Estimates the token count of a piece of text
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Packs text pieces into chunks of at most `max_chars` characters.
+///
+/// Pieces are never split unless a single piece is larger than `max_chars`
+/// on its own, in which case it is broken up on whitespace.
+pub fn pack<'a, I>(pieces: I, separator: &str, max_chars: usize) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for piece in pieces {
+        let piece = piece.trim();
+        if piece.is_empty() {
+            continue;
+        }
+
+        for part in split_oversized(piece, max_chars) {
+            let needed = if current.is_empty() {
+                part.chars().count()
+            } else {
+                current.chars().count() + separator.chars().count() + part.chars().count()
+            };
+
+            if needed > max_chars && !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+
+            if !current.is_empty() {
+                current.push_str(separator);
+            }
+            current.push_str(part);
+        }
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Splits a single piece that exceeds `max_chars` on whitespace boundaries.
+/// Words longer than `max_chars` are split on character boundaries.
+fn split_oversized(piece: &str, max_chars: usize) -> Vec<&str> {
+    if piece.chars().count() <= max_chars {
+        return vec![piece];
+    }
+
+    let mut parts = Vec::new();
+    let mut rest = piece;
+
+    while rest.chars().count() > max_chars {
+        // Byte offset of the character just past the limit
+        let limit = rest
+            .char_indices()
+            .nth(max_chars)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+
+        let cut = rest[..limit]
+            .rfind(char::is_whitespace)
+            .filter(|&i| i > 0)
+            .unwrap_or(limit);
+
+        parts.push(rest[..cut].trim_end());
+        rest = rest[cut..].trim_start();
+    }
+
+    if !rest.is_empty() {
+        parts.push(rest);
+    }
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_chunk_budgets() {
+        let prompt = "p".repeat(300);
+        let cases = [
+            // Room for the prompt, completion and overhead
+            (32_768, 4096, (32_768 - 100 - 4096 - 512) * 3),
+            (8192, 4096, (8192 - 100 - 4096 - 512) * 3),
+            // The completion leaves too little; the floor takes from it
+            (5000, 4096, MIN_CHUNK_CHARS),
+            // The floor itself doesn't fit and is clamped to the context
+            (1000, 4096, (1000 - 100 - 512) * 3),
+            // Not even the prompt fits
+            (600, 4096, 1),
+            (0, 0, 1),
+        ];
+        for (context_length, max_tokens, expected) in cases {
+            assert_eq!(
+                chunk_budget(context_length, &prompt, max_tokens),
+                expected,
+                "context {}",
+                context_length
+            );
+        }
+    }
+
+    #[test]
+    fn estimates_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("abcd"), 2);
+        assert_eq!(estimate_tokens("\u{e9}\u{e9}\u{e9}"), 1);
+    }
+
+    #[test]
+    fn packs_pieces_up_to_the_budget() {
+        let pieces = ["one", "two", " ", "three", "four five"];
+        assert_eq!(pack(pieces, " ", 9), ["one two", "three", "four five"]);
+        assert_eq!(pack(pieces, " ", 100), ["one two three four five"]);
+        assert_eq!(
+            pack(pieces, "\n\n", 10),
+            ["one\n\ntwo", "three", "four five"]
+        );
+        assert!(pack([" ", ""], " ", 10).is_empty());
+    }
+
+    #[test]
+    fn splits_oversized_pieces() {
+        assert_eq!(split_oversized("short", 10), ["short"]);
+        assert_eq!(
+            split_oversized("the quick brown fox jumps", 10),
+            ["the quick", "brown fox", "jumps"]
+        );
+        // Words longer than the budget are cut on character boundaries
+        assert_eq!(
+            split_oversized("abcdefghij\u{e9}\u{e9}", 4),
+            ["abcd", "efgh", "ij\u{e9}\u{e9}"]
+        );
+        assert_eq!(split_oversized("ab  cd", 1), ["a", "b", "c", "d"]);
+
+        // Packed chunks never exceed the budget, even for oversized pieces
+        let long = "word ".repeat(100);
+        let chunks = pack(["intro", long.as_str(), "end"], " ", 23);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 23));
+        assert_eq!(chunks.join(" ").split_whitespace().count(), 102);
+    }
+}
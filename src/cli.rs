@@ -1,136 +1,1225 @@
 use std::env;
+use std::ffi::OsString;
 
-use crate::openrouter::DEFAULT_MODEL;
+use clap::builder::{PossibleValuesParser, RangedU64ValueParser};
+use clap::{Args as ClapArgs, CommandFactory, Parser, Subcommand};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::env::{CompleteEnv, Shells};
 
-#[derive(Debug)]
+use crate::server::{DEFAULT_BIND, DEFAULT_TIMEOUT_SECS};
+use youtube_summary::cache::{Cache, CachePolicy};
+use youtube_summary::cost;
+use youtube_summary::openrouter::DEFAULT_MODEL;
+use youtube_summary::templates;
+
+/// Prefix of environment variables that override config file settings,
+/// e.g. `YOUTUBE_SUMMARY_DEFAULT_MODEL=openai/gpt-4o` for `default_model`
+pub const ENV_PREFIX: &str = "YOUTUBE_SUMMARY_";
+
+/// Variable the shell sets when asking the program for completions
+const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Shells `completions` can register completions for
+const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+/// First arguments that are handled by clap itself; anything else is
+/// taken as the start of a `summarize` command line
+const TOP_LEVEL: &[&str] = &[
+    "summarize",
+    "chat",
+    "models",
+    "transcript",
+    "config",
+    "cache",
+    "history",
+    "templates",
+    "serve",
+    "mcp",
+    "completions",
+    "man",
+    "help",
+    "-h",
+    "--help",
+    "-V",
+    "--version",
+];
+
+const AFTER_HELP: &str = r#"Without a subcommand, arguments are passed to `summarize`:
+  youtube-summary "https://youtube.com/watch?v=VIDEO_ID"
+
+Environment:
+  OPENROUTER_API_KEY        API key for OpenRouter
+  OPENAI_API_KEY            API key for OpenAI-compatible servers (optional)
+  YOUTUBE_SUMMARY_<KEY>     Overrides config file setting <key>, e.g.
+                            YOUTUBE_SUMMARY_DEFAULT_MODEL=openai/gpt-4o"#;
+
+const SUMMARIZE_EXAMPLES: &str = r#"Examples:
+  youtube-summary summarize "https://youtube.com/watch?v=VIDEO_ID"
+  youtube-summary summarize VIDEO_ID -m openai/gpt-4o -m anthropic/claude-haiku-4.5
+  youtube-summary summarize VIDEO_ID -P ollama -m llama3.1
+  youtube-summary summarize VIDEO_ID -P openai -u http://localhost:8080/v1 -m local
+  youtube-summary summarize VIDEO_ID --lang de,en -o English
+  youtube-summary summarize VIDEO_ID --format json | jq .usage
+  youtube-summary summarize --batch urls.txt -j 8 -d summaries/
+  cat urls.txt | youtube-summary summarize --batch - -d summaries/
+  youtube-summary summarize "https://youtube.com/playlist?list=PLAYLIST_ID" --overview
+  youtube-summary summarize "https://youtube.com/@channel" --since 30d --limit 10
+  youtube-summary summarize recording.vtt --lang de
+  cat captions.srt | youtube-summary summarize -
+  youtube-summary summarize -- -2BcDeFgHiJ           # IDs starting with a dash"#;
+
+const HISTORY_AFTER_HELP: &str = "Every summary is recorded in $XDG_DATA_HOME/youtube-summary/history.db \
+     (default: ~/.local/share/youtube-summary/history.db).";
+
+const EXPORT_EXAMPLES: &str = r#"Examples:
+  youtube-summary transcript export VIDEO_ID > transcript.txt
+  youtube-summary transcript export VIDEO_ID --format srt --output captions.srt
+  youtube-summary transcript export VIDEO_ID --format markdown --strip-markers
+  youtube-summary transcript export VIDEO_ID --format json --sentences | jq '.snippets[0]'"#;
+
+const SERVE_AFTER_HELP: &str = r#"Endpoints:
+  GET  /health                 Liveness check, without authentication
+  POST /summarize              {"url": ..., "model", "template", "prompt", "lang",
+                               "output_lang", "vars", "wait"}; answers 202 with a
+                               job, or with the finished job if "wait" is true
+  GET  /jobs/{id}              Status and result of a summary job
+  GET  /transcript/{id}        Transcript of a video; ?lang=, ?translate=, and
+                               ?format= text, srt, vtt, json or markdown
+  GET  /models                 The provider's models
+
+With --token, or serve_token in the config file, every request but /health
+must send "Authorization: Bearer TOKEN".
+
+Examples:
+  youtube-summary serve -P ollama -m llama3.1
+  curl -X POST localhost:8080/summarize -d '{"url": "VIDEO_ID", "wait": true}' \
+       -H 'Content-Type: application/json'"#;
+
+const MCP_AFTER_HELP: &str = r#"Speaks the Model Context Protocol as JSON-RPC on stdin and stdout, offering
+the tools get_transcript, summarize_video and list_models.
+
+Example client configuration:
+  {"mcpServers": {"youtube-summary": {
+      "command": "youtube-summary", "args": ["mcp", "-m", "openai/gpt-4o"]}}}"#;
+
+const CACHE_AFTER_HELP: &str =
+    "Entries are stored under $XDG_CACHE_HOME/youtube-summary (default: ~/.cache/youtube-summary).";
+
+/// The command line as declared to clap
+#[derive(Debug, Parser)]
+#[command(
+    name = "youtube-summary",
+    version,
+    about = "Summarize YouTube videos with LLMs",
+    after_help = AFTER_HELP
+)]
+pub struct Cli {
+    #[command(flatten)]
+    global: GlobalOptions,
+
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+/// Options accepted before or after any subcommand
+#[derive(Debug, ClapArgs)]
+struct GlobalOptions {
+    /// LLM provider: openrouter, ollama or openai [default: openrouter]
+    #[arg(short = 'P', long, global = true, value_name = "NAME")]
+    provider: Option<String>,
+
+    /// Provider base URL (required for openai)
+    #[arg(short = 'u', long, global = true, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// Provider API key (overrides env/config)
+    #[arg(short = 'k', long, global = true, value_name = "KEY")]
+    api_key: Option<String>,
+
+    /// Path to config file [default: ~/.config/youtube-summary/config]
+    #[arg(short = 'c', long = "config", global = true, value_name = "PATH")]
+    config_path: Option<String>,
+
+    /// Show verbose output
+    #[arg(short, long, global = true)]
+    verbose: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum CliCommand {
+    /// Summarize a video, playlist or channel, or a batch of them
+    #[command(after_help = SUMMARIZE_EXAMPLES)]
+    Summarize(Box<SummarizeArgs>),
+    /// Ask follow-up questions about a video
+    Chat(ChatArgs),
+    /// List the provider's models
+    Models {
+        /// Only list models whose ID or name contains TERM
+        #[arg(value_name = "TERM")]
+        search: Option<String>,
+    },
+    /// Inspect the transcripts of a video
+    #[command(subcommand)]
+    Transcript(TranscriptCommand),
+    /// Show the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Manage the transcript and summary cache
+    #[command(subcommand, after_help = CACHE_AFTER_HELP)]
+    Cache(CacheCommand),
+    /// Browse and search past summaries
+    #[command(subcommand, after_help = HISTORY_AFTER_HELP)]
+    History(HistoryCommand),
+    /// Manage prompt templates
+    #[command(subcommand)]
+    Templates(TemplatesCommand),
+    /// Serve summaries and transcripts over HTTP
+    #[command(after_help = SERVE_AFTER_HELP)]
+    Serve(Box<ServeArgs>),
+    /// Offer transcripts and summaries as tools to MCP clients over stdio
+    #[command(after_help = MCP_AFTER_HELP)]
+    Mcp(Box<McpArgs>),
+    /// Print a script that registers shell completions, e.g.
+    /// `source <(youtube-summary completions bash)`
+    #[command(hide = true)]
+    Completions {
+        #[arg(value_parser = PossibleValuesParser::new(SHELLS))]
+        shell: String,
+    },
+    /// Print the man page, or write one per subcommand into DIR
+    Man {
+        /// Write youtube-summary.1 and a page per subcommand into DIR
+        #[arg(short = 'd', long, value_name = "DIR")]
+        output_dir: Option<String>,
+    },
+}
+
+#[derive(Debug, ClapArgs)]
+struct SummarizeArgs {
+    /// YouTube video, playlist or channel URL, a video ID, or a subtitle file
+    /// (.srt, .vtt, .sbv, .json, .json3, .txt, or any file as ./PATH; - for stdin)
+    #[arg(required_unless_present = "batch", conflicts_with = "batch")]
+    url: Option<String>,
+
+    /// Custom prompt for the summary; may use template variables such as
+    /// {{title}}, and \{{ for literal braces
+    #[arg(short, long, conflicts_with = "template")]
+    prompt: Option<String>,
+
+    /// Prompt template to use [default: default]
+    #[arg(
+        short = 'T',
+        long,
+        value_name = "NAME",
+        add = ArgValueCandidates::new(template_candidates)
+    )]
+    template: Option<String>,
+
+    /// Sets a template variable; repeat for several
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = templates::parse_var)]
+    vars: Vec<(String, String)>,
+
+    #[command(flatten)]
+    model: ModelOptions,
+
+    /// Print the summary as it is generated
+    #[arg(short, long)]
+    stream: bool,
+
+    /// Link each key point to its position in the video
+    #[arg(short, long)]
+    timestamps: bool,
+
+    #[command(flatten)]
+    transcript: TranscriptOptions,
+
+    /// Language to write the summary in
+    #[arg(short = 'o', long = "output-lang", value_name = "LANG")]
+    output_language: Option<String>,
+
+    /// Summarize chapter by chapter: auto, off or propose [default: auto]
+    #[arg(long, value_name = "MODE")]
+    chapters: Option<String>,
+
+    /// Output format: text or json [default: text]
+    #[arg(short, long)]
+    format: Option<String>,
+
+    /// Don't send requests projected to cost more than USD
+    #[arg(long, value_name = "USD", value_parser = cost::parse_max_cost)]
+    max_cost: Option<f64>,
+
+    /// Summarize every URL or video ID in FILE (- for stdin)
+    #[arg(short, long, value_name = "FILE")]
+    batch: Option<String>,
+
+    /// Videos processed at once in batch mode [default: 4]
+    #[arg(short, long, value_name = "N", value_parser = positive())]
+    jobs: Option<usize>,
+
+    /// Directory for batch, playlist and channel summaries [default: .]
+    #[arg(short = 'd', long, value_name = "DIR")]
+    output_dir: Option<String>,
+
+    /// Videos taken from each playlist or channel [channel default: 50]
+    #[arg(long, value_name = "N", value_parser = positive())]
+    limit: Option<usize>,
+
+    /// Only channel videos since DATE (YYYY-MM-DD) or within an age, e.g. 30d
+    #[arg(long, value_name = "DATE")]
+    since: Option<String>,
+
+    /// Also write an overview of each playlist or channel
+    #[arg(long)]
+    overview: bool,
+
+    /// Don't record the summary in the history
+    #[arg(long)]
+    no_history: bool,
+
+    #[command(flatten)]
+    cache: CacheOptions,
+}
+
+#[derive(Debug, ClapArgs)]
+struct ChatArgs {
+    /// YouTube video URL or ID, or a subtitle file
+    url: String,
+
+    #[command(flatten)]
+    model: ModelOptions,
+
+    #[command(flatten)]
+    transcript: TranscriptOptions,
+
+    /// Language to answer in
+    #[arg(short = 'o', long = "output-lang", value_name = "LANG")]
+    output_language: Option<String>,
+
+    /// Don't send questions once the chat is projected to cost more than USD
+    #[arg(long, value_name = "USD", value_parser = cost::parse_max_cost)]
+    max_cost: Option<f64>,
+
+    #[command(flatten)]
+    cache: CacheOptions,
+}
+
+#[derive(Debug, ClapArgs)]
+struct ServeArgs {
+    #[command(flatten)]
+    serve: ServeOptions,
+
+    /// Token clients must send as "Authorization: Bearer TOKEN"
+    #[arg(long, value_name = "TOKEN")]
+    token: Option<String>,
+
+    /// Default prompt template [default: default]
+    #[arg(
+        short = 'T',
+        long,
+        value_name = "NAME",
+        add = ArgValueCandidates::new(template_candidates)
+    )]
+    template: Option<String>,
+
+    /// Sets a template variable; repeat for several
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = templates::parse_var)]
+    vars: Vec<(String, String)>,
+
+    #[command(flatten)]
+    model: ModelOptions,
+
+    #[command(flatten)]
+    transcript: TranscriptOptions,
+
+    /// Default language to write summaries in
+    #[arg(short = 'o', long = "output-lang", value_name = "LANG")]
+    output_language: Option<String>,
+
+    /// Summarize chapter by chapter: auto, off or propose [default: auto]
+    #[arg(long, value_name = "MODE")]
+    chapters: Option<String>,
+
+    /// Summaries generated at once; further jobs wait [default: 4]
+    #[arg(short, long, value_name = "N", value_parser = positive())]
+    jobs: Option<usize>,
+
+    /// Don't send requests projected to cost more than USD
+    #[arg(long, value_name = "USD", value_parser = cost::parse_max_cost)]
+    max_cost: Option<f64>,
+
+    /// Don't record summaries in the history
+    #[arg(long)]
+    no_history: bool,
+
+    #[command(flatten)]
+    cache: CacheOptions,
+}
+
+#[derive(Debug, ClapArgs)]
+struct McpArgs {
+    /// Default prompt template [default: default]
+    #[arg(
+        short = 'T',
+        long,
+        value_name = "NAME",
+        add = ArgValueCandidates::new(template_candidates)
+    )]
+    template: Option<String>,
+
+    /// Sets a template variable; repeat for several
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = templates::parse_var)]
+    vars: Vec<(String, String)>,
+
+    #[command(flatten)]
+    model: ModelOptions,
+
+    #[command(flatten)]
+    transcript: TranscriptOptions,
+
+    /// Default language to write summaries in
+    #[arg(short = 'o', long = "output-lang", value_name = "LANG")]
+    output_language: Option<String>,
+
+    /// Link each key point to its position in the video
+    #[arg(short, long)]
+    timestamps: bool,
+
+    /// Summarize chapter by chapter: auto, off or propose [default: auto]
+    #[arg(long, value_name = "MODE")]
+    chapters: Option<String>,
+
+    /// Don't send requests projected to cost more than USD
+    #[arg(long, value_name = "USD", value_parser = cost::parse_max_cost)]
+    max_cost: Option<f64>,
+
+    /// Don't record summaries in the history
+    #[arg(long)]
+    no_history: bool,
+
+    #[command(flatten)]
+    cache: CacheOptions,
+}
+
+/// Where and how `serve` listens
+#[derive(Debug, Clone, ClapArgs)]
+pub struct ServeOptions {
+    /// Address to listen on
+    #[arg(long, value_name = "ADDR", default_value = DEFAULT_BIND)]
+    pub bind: String,
+
+    /// Seconds a summary may take before its job fails
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = DEFAULT_TIMEOUT_SECS,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub timeout: u64,
+}
+
+#[derive(Debug, ClapArgs)]
+struct ModelOptions {
+    #[arg(
+        short,
+        long = "model",
+        value_name = "MODEL",
+        add = ArgValueCandidates::new(model_candidates),
+        help = format!(
+            "Model ID [default for OpenRouter: {}]; repeat to give fallback models, \
+             tried in order when a model is rate-limited or unavailable",
+            DEFAULT_MODEL
+        )
+    )]
+    models: Vec<String>,
+}
+
+#[derive(Debug, ClapArgs)]
+struct TranscriptOptions {
+    /// Preferred transcript languages, e.g. "de,en" [default: en]
+    #[arg(long = "lang", value_name = "CODES")]
+    languages: Option<String>,
+
+    /// Use YouTube's translation of the transcript into CODE
+    #[arg(long, value_name = "CODE")]
+    translate: Option<String>,
+}
+
+#[derive(Debug, ClapArgs)]
+struct CacheOptions {
+    /// Don't read or write the transcript/summary cache
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Ignore cached entries and store fresh results
+    #[arg(long, conflicts_with = "no_cache")]
+    refresh: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum TranscriptCommand {
+    /// List the transcript tracks available for a video
+    List {
+        /// YouTube video URL or ID
+        url: String,
+    },
+    /// Write the transcript of a video without summarizing it
+    #[command(after_help = EXPORT_EXAMPLES)]
+    Export(ExportArgs),
+}
+
+#[derive(Debug, ClapArgs)]
+struct ExportArgs {
+    /// YouTube video URL or ID, or a subtitle file (- for stdin)
+    url: String,
+
+    #[command(flatten)]
+    export: ExportOptions,
+
+    #[command(flatten)]
+    transcript: TranscriptOptions,
+
+    #[command(flatten)]
+    cache: CacheOptions,
+}
+
+/// How `transcript export` writes a transcript
+#[derive(Debug, Clone, Default, ClapArgs)]
+pub struct ExportOptions {
+    /// Format: text, srt, vtt, json or markdown [default: text]
+    #[arg(short, long)]
+    pub format: Option<String>,
+
+    /// Write to FILE instead of stdout
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<String>,
+
+    /// Remove sound markers such as [Music] and [Applause]
+    #[arg(long)]
+    pub strip_markers: bool,
+
+    /// Merge caption snippets into whole sentences
+    #[arg(long)]
+    pub sentences: bool,
+}
+
+/// Actions of the `config` subcommand
+#[derive(Debug, Clone, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective settings after the config file, environment
+    /// and command line are applied
+    Show,
+    /// Print the path of the config file
+    Path,
+}
+
+/// Actions of the `templates` subcommand
+#[derive(Debug, Clone, Subcommand)]
+pub enum TemplatesCommand {
+    /// List built-in and user templates
+    List,
+    /// Print a template
+    Show {
+        #[arg(add = ArgValueCandidates::new(template_candidates))]
+        name: String,
+    },
+    /// Open a template in $VISUAL or $EDITOR; editing a built-in template
+    /// creates a user copy that overrides it
+    Edit {
+        #[arg(add = ArgValueCandidates::new(template_candidates))]
+        name: String,
+    },
+    /// Create a user template
+    New {
+        name: String,
+        /// Template to start from [default: default]
+        #[arg(
+            long,
+            value_name = "TEMPLATE",
+            add = ArgValueCandidates::new(template_candidates)
+        )]
+        from: Option<String>,
+    },
+}
+
+/// Actions of the `cache` subcommand
+#[derive(Debug, Clone, Subcommand)]
+pub enum CacheCommand {
+    /// List cached transcripts and summaries
+    List,
+    /// Show a cached entry
+    Show {
+        /// Entry key as shown by `cache list`
+        key: String,
+    },
+    /// Remove old entries
+    #[command(group(
+        clap::ArgGroup::new("limits")
+            .args(["older_than", "max_size"])
+            .required(true)
+            .multiple(true)
+    ))]
+    Prune {
+        /// Remove entries older than AGE, e.g. 30m, 12h, 7d
+        #[arg(long, value_name = "AGE")]
+        older_than: Option<String>,
+        /// Remove oldest entries until the cache fits SIZE, e.g. 500K, 100M
+        #[arg(long, value_name = "SIZE")]
+        max_size: Option<String>,
+    },
+    /// Remove all entries
+    Clear,
+}
+
+/// Actions of the `history` subcommand
+#[derive(Debug, Clone, Subcommand)]
+pub enum HistoryCommand {
+    /// List past summaries, most recent first
+    List {
+        /// Only list summaries of this video (URL or ID)
+        #[arg(long, value_name = "VIDEO")]
+        video: Option<String>,
+        /// Number of summaries to list
+        #[arg(short = 'n', long, value_name = "N", default_value_t = 20)]
+        limit: usize,
+    },
+    /// Show a past summary
+    Show {
+        /// History ID as shown by `history list`, or a video URL or ID for
+        /// its latest summary
+        id: String,
+        /// Show the changes against the previous summary of the same video
+        #[arg(long)]
+        diff: bool,
+    },
+    /// Search titles, summaries and transcripts
+    Search {
+        /// Words that must all occur
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+        /// Number of results to show
+        #[arg(short = 'n', long, value_name = "N", default_value_t = 20)]
+        limit: usize,
+    },
+    /// Write all past summaries to stdout or a file
+    Export {
+        /// Format: json or markdown [default: json]
+        #[arg(short, long)]
+        format: Option<String>,
+        /// Write to FILE instead of stdout
+        #[arg(long, value_name = "FILE")]
+        output: Option<String>,
+    },
+}
+
+/// What the program was asked to do
+#[derive(Debug, Clone, Default)]
+pub enum Command {
+    #[default]
+    Summarize,
+    Chat,
+    /// List models, optionally filtered by a search term
+    Models(Option<String>),
+    ListTranscripts,
+    /// Write a transcript without summarizing it
+    ExportTranscript(ExportOptions),
+    Config(ConfigCommand),
+    Cache(CacheCommand),
+    History(HistoryCommand),
+    Templates(TemplatesCommand),
+    /// Serve the HTTP API
+    Serve(ServeOptions),
+    /// Answer MCP requests on stdin
+    Mcp,
+    /// Print the completion registration script for a shell
+    Completions(String),
+    /// Print the man page, or write all pages into a directory
+    Man(Option<String>),
+}
+
+/// The parsed command line, flattened into the settings `Config` is
+/// loaded from
+#[derive(Debug, Default)]
 pub struct Args {
+    pub command: Command,
     pub url: Option<String>,
     pub prompt: Option<String>,
-    pub model: Option<String>,
+    pub template: Option<String>,
+    /// Template variables from `--var KEY=VALUE`
+    pub vars: Vec<(String, String)>,
+    /// Repeated `--model` flags; the first is the primary model
+    pub models: Vec<String>,
+    pub provider: Option<String>,
+    pub base_url: Option<String>,
     pub api_key: Option<String>,
     pub config_path: Option<String>,
     pub verbose: bool,
-    pub list_models: Option<Option<String>>,
+    pub stream: bool,
+    pub timestamps: bool,
+    pub languages: Option<String>,
+    pub translate: Option<String>,
+    pub output_language: Option<String>,
+    pub chapters: Option<String>,
+    pub batch: Option<String>,
+    pub jobs: Option<usize>,
+    pub output_dir: Option<String>,
+    pub limit: Option<usize>,
+    pub since: Option<String>,
+    pub overview: bool,
+    pub no_history: bool,
+    pub format: Option<String>,
+    pub max_cost: Option<f64>,
+    pub no_cache: bool,
+    pub refresh: bool,
+    /// Bearer token required by `serve`
+    pub serve_token: Option<String>,
+    /// Config file settings given as `YOUTUBE_SUMMARY_<KEY>=value`, with
+    /// the key lowercased
+    pub env_overrides: Vec<(String, String)>,
 }
 
 impl Args {
-    pub fn parse() -> Result<Self, String> {
-        let args: Vec<String> = env::args().collect();
+    /// Parses the process arguments and environment. Help, the version and
+    /// usage errors are printed and exit with clap's status codes.
+    pub fn parse() -> Self {
+        Self::parse_from(env::args_os(), env::vars()).unwrap_or_else(|e| e.exit())
+    }
 
-        if args.len() < 2 {
-            return Err(Self::usage());
-        }
+    /// Parses `args`, including the program name, with `vars` standing in
+    /// for the environment
+    pub fn parse_from<I, T, V>(args: I, vars: V) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+        V: IntoIterator<Item = (String, String)>,
+    {
+        let mut args: Vec<OsString> = args.into_iter().map(Into::into).collect();
 
-        // Check for help flag
-        if args.iter().any(|a| a == "--help" || a == "-h") {
-            return Err(Self::usage());
+        // Global options may come before the subcommand, so the first
+        // argument after them decides whether `summarize` is implied
+        let first = first_command_arg(&args);
+        if args
+            .get(first)
+            .is_some_and(|arg| !arg.to_str().is_some_and(|arg| TOP_LEVEL.contains(&arg)))
+        {
+            args.insert(first, "summarize".into());
         }
 
-        let mut url = None;
-        let mut prompt = None;
-        let mut model = None;
-        let mut api_key = None;
-        let mut config_path = None;
-        let mut verbose = false;
-        let mut list_models: Option<Option<String>> = None;
-
-        let mut i = 1;
-        while i < args.len() {
-            let arg = &args[i];
-
-            match arg.as_str() {
-                "-p" | "--prompt" => {
-                    i += 1;
-                    if i >= args.len() {
-                        return Err("--prompt requires a value".to_string());
-                    }
-                    prompt = Some(args[i].clone());
-                }
-                "-m" | "--model" => {
-                    i += 1;
-                    if i >= args.len() {
-                        return Err("--model requires a value".to_string());
-                    }
-                    model = Some(args[i].clone());
-                }
-                "-k" | "--api-key" => {
-                    i += 1;
-                    if i >= args.len() {
-                        return Err("--api-key requires a value".to_string());
-                    }
-                    api_key = Some(args[i].clone());
-                }
-                "-c" | "--config" => {
-                    i += 1;
-                    if i >= args.len() {
-                        return Err("--config requires a path".to_string());
-                    }
-                    config_path = Some(args[i].clone());
-                }
-                "-v" | "--verbose" => {
-                    verbose = true;
-                }
-                "-l" | "--list-models" => {
-                    // Check if next arg is a search term (not starting with -)
-                    if i + 1 < args.len() && !args[i + 1].starts_with('-') {
-                        i += 1;
-                        list_models = Some(Some(args[i].clone()));
-                    } else {
-                        list_models = Some(None);
-                    }
-                }
-                _ if !arg.starts_with('-') && url.is_none() => {
-                    url = Some(arg.clone());
-                }
-                _ => {
-                    return Err(format!("Unknown argument: {}", arg));
-                }
+        let cli = Cli::try_parse_from(args)?;
+
+        let env_overrides = vars
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let key = key.strip_prefix(ENV_PREFIX)?.to_lowercase();
+                Some((key, value))
+            })
+            .collect();
+
+        let mut parsed = Args {
+            provider: cli.global.provider,
+            base_url: cli.global.base_url,
+            api_key: cli.global.api_key,
+            config_path: cli.global.config_path,
+            verbose: cli.global.verbose,
+            env_overrides,
+            ..Default::default()
+        };
+
+        match cli.command {
+            CliCommand::Summarize(summarize) => {
+                let summarize = *summarize;
+                parsed.command = Command::Summarize;
+                parsed.url = summarize.url;
+                parsed.prompt = summarize.prompt;
+                parsed.template = summarize.template;
+                parsed.vars = summarize.vars;
+                parsed.models = summarize.model.models;
+                parsed.stream = summarize.stream;
+                parsed.timestamps = summarize.timestamps;
+                parsed.languages = summarize.transcript.languages;
+                parsed.translate = summarize.transcript.translate;
+                parsed.output_language = summarize.output_language;
+                parsed.chapters = summarize.chapters;
+                parsed.format = summarize.format;
+                parsed.max_cost = summarize.max_cost;
+                parsed.batch = summarize.batch;
+                parsed.jobs = summarize.jobs;
+                parsed.output_dir = summarize.output_dir;
+                parsed.limit = summarize.limit;
+                parsed.since = summarize.since;
+                parsed.overview = summarize.overview;
+                parsed.no_history = summarize.no_history;
+                parsed.no_cache = summarize.cache.no_cache;
+                parsed.refresh = summarize.cache.refresh;
             }
-            i += 1;
+            CliCommand::Chat(chat) => {
+                parsed.command = Command::Chat;
+                parsed.url = Some(chat.url);
+                parsed.models = chat.model.models;
+                parsed.languages = chat.transcript.languages;
+                parsed.translate = chat.transcript.translate;
+                parsed.output_language = chat.output_language;
+                parsed.max_cost = chat.max_cost;
+                parsed.no_cache = chat.cache.no_cache;
+                parsed.refresh = chat.cache.refresh;
+            }
+            CliCommand::Models { search } => parsed.command = Command::Models(search),
+            CliCommand::Transcript(TranscriptCommand::List { url }) => {
+                parsed.command = Command::ListTranscripts;
+                parsed.url = Some(url);
+            }
+            CliCommand::Transcript(TranscriptCommand::Export(export)) => {
+                parsed.command = Command::ExportTranscript(export.export);
+                parsed.url = Some(export.url);
+                parsed.languages = export.transcript.languages;
+                parsed.translate = export.transcript.translate;
+                parsed.no_cache = export.cache.no_cache;
+                parsed.refresh = export.cache.refresh;
+            }
+            CliCommand::Config(action) => parsed.command = Command::Config(action),
+            CliCommand::Cache(action) => parsed.command = Command::Cache(action),
+            CliCommand::History(action) => parsed.command = Command::History(action),
+            CliCommand::Templates(action) => parsed.command = Command::Templates(action),
+            CliCommand::Serve(serve) => {
+                let serve = *serve;
+                parsed.command = Command::Serve(serve.serve);
+                parsed.serve_token = serve.token;
+                parsed.template = serve.template;
+                parsed.vars = serve.vars;
+                parsed.models = serve.model.models;
+                parsed.languages = serve.transcript.languages;
+                parsed.translate = serve.transcript.translate;
+                parsed.output_language = serve.output_language;
+                parsed.chapters = serve.chapters;
+                parsed.jobs = serve.jobs;
+                parsed.max_cost = serve.max_cost;
+                parsed.no_history = serve.no_history;
+                parsed.no_cache = serve.cache.no_cache;
+                parsed.refresh = serve.cache.refresh;
+            }
+            CliCommand::Mcp(mcp) => {
+                let mcp = *mcp;
+                parsed.command = Command::Mcp;
+                parsed.template = mcp.template;
+                parsed.vars = mcp.vars;
+                parsed.models = mcp.model.models;
+                parsed.languages = mcp.transcript.languages;
+                parsed.translate = mcp.transcript.translate;
+                parsed.output_language = mcp.output_language;
+                parsed.timestamps = mcp.timestamps;
+                parsed.chapters = mcp.chapters;
+                parsed.max_cost = mcp.max_cost;
+                parsed.no_history = mcp.no_history;
+                parsed.no_cache = mcp.cache.no_cache;
+                parsed.refresh = mcp.cache.refresh;
+            }
+            CliCommand::Completions { shell } => parsed.command = Command::Completions(shell),
+            CliCommand::Man { output_dir } => parsed.command = Command::Man(output_dir),
         }
 
-        // URL is required unless --list-models is specified
-        if list_models.is_none() && url.is_none() {
-            return Err("YouTube URL is required".to_string());
+        Ok(parsed)
+    }
+}
+
+/// Index of the first argument that isn't a global option or its value
+fn first_command_arg(args: &[OsString]) -> usize {
+    let cli = Cli::command();
+    let globals: Vec<&clap::Arg> = cli
+        .get_arguments()
+        .filter(|arg| arg.is_global_set())
+        .collect();
+
+    let mut index = 1;
+    while let Some(arg) = args.get(index).and_then(|arg| arg.to_str()) {
+        match global_option(&globals, arg) {
+            Some(true) => index += 2,
+            Some(false) => index += 1,
+            None => break,
         }
+    }
+    index
+}
+
+/// Whether `arg` is a global option whose value is the next argument, or
+/// `None` if it isn't a global option at all
+fn global_option(globals: &[&clap::Arg], arg: &str) -> Option<bool> {
+    let takes_value = |option: &clap::Arg| option.get_action().takes_values();
 
-        Ok(Args {
-            url,
-            prompt,
-            model,
-            api_key,
-            config_path,
-            verbose,
-            list_models,
+    if let Some(long) = arg.strip_prefix("--") {
+        let (name, value) = match long.split_once('=') {
+            Some((name, _)) => (name, true),
+            None => (long, false),
+        };
+        let option = globals
+            .iter()
+            .find(|option| option.get_long() == Some(name))?;
+        return Some(!value && takes_value(option));
+    }
+
+    // Short flags can be combined, as in `-vP ollama` or `-Pollama`
+    let mut shorts = arg.strip_prefix('-')?.chars();
+    let mut any = false;
+    while let Some(short) = shorts.next() {
+        let option = globals
+            .iter()
+            .find(|option| option.get_short() == Some(short))?;
+        any = true;
+        if takes_value(option) {
+            return Some(shorts.as_str().is_empty());
+        }
+    }
+    any.then_some(false)
+}
+
+/// The clap definition of the command line, used for help, completions and
+/// man pages
+pub fn command() -> clap::Command {
+    Cli::command()
+}
+
+/// Answers a completion request from the shell and exits, if the program
+/// was started by a registered completion script
+pub fn complete() {
+    CompleteEnv::with_factory(command)
+        .var(COMPLETE_VAR)
+        .complete();
+}
+
+/// Writes the script registering completions for `shell`. The script calls
+/// back into the installed binary, so completions follow its version and
+/// include dynamic values such as cached model IDs.
+pub fn write_completions(shell: &str, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(shell)
+        .ok_or_else(|| std::io::Error::other(format!("Unsupported shell: {}", shell)))?;
+    let name = env!("CARGO_PKG_NAME");
+    completer.write_registration(COMPLETE_VAR, name, name, name, out)
+}
+
+/// Parser of counts that must be at least one
+fn positive() -> RangedU64ValueParser<usize> {
+    RangedU64ValueParser::new().range(1..)
+}
+
+/// Model IDs from the cached model lists of all providers. Stale lists are
+/// used too, since completion must not wait for the network.
+fn model_candidates() -> Vec<CompletionCandidate> {
+    Cache::new(CachePolicy::Use)
+        .cached_models()
+        .into_iter()
+        .map(|model| {
+            let help = (model.name != model.id).then(|| model.name.into());
+            CompletionCandidate::new(model.id).help(help)
+        })
+        .collect()
+}
+
+/// Names of the built-in and user prompt templates
+fn template_candidates() -> Vec<CompletionCandidate> {
+    templates::list()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|template| {
+            let help = Some(template.description().into());
+            CompletionCandidate::new(template.name).help(help)
         })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        let args = std::iter::once("youtube-summary").chain(args.iter().copied());
+        Args::parse_from(args, Vec::new())
     }
 
-    fn usage() -> String {
-        format!(
-            r#"Usage: youtube-summary [OPTIONS] [URL]
+    #[test]
+    fn global_options_before_a_subcommand() {
+        let args = parse(&["-v", "models"]).unwrap();
+        assert!(matches!(args.command, Command::Models(None)));
+        assert!(args.verbose);
 
-Arguments:
-  [URL]                     YouTube video URL (required unless --list-models)
+        let args = parse(&["-v", "history", "list"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Command::History(HistoryCommand::List { limit: 20, .. })
+        ));
 
-Options:
-  -p, --prompt <PROMPT>     Custom prompt for the summary
-  -m, --model <MODEL>       OpenRouter model ID (default: {})
-  -k, --api-key <KEY>       OpenRouter API key (overrides env/config)
-  -c, --config <PATH>       Path to config file
-  -l, --list-models [TERM]  List available models (optionally filter by TERM)
-  -v, --verbose             Show verbose output
-  -h, --help                Show this help message
+        let args = parse(&["-P", "ollama", "-u", "http://localhost:11434", "models"]).unwrap();
+        assert!(matches!(args.command, Command::Models(None)));
+        assert_eq!(args.provider.as_deref(), Some("ollama"));
+        assert_eq!(args.base_url.as_deref(), Some("http://localhost:11434"));
+    }
 
-Environment:
-  OPENROUTER_API_KEY        API key for OpenRouter
+    #[test]
+    fn summarize_is_the_default_subcommand() {
+        for argv in [
+            &["dQw4w9WgXcQ"][..],
+            &["summarize", "dQw4w9WgXcQ"],
+            &["-v", "dQw4w9WgXcQ"],
+            &["-vP", "ollama", "dQw4w9WgXcQ"],
+            &["-Pollama", "dQw4w9WgXcQ"],
+            &["--provider=ollama", "--config", "x.conf", "dQw4w9WgXcQ"],
+            &["-k", "models", "dQw4w9WgXcQ"],
+        ] {
+            let args = parse(argv).unwrap_or_else(|e| panic!("{:?}: {}", argv, e));
+            assert!(matches!(args.command, Command::Summarize), "{:?}", argv);
+            assert_eq!(args.url.as_deref(), Some("dQw4w9WgXcQ"), "{:?}", argv);
+        }
 
-Examples:
-  youtube-summary "https://youtube.com/watch?v=VIDEO_ID"
-  youtube-summary "https://youtube.com/watch?v=VIDEO_ID" -m anthropic/claude-sonnet-4
-  youtube-summary --list-models                    # List all models
-  youtube-summary --list-models claude             # List models matching "claude"
-  youtube-summary -l gpt -v                        # List GPT models with verbose output"#,
-            DEFAULT_MODEL
-        )
+        let args = parse(&["--", "-2BcDeFgHiJ"]).unwrap();
+        assert_eq!(args.url.as_deref(), Some("-2BcDeFgHiJ"));
+        let args = parse(&["-"]).unwrap();
+        assert_eq!(args.url.as_deref(), Some("-"));
+    }
+
+    #[test]
+    fn global_options_after_a_subcommand() {
+        let args = parse(&["models", "-k", "KEY", "--verbose", "gpt"]).unwrap();
+        assert!(matches!(args.command, Command::Models(Some(ref term)) if term == "gpt"));
+        assert_eq!(args.api_key.as_deref(), Some("KEY"));
+        assert!(args.verbose);
+
+        let args = parse(&["dQw4w9WgXcQ", "-c", "x.conf"]).unwrap();
+        assert_eq!(args.config_path.as_deref(), Some("x.conf"));
+    }
+
+    #[test]
+    fn summarize_options() {
+        let args = parse(&[
+            "dQw4w9WgXcQ",
+            "-m",
+            "a",
+            "-m",
+            "b",
+            "-T",
+            "tldr",
+            "--var",
+            "k=v",
+            "--lang",
+            "de,en",
+            "--translate",
+            "fr",
+            "-o",
+            "English",
+            "--chapters",
+            "off",
+            "-f",
+            "json",
+            "--max-cost",
+            "$0.05",
+            "--limit",
+            "3",
+            "--since",
+            "30d",
+            "-d",
+            "out",
+            "--overview",
+            "--no-history",
+            "--refresh",
+            "-s",
+            "-t",
+        ])
+        .unwrap();
+        assert_eq!(args.models, ["a", "b"]);
+        assert_eq!(args.template.as_deref(), Some("tldr"));
+        assert_eq!(args.vars, [("k".to_string(), "v".to_string())]);
+        assert_eq!(args.languages.as_deref(), Some("de,en"));
+        assert_eq!(args.translate.as_deref(), Some("fr"));
+        assert_eq!(args.output_language.as_deref(), Some("English"));
+        assert_eq!(args.chapters.as_deref(), Some("off"));
+        assert_eq!(args.format.as_deref(), Some("json"));
+        assert_eq!(args.max_cost, Some(0.05));
+        assert_eq!(args.limit, Some(3));
+        assert_eq!(args.since.as_deref(), Some("30d"));
+        assert_eq!(args.output_dir.as_deref(), Some("out"));
+        assert!(args.overview && args.no_history && args.refresh);
+        assert!(args.stream && args.timestamps);
+
+        let args = parse(&["--batch", "urls.txt", "-j", "8"]).unwrap();
+        assert_eq!(args.batch.as_deref(), Some("urls.txt"));
+        assert_eq!(args.jobs, Some(8));
+        assert_eq!(args.url, None);
+    }
+
+    #[test]
+    fn rejects_invalid_numbers() {
+        for argv in [
+            &["dQw4w9WgXcQ", "--jobs", "0"][..],
+            &["dQw4w9WgXcQ", "--jobs=-1"],
+            &["dQw4w9WgXcQ", "--jobs", "many"],
+            &["dQw4w9WgXcQ", "--limit", "0"],
+            &["dQw4w9WgXcQ", "--limit", "1.5"],
+            &["dQw4w9WgXcQ", "--max-cost=-1"],
+            &["dQw4w9WgXcQ", "--max-cost", "NaN"],
+            &["dQw4w9WgXcQ", "--max-cost", "inf"],
+            &["dQw4w9WgXcQ", "--max-cost", "cheap"],
+            &["serve", "--jobs", "0"],
+            &["serve", "--timeout", "0"],
+            &["mcp", "--max-cost", "x"],
+            &["history", "list", "-n", "x"],
+        ] {
+            let error = parse(argv).expect_err(&format!("{:?} should fail", argv));
+            assert_eq!(
+                error.kind(),
+                clap::error::ErrorKind::ValueValidation,
+                "{:?}",
+                argv
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_usage_errors() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["summarize"]).is_err());
+        assert!(parse(&["dQw4w9WgXcQ", "--batch", "urls.txt"]).is_err());
+        assert!(parse(&["dQw4w9WgXcQ", "-p", "x", "-T", "tldr"]).is_err());
+        assert!(parse(&["dQw4w9WgXcQ", "--no-cache", "--refresh"]).is_err());
+        assert!(parse(&["dQw4w9WgXcQ", "--var", "novalue"]).is_err());
+        assert!(parse(&["cache", "prune"]).is_err());
+        assert!(parse(&["history", "search"]).is_err());
+        assert!(parse(&["completions", "powershell"]).is_err());
+        assert_eq!(
+            parse(&["-v", "--help"]).unwrap_err().kind(),
+            clap::error::ErrorKind::DisplayHelp
+        );
+    }
+
+    #[test]
+    fn every_subcommand() {
+        let args = parse(&[
+            "chat",
+            "dQw4w9WgXcQ",
+            "-m",
+            "m",
+            "--lang",
+            "de",
+            "--max-cost",
+            "$0.10",
+        ])
+        .unwrap();
+        assert!(matches!(args.command, Command::Chat));
+        assert_eq!(args.url.as_deref(), Some("dQw4w9WgXcQ"));
+        assert_eq!(args.languages.as_deref(), Some("de"));
+        assert_eq!(args.max_cost, Some(0.1));
+
+        let args = parse(&["transcript", "list", "dQw4w9WgXcQ"]).unwrap();
+        assert!(matches!(args.command, Command::ListTranscripts));
+
+        let args = parse(&[
+            "transcript",
+            "export",
+            "dQw4w9WgXcQ",
+            "-f",
+            "srt",
+            "--output",
+            "a.srt",
+            "--sentences",
+        ])
+        .unwrap();
+        let Command::ExportTranscript(export) = args.command else {
+            panic!("expected export");
+        };
+        assert_eq!(export.format.as_deref(), Some("srt"));
+        assert_eq!(export.output.as_deref(), Some("a.srt"));
+        assert!(export.sentences && !export.strip_markers);
+
+        assert!(matches!(
+            parse(&["config", "show"]).unwrap().command,
+            Command::Config(ConfigCommand::Show)
+        ));
+        assert!(matches!(
+            parse(&["config", "path"]).unwrap().command,
+            Command::Config(ConfigCommand::Path)
+        ));
+        assert!(matches!(
+            parse(&["cache", "list"]).unwrap().command,
+            Command::Cache(CacheCommand::List)
+        ));
+        assert!(matches!(
+            parse(&["cache", "show", "key"]).unwrap().command,
+            Command::Cache(CacheCommand::Show { ref key }) if key == "key"
+        ));
+        assert!(matches!(
+            parse(&["cache", "prune", "--older-than", "7d"])
+                .unwrap()
+                .command,
+            Command::Cache(CacheCommand::Prune {
+                older_than: Some(_),
+                max_size: None
+            })
+        ));
+        assert!(matches!(
+            parse(&["cache", "clear"]).unwrap().command,
+            Command::Cache(CacheCommand::Clear)
+        ));
+        assert!(matches!(
+            parse(&["history", "show", "3", "--diff"]).unwrap().command,
+            Command::History(HistoryCommand::Show { diff: true, .. })
+        ));
+        assert!(matches!(
+            parse(&["history", "search", "rust", "async", "-n", "5"]).unwrap().command,
+            Command::History(HistoryCommand::Search { ref query, limit: 5 }) if query.len() == 2
+        ));
+        assert!(matches!(
+            parse(&["history", "export", "-f", "markdown"])
+                .unwrap()
+                .command,
+            Command::History(HistoryCommand::Export { .. })
+        ));
+        assert!(matches!(
+            parse(&["templates", "list"]).unwrap().command,
+            Command::Templates(TemplatesCommand::List)
+        ));
+        assert!(matches!(
+            parse(&["templates", "new", "mine", "--from", "tldr"])
+                .unwrap()
+                .command,
+            Command::Templates(TemplatesCommand::New { from: Some(_), .. })
+        ));
+
+        let args = parse(&["serve", "--bind", "0.0.0.0:9000", "--token", "t", "-j", "2"]).unwrap();
+        let Command::Serve(ref serve) = args.command else {
+            panic!("expected serve");
+        };
+        assert_eq!(serve.bind, "0.0.0.0:9000");
+        assert_eq!(serve.timeout, DEFAULT_TIMEOUT_SECS);
+        assert_eq!(args.serve_token.as_deref(), Some("t"));
+        assert_eq!(args.jobs, Some(2));
+
+        let args = parse(&["mcp", "-T", "tldr", "--max-cost", "1"]).unwrap();
+        assert!(matches!(args.command, Command::Mcp));
+        assert_eq!(args.max_cost, Some(1.0));
+
+        assert!(matches!(
+            parse(&["completions", "zsh"]).unwrap().command,
+            Command::Completions(ref shell) if shell == "zsh"
+        ));
+        assert!(matches!(
+            parse(&["man", "-d", "man"]).unwrap().command,
+            Command::Man(Some(_))
+        ));
+    }
+
+    #[test]
+    fn reads_environment_overrides() {
+        let vars = [
+            ("YOUTUBE_SUMMARY_DEFAULT_MODEL".to_string(), "m".to_string()),
+            ("HOME".to_string(), "/root".to_string()),
+        ];
+        let args = Args::parse_from(["youtube-summary", "models"], vars).unwrap();
+        assert_eq!(
+            args.env_overrides,
+            [("default_model".to_string(), "m".to_string())]
+        );
+    }
+
+    #[test]
+    fn command_definition_is_consistent() {
+        command().debug_assert();
     }
 }
@@ -0,0 +1,595 @@
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+
+use crate::error::{Error, Result};
+use crate::transcript::{CONSENT_COOKIE, USER_AGENT};
+use crate::video_id;
+
+const BROWSE_URL: &str = "https://www.youtube.com/youtubei/v1/browse";
+
+/// Client version sent with continuation requests if the page lacks one
+const FALLBACK_CLIENT_VERSION: &str = "2.20250101.00.00";
+
+/// Number of channel videos expanded when no limit is given, since
+/// channels can have thousands of uploads
+pub const DEFAULT_CHANNEL_LIMIT: usize = 50;
+
+/// A YouTube URL that stands for several videos
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Collection {
+    /// A playlist, by playlist ID
+    Playlist(String),
+    /// A channel, by URL path such as "@handle" or "channel/UC..."
+    Channel(String),
+}
+
+/// Restricts which videos of a collection are expanded
+#[derive(Debug, Clone, Default)]
+pub struct ExpandOptions {
+    pub limit: Option<usize>,
+    /// Only channel videos published within this age are kept
+    pub max_age: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectionVideo {
+    pub video_id: String,
+    pub title: Option<String>,
+    /// Relative publish time as shown by YouTube, e.g. "3 days ago"
+    pub published: Option<String>,
+}
+
+/// The member videos of a collection
+#[derive(Debug)]
+pub struct Expanded {
+    pub collection: Collection,
+    pub title: Option<String>,
+    pub videos: Vec<CollectionVideo>,
+}
+
+/// One page of a collection listing
+#[derive(Debug, Default)]
+pub struct Page {
+    pub title: Option<String>,
+    pub videos: Vec<CollectionVideo>,
+    pub continuation: Option<String>,
+}
+
+impl Collection {
+    /// Recognizes playlist and channel URLs, on the same hosts as video
+    /// links. Watch URLs that carry a `list=` parameter are videos, not
+    /// playlists.
+    pub fn parse(input: &str) -> Option<Self> {
+        let url = video_id::parse_url(input)?;
+        let host = video_id::youtube_host(&url)?;
+        if host == video_id::SHORT_LINK_HOST {
+            return None;
+        }
+
+        match video_id::path_segments(&url).as_slice() {
+            ["playlist"] => video_id::query(&url, "list")
+                .filter(|id| video_id::is_valid_playlist(id))
+                .map(Collection::Playlist),
+            [handle, ..] if handle.strip_prefix('@').is_some_and(is_valid_handle) => {
+                Some(Collection::Channel(handle.to_string()))
+            }
+            [kind @ ("channel" | "c" | "user"), name, ..] if is_valid_name(name) => {
+                Some(Collection::Channel(format!("{}/{}", kind, name)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Stable identifier used in output file names
+    pub fn id(&self) -> String {
+        match self {
+            Collection::Playlist(id) => id.clone(),
+            Collection::Channel(path) => path.trim_start_matches('@').replace('/', "-"),
+        }
+    }
+
+    /// URL of the page listing the collection's videos
+    pub fn page_url(&self) -> String {
+        match self {
+            Collection::Playlist(id) => format!("https://www.youtube.com/playlist?list={}", id),
+            Collection::Channel(path) => format!("https://www.youtube.com/{}/videos", path),
+        }
+    }
+}
+
+/// Handles are letters, digits, `-`, `_` and `.`
+fn is_valid_handle(s: &str) -> bool {
+    (3..=30).contains(&s.len())
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+/// Channel IDs and legacy user and custom names are URL-safe base64
+fn is_valid_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= 100
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_'))
+}
+
+/// Fetches the member videos of a playlist or channel, following
+/// continuation pages until the limit or the date filter is reached
+pub async fn expand(collection: &Collection, options: &ExpandOptions) -> Result<Expanded> {
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to create HTTP client: {}", e)))?;
+
+    let limit = match collection {
+        Collection::Playlist(_) => options.limit,
+        Collection::Channel(_) => Some(options.limit.unwrap_or(DEFAULT_CHANNEL_LIMIT)),
+    };
+    // Only channel listings are sorted newest first
+    let max_age = match collection {
+        Collection::Playlist(_) => None,
+        Collection::Channel(_) => options.max_age,
+    };
+
+    let html = client
+        .get(collection.page_url())
+        .header("Accept-Language", "en-US,en")
+        .header("Cookie", CONSENT_COOKIE)
+        .send()
+        .await
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to fetch collection page: {}", e)))?
+        .error_for_status()
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to fetch collection page: {}", e)))?
+        .text()
+        .await
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to read collection page: {}", e)))?;
+
+    let client_version = extract_client_version(&html);
+    let mut page = parse_page(&extract_initial_data(&html)?);
+    let title = page.title.take();
+
+    let mut videos = Vec::new();
+    let mut seen = HashSet::new();
+
+    loop {
+        for video in page.videos.drain(..) {
+            if limit.is_some_and(|limit| videos.len() >= limit) {
+                break;
+            }
+            if let Some(max_age) = max_age
+                && let Some(age) = video.published.as_deref().and_then(parse_relative_age)
+                && age > max_age
+            {
+                // Everything after this is older still
+                page.continuation = None;
+                break;
+            }
+            if seen.insert(video.video_id.clone()) {
+                videos.push(video);
+            }
+        }
+
+        let done = limit.is_some_and(|limit| videos.len() >= limit);
+        let Some(token) = page.continuation.take().filter(|_| !done) else {
+            break;
+        };
+
+        let data = fetch_continuation(&client, &token, client_version.as_deref()).await?;
+        page = parse_page(&data);
+    }
+
+    if videos.is_empty() {
+        return Err(Error::TranscriptFetch(format!(
+            "No videos found at {}",
+            collection.page_url()
+        )));
+    }
+
+    Ok(Expanded {
+        collection: collection.clone(),
+        title,
+        videos,
+    })
+}
+
+async fn fetch_continuation(
+    client: &reqwest::Client,
+    token: &str,
+    client_version: Option<&str>,
+) -> Result<Value> {
+    let body = json!({
+        "context": {
+            "client": {
+                "clientName": "WEB",
+                "clientVersion": client_version.unwrap_or(FALLBACK_CLIENT_VERSION),
+                "hl": "en",
+            }
+        },
+        "continuation": token,
+    });
+
+    client
+        .post(BROWSE_URL)
+        .header("Cookie", CONSENT_COOKIE)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to fetch next page: {}", e)))?
+        .error_for_status()
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to fetch next page: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to parse next page: {}", e)))
+}
+
+/// Extracts the `ytInitialData` JSON embedded in a playlist or channel page
+pub fn extract_initial_data(html: &str) -> Result<Value> {
+    extract_variable(html, "ytInitialData")
+        .ok_or_else(|| Error::TranscriptFetch("Collection page contains no video data".to_string()))
+}
+
+/// Extracts a JSON value assigned to a global in a YouTube page's scripts,
+/// such as `ytInitialData` or `ytInitialPlayerResponse`
+pub fn extract_variable(html: &str, name: &str) -> Option<Value> {
+    let start = [
+        format!("var {} = ", name),
+        format!("window[\"{}\"] = ", name),
+        format!("{} = ", name),
+    ]
+    .iter()
+    .find_map(|marker| html.find(marker.as_str()).map(|pos| pos + marker.len()))?;
+
+    // Parse just the first JSON value; the script continues after it
+    serde_json::Deserializer::from_str(&html[start..])
+        .into_iter::<Value>()
+        .next()
+        .and_then(|value| value.ok())
+}
+
+/// Reads the web client version from the page's `ytcfg`
+pub fn extract_client_version(html: &str) -> Option<String> {
+    let marker = "\"INNERTUBE_CLIENT_VERSION\":\"";
+    let start = html.find(marker)? + marker.len();
+    let end = html[start..].find('"')?;
+    Some(html[start..start + end].to_string())
+}
+
+/// Collects the videos, the title and the continuation token from a
+/// `ytInitialData` document or a continuation response.
+///
+/// The document is walked as a whole rather than by fixed paths, since
+/// YouTube moves renderers around between page layouts.
+pub fn parse_page(data: &Value) -> Page {
+    let mut page = Page::default();
+    walk(data, &mut page);
+    page
+}
+
+fn walk(value: &Value, page: &mut Page) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                match key.as_str() {
+                    "playlistVideoRenderer" | "videoRenderer" | "gridVideoRenderer" => {
+                        if let Some(video) = parse_video_renderer(child) {
+                            page.videos.push(video);
+                        }
+                    }
+                    "lockupViewModel" => {
+                        if let Some(video) = parse_lockup(child) {
+                            page.videos.push(video);
+                        }
+                    }
+                    "playlistMetadataRenderer" | "channelMetadataRenderer"
+                        if page.title.is_none() =>
+                    {
+                        page.title = child["title"].as_str().map(str::to_string);
+                    }
+                    "continuationItemRenderer" => {
+                        if let Some(token) = child
+                            .pointer("/continuationEndpoint/continuationCommand/token")
+                            .and_then(Value::as_str)
+                        {
+                            page.continuation = Some(token.to_string());
+                        }
+                    }
+                    _ => walk(child, page),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                walk(item, page);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn parse_video_renderer(renderer: &Value) -> Option<CollectionVideo> {
+    let video_id = renderer["videoId"].as_str()?;
+
+    Some(CollectionVideo {
+        video_id: video_id.to_string(),
+        title: text(&renderer["title"]),
+        published: text(&renderer["publishedTimeText"]),
+    })
+}
+
+/// Newer layouts describe videos with view models instead of renderers
+fn parse_lockup(lockup: &Value) -> Option<CollectionVideo> {
+    if lockup["contentType"].as_str() != Some("LOCKUP_CONTENT_TYPE_VIDEO") {
+        return None;
+    }
+    let video_id = lockup["contentId"].as_str()?;
+
+    Some(CollectionVideo {
+        video_id: video_id.to_string(),
+        title: lockup
+            .pointer("/metadata/lockupMetadataViewModel/title/content")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        published: None,
+    })
+}
+
+/// Reads a YouTube text object, either `{"simpleText": ...}` or `{"runs": [...]}`
+pub fn text(value: &Value) -> Option<String> {
+    if let Some(simple) = value["simpleText"].as_str() {
+        return Some(simple.to_string());
+    }
+
+    let runs = value["runs"].as_array()?;
+    Some(
+        runs.iter()
+            .filter_map(|run| run["text"].as_str())
+            .collect::<String>(),
+    )
+}
+
+/// Parses YouTube's relative publish times such as "3 days ago" or
+/// "Streamed 2 weeks ago". Months and years are approximated.
+pub fn parse_relative_age(text: &str) -> Option<Duration> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let ago = words.iter().position(|w| w.eq_ignore_ascii_case("ago"))?;
+    if ago < 2 {
+        return None;
+    }
+
+    let count: u64 = words[ago - 2].parse().ok()?;
+    let unit = words[ago - 1].trim_end_matches('s').to_lowercase();
+    let seconds = match unit.as_str() {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 3600,
+        "day" => 86_400,
+        "week" => 7 * 86_400,
+        "month" => 30 * 86_400,
+        "year" => 365 * 86_400,
+        _ => return None,
+    };
+
+    count.checked_mul(seconds).map(Duration::from_secs)
+}
+
+/// Parses a `--since` value: either an age such as "30d" or a date in
+/// YYYY-MM-DD form, returned as the maximum age of kept videos
+pub fn parse_since(value: &str) -> Result<Duration> {
+    if let Some(timestamp) = parse_date(value) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        return Ok(Duration::from_secs(now.saturating_sub(timestamp)));
+    }
+
+    crate::cache::parse_age(value).map_err(|_| {
+        Error::Config(format!(
+            "Invalid date: '{}' (expected YYYY-MM-DD or an age like 30d)",
+            value
+        ))
+    })
+}
+
+/// Converts a YYYY-MM-DD date into a Unix timestamp at midnight UTC
+fn parse_date(value: &str) -> Option<u64> {
+    let mut parts = value.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || year < 1970 {
+        return None;
+    }
+
+    // Days from civil, see http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    u64::try_from(days * 86_400).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYLIST: &str = include_str!("../tests/fixtures/playlist.html");
+    const PLAYLIST_CONTINUATION: &str =
+        include_str!("../tests/fixtures/playlist_continuation.json");
+    const CHANNEL: &str = include_str!("../tests/fixtures/channel.html");
+    const CHANNEL_CONTINUATION: &str = include_str!("../tests/fixtures/channel_continuation.json");
+
+    fn ids(page: &Page) -> Vec<&str> {
+        page.videos.iter().map(|v| v.video_id.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_collection_urls() {
+        let playlist = |id: &str| Some(Collection::Playlist(id.to_string()));
+        let channel = |path: &str| Some(Collection::Channel(path.to_string()));
+        let cases = [
+            (
+                "https://www.youtube.com/playlist?list=PLabc-123_x",
+                playlist("PLabc-123_x"),
+            ),
+            ("youtube.com/playlist?list=PLabc", playlist("PLabc")),
+            (
+                "https://music.youtube.com/playlist?list=OLAK5uy_abc",
+                playlist("OLAK5uy_abc"),
+            ),
+            (
+                "https://m.youtube.com/playlist?si=x&list=PLabc#top",
+                playlist("PLabc"),
+            ),
+            (
+                "HTTPS://WWW.YOUTUBE.COM./playlist?list=PLabc",
+                playlist("PLabc"),
+            ),
+            (
+                "https://www.youtube.com/@some.handle",
+                channel("@some.handle"),
+            ),
+            ("www.youtube.com/@handle/videos", channel("@handle")),
+            (
+                "https://www.youtube.com/channel/UCabc-_123",
+                channel("channel/UCabc-_123"),
+            ),
+            ("https://www.youtube.com/c/Name/videos", channel("c/Name")),
+            ("https://www.youtube.com/user/name", channel("user/name")),
+            (
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLabc",
+                None,
+            ),
+            ("https://youtu.be/playlist?list=PLabc", None),
+            ("https://www.youtube.com/playlist?list=", None),
+            ("https://www.youtube.com/playlist?list=../x", None),
+            ("https://www.youtube.com/channel/..", None),
+            ("https://www.youtube.com/c/a%2F..", None),
+            ("https://www.youtube.com/@", None),
+            ("https://www.youtube.com/@a/", None),
+            ("https://youtube.com@evil.com/playlist?list=PLabc", None),
+            ("https://evil.com/playlist?list=PLabc", None),
+            ("ftp://www.youtube.com/playlist?list=PLabc", None),
+            ("dQw4w9WgXcQ", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(Collection::parse(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn parses_a_playlist_page() {
+        let page = parse_page(&extract_initial_data(PLAYLIST).unwrap());
+        assert_eq!(page.title.as_deref(), Some("Rust talks"));
+        assert_eq!(ids(&page), ["dQw4w9WgXcQ", "a-b_c-d_e-f"]);
+        assert_eq!(page.videos[0].title.as_deref(), Some("Ownership explained"));
+        assert_eq!(
+            page.videos[1].title.as_deref(),
+            Some("Borrowing & lifetimes")
+        );
+        assert_eq!(
+            page.continuation.as_deref(),
+            Some("4qmFsgJhEiRWTFBMcGxheWxpc3Q")
+        );
+        assert_eq!(
+            extract_client_version(PLAYLIST).as_deref(),
+            Some("2.20251015.01.00")
+        );
+    }
+
+    #[test]
+    fn parses_a_playlist_continuation() {
+        let data: Value = serde_json::from_str(PLAYLIST_CONTINUATION).unwrap();
+        let page = parse_page(&data);
+        assert_eq!(page.title, None);
+        assert_eq!(ids(&page), ["BBBBBBBBBBB", "dQw4w9WgXcQ"]);
+        assert_eq!(page.continuation, None);
+    }
+
+    #[test]
+    fn parses_a_channel_page() {
+        let page = parse_page(&extract_initial_data(CHANNEL).unwrap());
+        assert_eq!(page.title.as_deref(), Some("Example Channel"));
+        assert_eq!(ids(&page), ["CCCCCCCCCCC", "DDDDDDDDDDD", "EEEEEEEEEEE"]);
+        assert_eq!(page.videos[0].published.as_deref(), Some("3 days ago"));
+        assert_eq!(page.videos[2].title.as_deref(), Some("View model layout"));
+        assert_eq!(page.videos[2].published, None);
+        assert_eq!(
+            page.continuation.as_deref(),
+            Some("4qmFsgKrCBIYVUNjaGFubmVs")
+        );
+        // The channel page names a different key than the playlist page
+        assert_eq!(extract_client_version(CHANNEL), None);
+    }
+
+    #[test]
+    fn parses_a_channel_continuation() {
+        let data: Value = serde_json::from_str(CHANNEL_CONTINUATION).unwrap();
+        let page = parse_page(&data);
+        assert_eq!(ids(&page), ["FFFFFFFFFFF", "GGGGGGGGGGG"]);
+        assert_eq!(page.videos[1].title.as_deref(), Some("Grid layout"));
+        assert_eq!(page.continuation, None);
+    }
+
+    #[test]
+    fn rejects_pages_without_data() {
+        assert!(extract_initial_data("<html><body>Before you continue</body></html>").is_err());
+        assert!(extract_initial_data("var ytInitialData = {broken").is_err());
+        assert!(parse_page(&json!({})).videos.is_empty());
+    }
+
+    #[test]
+    fn extracts_variables_in_every_assignment_form() {
+        for html in [
+            r#"<script>var ytInitialData = {"a": 1};</script>"#,
+            r#"<script>window["ytInitialData"] = {"a": 1};</script>"#,
+            r#"<script>ytInitialData = {"a": 1}; var other = 2;</script>"#,
+        ] {
+            assert_eq!(
+                extract_variable(html, "ytInitialData"),
+                Some(json!({"a": 1}))
+            );
+        }
+        assert_eq!(extract_variable("var other = {}", "ytInitialData"), None);
+    }
+
+    #[test]
+    fn parses_relative_ages() {
+        let cases: &[(&str, Option<u64>)] = &[
+            ("3 days ago", Some(3 * 86_400)),
+            ("1 day ago", Some(86_400)),
+            ("Streamed 2 weeks ago", Some(14 * 86_400)),
+            ("5 hours ago", Some(5 * 3600)),
+            ("1 year ago", Some(365 * 86_400)),
+            ("Premiered 10 minutes ago", Some(600)),
+            ("ago", None),
+            ("3 days", None),
+            ("three days ago", None),
+            ("3 fortnights ago", None),
+            ("99999999999999999999 years ago", None),
+            ("999999999999999999 years ago", None),
+        ];
+        for &(text, expected) in cases {
+            assert_eq!(
+                parse_relative_age(text).map(|age| age.as_secs()),
+                expected,
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-02-29"), Some(1_709_164_800));
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("1969-12-31"), None);
+        assert_eq!(parse_date("30d"), None);
+    }
+}
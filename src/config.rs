@@ -1,151 +1,134 @@
-use crate::cli::Args;
+use crate::cache::CachePolicy;
+use crate::collection::ExpandOptions;
 use crate::error::{Error, Result};
-use crate::openrouter::DEFAULT_MODEL;
-use std::env;
-use std::fs;
-use std::path::PathBuf;
-
-#[derive(Debug)]
+use crate::output::OutputFormat;
+use crate::provider::ProviderKind;
+use crate::retry::RetryPolicy;
+use crate::summarize::ChapterMode;
+use crate::templates;
+use crate::transcript::TranscriptOptions;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
 pub struct Config {
-    pub api_key: String,
+    pub provider: ProviderConfig,
     pub model: String,
+    /// Models tried in order when `model` fails with a rate limit,
+    /// capacity, availability or context length error
+    pub fallback_models: Vec<String>,
+    /// The prompt template, with `{{variables}}` filled in per video
     pub prompt: String,
+    /// Name of the template the prompt came from, `None` for `--prompt`
+    pub template: Option<String>,
+    /// Template variables from `--var`, which take precedence over the
+    /// variables taken from the video
+    pub variables: Vec<(String, String)>,
     pub verbose: bool,
+    pub stream: bool,
+    pub timestamps: bool,
+    pub transcript: TranscriptOptions,
+    pub output_language: Option<String>,
+    /// Whether summaries follow the chapters of a video
+    pub chapters: ChapterMode,
+    pub cache: CachePolicy,
+    /// Whether summaries are recorded in the history database
+    pub history: bool,
+    pub jobs: usize,
+    pub expand: ExpandOptions,
+    pub overview: bool,
+    pub format: OutputFormat,
+    /// Largest projected cost in dollars a summary may have
+    pub max_cost: Option<f64>,
+    /// Bearer token `serve` requires from clients
+    pub serve_token: Option<String>,
 }
 
 impl Config {
-    pub fn load(args: &Args) -> Result<Self> {
-        // Load config file if it exists
-        let file_config = Self::load_config_file(args.config_path.as_deref())?;
-
-        // Load credentials from ~/.config/youtube-summary/credentials
-        let credentials = Credentials::load()?;
-
-        // API key precedence: CLI > env > credentials > config file
-        let api_key = args
-            .api_key
-            .clone()
-            .or_else(|| env::var("OPENROUTER_API_KEY").ok())
-            .or(credentials.openrouter_api_key)
-            .or(file_config.api_key)
-            .ok_or_else(|| {
-                Error::Config(
-                    "No API key found. Set OPENROUTER_API_KEY env var, use --api-key, or add to ~/.config/youtube-summary/credentials"
-                        .to_string(),
-                )
-            })?;
-
-        // Model precedence: CLI > config file > default
-        let model = args
-            .model
-            .clone()
-            .or(file_config.model)
-            .unwrap_or_else(|| DEFAULT_MODEL.to_string());
-
-        // Prompt: CLI > default
-        let prompt = args.prompt.clone().unwrap_or_else(|| {
-            "Please provide a comprehensive summary of the following YouTube video transcript. \
-             Include the main topics discussed, key points, and any important conclusions."
-                .to_string()
-        });
-
-        Ok(Config {
-            api_key,
-            model,
-            prompt,
-            verbose: args.verbose,
-        })
-    }
-
-    fn load_config_file(custom_path: Option<&str>) -> Result<FileConfig> {
-        let path = match custom_path {
-            Some(p) => PathBuf::from(p),
-            None => {
-                let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
-                PathBuf::from(home).join(".config/youtube-summary/config")
-            }
-        };
-
-        if !path.exists() {
-            return Ok(FileConfig::default());
+    /// A copy of the configuration with the settings of one `serve` or
+    /// `mcp` request applied
+    pub fn with_overrides(&self, overrides: &Overrides) -> Result<Config> {
+        let mut config = self.clone();
+
+        if let Some(ref model) = overrides.model {
+            config.model = model.clone();
+            config.fallback_models.clear();
         }
-
-        let content = fs::read_to_string(&path)
-            .map_err(|e| Error::Config(format!("Failed to read config file: {}", e)))?;
-
-        Self::parse_config(&content)
-    }
-
-    fn parse_config(content: &str) -> Result<FileConfig> {
-        let mut config = FileConfig::default();
-
-        for line in content.lines() {
-            let line = line.trim();
-
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with('#') {
-                continue;
+        match (&overrides.prompt, &overrides.template) {
+            (Some(_), Some(_)) => {
+                return Err(Error::Config(
+                    "Give either a prompt or a template, not both".to_string(),
+                ));
             }
-
-            if let Some((key, value)) = line.split_once('=') {
-                let key = key.trim();
-                let value = value.trim();
-
-                match key {
-                    "api_key" => config.api_key = Some(value.to_string()),
-                    "default_model" => config.model = Some(value.to_string()),
-                    _ => {} // Ignore unknown keys
-                }
+            (Some(prompt), None) => {
+                config.prompt = prompt.clone();
+                config.template = None;
             }
+            (None, Some(name)) => {
+                config.prompt = templates::find(name)?.body;
+                config.template = Some(name.clone());
+            }
+            (None, None) => {}
         }
-
+        if let Some(ref languages) = overrides.lang {
+            config.transcript.languages = parse_language_list(languages)?;
+        }
+        if overrides.output_lang.is_some() {
+            config.output_language = overrides.output_lang.clone();
+        }
+        config.variables.extend(
+            overrides
+                .vars
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+
+        templates::validate(&config.prompt, &config.variables)?;
         Ok(config)
     }
 }
 
-#[derive(Debug, Default)]
-struct FileConfig {
-    api_key: Option<String>,
-    model: Option<String>,
+/// Settings a single request to `serve` or `mcp` may change; unset
+/// fields keep the configured values
+#[derive(Debug, Default, Deserialize)]
+pub struct Overrides {
+    pub model: Option<String>,
+    pub template: Option<String>,
+    pub prompt: Option<String>,
+    /// Preferred transcript languages, e.g. "de,en"
+    pub lang: Option<String>,
+    pub output_lang: Option<String>,
+    /// Template variables
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Default)]
-struct Credentials {
-    openrouter_api_key: Option<String>,
+/// Settings needed to talk to the LLM backend
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    pub kind: ProviderKind,
+    pub base_url: Option<String>,
+    pub api_key: Option<String>,
+    pub retry: RetryPolicy,
+    /// Whether retries are logged
+    pub verbose: bool,
 }
 
-impl Credentials {
-    fn load() -> Result<Self> {
-        let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        let path = PathBuf::from(home).join(".config/youtube-summary/credentials");
-
-        if !path.exists() {
-            return Ok(Credentials::default());
-        }
-
-        let content = fs::read_to_string(&path)
-            .map_err(|e| Error::Config(format!("Failed to read credentials file: {}", e)))?;
-
-        let mut credentials = Credentials::default();
-
-        for line in content.lines() {
-            let line = line.trim();
-
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
-
-            if let Some((key, value)) = line.split_once('=') {
-                let key = key.trim();
-                let value = value.trim().trim_matches('"').trim_matches('\'');
-
-                if key == "OPENROUTER_API_KEY" {
-                    credentials.openrouter_api_key = Some(value.to_string());
-                }
-            }
-        }
-
-        Ok(credentials)
+/// Parses a comma separated list of language codes such as "de,en"
+pub fn parse_language_list(value: &str) -> Result<Vec<String>> {
+    let languages: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if languages.is_empty() {
+        return Err(Error::Config(format!(
+            "Invalid language list: '{}' (expected codes like \"de,en\")",
+            value
+        )));
     }
+
+    Ok(languages)
 }
@@ -0,0 +1,249 @@
+use crate::chunking;
+use crate::provider::{ChatRequest, Pricing, Usage};
+
+/// Per-token prices of a model in dollars
+#[derive(Debug, Clone, Copy)]
+pub struct Prices {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl Prices {
+    /// Parses the provider's pricing strings. Negative prices mark
+    /// variable pricing, which can't be estimated.
+    pub fn parse(pricing: &Pricing) -> Option<Self> {
+        let prompt: f64 = pricing.prompt.parse().ok()?;
+        let completion: f64 = pricing.completion.parse().ok()?;
+        if prompt < 0.0 || completion < 0.0 {
+            return None;
+        }
+        Some(Prices { prompt, completion })
+    }
+
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        prompt_tokens as f64 * self.prompt + completion_tokens as f64 * self.completion
+    }
+
+    /// Cost of a finished request, unless the provider already reported it
+    pub fn usage_cost(&self, usage: &Usage) -> f64 {
+        usage
+            .cost
+            .unwrap_or_else(|| self.cost(usage.prompt_tokens, usage.completion_tokens))
+    }
+}
+
+/// Projected usage of a summary, computed before anything is sent
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Estimate {
+    pub requests: usize,
+    pub prompt_tokens: u64,
+    /// Upper bound, assuming every completion uses all of `max_tokens`
+    pub completion_tokens: u64,
+}
+
+impl Estimate {
+    /// Estimates summarizing `chunks` with `prompt`: one request per chunk,
+    /// plus one request combining the partial summaries if there are
+    /// several chunks. Further combining rounds are not accounted for.
+    pub fn new(prompt: &str, chunks: &[String], max_tokens: u32) -> Self {
+        let prompt_tokens = chunking::estimate_tokens(prompt) as u64;
+        let max_tokens = u64::from(max_tokens);
+
+        let mut estimate = Estimate {
+            requests: chunks.len().max(1),
+            prompt_tokens: chunks
+                .iter()
+                .map(|chunk| prompt_tokens + chunking::estimate_tokens(chunk) as u64)
+                .sum::<u64>()
+                .max(prompt_tokens),
+            completion_tokens: chunks.len().max(1) as u64 * max_tokens,
+        };
+
+        if chunks.len() > 1 {
+            estimate.requests += 1;
+            estimate.prompt_tokens += prompt_tokens + chunks.len() as u64 * max_tokens;
+            estimate.completion_tokens += max_tokens;
+        }
+
+        estimate
+    }
+
+    /// Estimates a single request, such as a chat question
+    pub fn for_request(request: &ChatRequest) -> Self {
+        Estimate {
+            requests: 1,
+            prompt_tokens: request
+                .messages
+                .iter()
+                .map(|message| chunking::estimate_tokens(&message.content) as u64)
+                .sum(),
+            completion_tokens: u64::from(request.max_tokens),
+        }
+    }
+
+    /// Adds the usage of further requests
+    pub fn add(&mut self, other: Estimate) {
+        self.requests += other.requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+
+    pub fn cost(&self, prices: &Prices) -> f64 {
+        prices.cost(self.prompt_tokens, self.completion_tokens)
+    }
+}
+
+pub fn format_cost(dollars: f64) -> String {
+    if dollars > 0.0 && dollars < 0.01 {
+        format!("${:.4}", dollars)
+    } else {
+        format!("${:.2}", dollars)
+    }
+}
+
+/// Parses a cost limit in dollars, such as `0.05` or `$0.05`
+pub fn parse_max_cost(value: &str) -> std::result::Result<f64, String> {
+    value
+        .trim()
+        .trim_start_matches('$')
+        .parse::<f64>()
+        .ok()
+        .filter(|cost| cost.is_finite() && *cost >= 0.0)
+        .ok_or_else(|| {
+            format!(
+                "invalid cost limit '{}' (expected an amount in dollars, e.g. 0.05)",
+                value
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::provider::ChatMessage;
+
+    const PRICES: Prices = Prices {
+        prompt: 0.5,
+        completion: 2.0,
+    };
+
+    #[test]
+    fn parses_prices() {
+        let pricing = |prompt: &str, completion: &str| Pricing {
+            prompt: prompt.to_string(),
+            completion: completion.to_string(),
+        };
+        let prices = Prices::parse(&pricing("0.000001", "0.000002")).unwrap();
+        assert_eq!((prices.prompt, prices.completion), (0.000001, 0.000002));
+        assert!(Prices::parse(&pricing("0", "0")).is_some());
+        assert!(Prices::parse(&pricing("-1", "0")).is_none());
+        assert!(Prices::parse(&pricing("0", "-1")).is_none());
+        assert!(Prices::parse(&pricing("free", "0")).is_none());
+    }
+
+    #[test]
+    fn prices_usage() {
+        assert_eq!(PRICES.cost(10, 3), 11.0);
+
+        let mut usage = Usage {
+            prompt_tokens: 10,
+            completion_tokens: 3,
+            cost: None,
+        };
+        assert_eq!(PRICES.usage_cost(&usage), 11.0);
+        // A cost reported by the provider wins over the estimate
+        usage.cost = Some(0.25);
+        assert_eq!(PRICES.usage_cost(&usage), 0.25);
+    }
+
+    #[test]
+    fn estimates_summaries() {
+        // 10 prompt tokens; chunks of 100 and 20 tokens
+        let prompt = "p".repeat(30);
+        let chunks = ["c".repeat(300), "c".repeat(60)];
+
+        let single = Estimate::new(&prompt, &chunks[..1], 100);
+        assert_eq!(
+            (
+                single.requests,
+                single.prompt_tokens,
+                single.completion_tokens
+            ),
+            (1, 110, 100)
+        );
+
+        // Both chunks, then a request combining two full-length partials
+        let several = Estimate::new(&prompt, &chunks, 100);
+        assert_eq!(
+            (
+                several.requests,
+                several.prompt_tokens,
+                several.completion_tokens
+            ),
+            (3, 110 + 30 + 10 + 200, 300)
+        );
+        assert_eq!(several.cost(&PRICES), 350.0 * 0.5 + 300.0 * 2.0);
+
+        let empty = Estimate::new(&prompt, &[], 100);
+        assert_eq!(
+            (empty.requests, empty.prompt_tokens, empty.completion_tokens),
+            (1, 10, 100)
+        );
+
+        let mut total = single;
+        total.add(several);
+        assert_eq!(
+            (total.requests, total.prompt_tokens, total.completion_tokens),
+            (4, 460, 400)
+        );
+    }
+
+    #[test]
+    fn estimates_a_request() {
+        let request = ChatRequest {
+            model: "m".to_string(),
+            fallback_models: Vec::new(),
+            messages: vec![
+                ChatMessage::system("s".repeat(30)),
+                ChatMessage::user("u".repeat(7)),
+            ],
+            max_tokens: 50,
+            context_length: 8192,
+        };
+        let estimate = Estimate::for_request(&request);
+        assert_eq!(
+            (
+                estimate.requests,
+                estimate.prompt_tokens,
+                estimate.completion_tokens
+            ),
+            (1, 13, 50)
+        );
+    }
+
+    #[test]
+    fn formats_costs() {
+        let cases = [
+            (0.0, "$0.00"),
+            (0.005, "$0.0050"),
+            (0.00012345, "$0.0001"),
+            (0.01, "$0.01"),
+            (0.125, "$0.12"),
+            (12.5, "$12.50"),
+        ];
+        for (dollars, expected) in cases {
+            assert_eq!(format_cost(dollars), expected, "{}", dollars);
+        }
+    }
+
+    #[test]
+    fn parses_cost_limits() {
+        for (value, expected) in [("0.05", 0.05), (" $1 ", 1.0), ("0", 0.0), ("$.5", 0.5)] {
+            assert_eq!(parse_max_cost(value), Ok(expected), "{:?}", value);
+        }
+        for value in ["", "$", "-1", "nan", "inf", "1 USD", "$-0.1"] {
+            assert!(parse_max_cost(value).is_err(), "{:?}", value);
+        }
+    }
+}
@@ -5,7 +5,33 @@ pub enum Error {
     InvalidYoutubeUrl(String),
     TranscriptFetch(String),
     ApiRequest(String),
+    /// The provider answered with a non-success HTTP status
+    ApiStatus {
+        status: u16,
+        message: String,
+    },
     Config(String),
+    Cache(String),
+    History(String),
+    Output(String),
+    /// The projected cost of a request exceeds the configured budget
+    Budget(String),
+}
+
+impl Error {
+    /// Name of the variant, used where failures are reported per item
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::InvalidYoutubeUrl(_) => "InvalidYoutubeUrl",
+            Error::TranscriptFetch(_) => "TranscriptFetch",
+            Error::ApiRequest(_) | Error::ApiStatus { .. } => "ApiRequest",
+            Error::Config(_) => "Config",
+            Error::Cache(_) => "Cache",
+            Error::History(_) => "History",
+            Error::Output(_) => "Output",
+            Error::Budget(_) => "Budget",
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -13,8 +39,14 @@ impl fmt::Display for Error {
         match self {
             Error::InvalidYoutubeUrl(msg) => write!(f, "Invalid YouTube URL: {}", msg),
             Error::TranscriptFetch(msg) => write!(f, "Failed to fetch transcript: {}", msg),
-            Error::ApiRequest(msg) => write!(f, "API request failed: {}", msg),
+            Error::ApiRequest(msg) | Error::ApiStatus { message: msg, .. } => {
+                write!(f, "API request failed: {}", msg)
+            }
             Error::Config(msg) => write!(f, "Configuration error: {}", msg),
+            Error::Cache(msg) => write!(f, "Cache error: {}", msg),
+            Error::History(msg) => write!(f, "History error: {}", msg),
+            Error::Output(msg) => write!(f, "Output error: {}", msg),
+            Error::Budget(msg) => write!(f, "Budget exceeded: {}", msg),
         }
     }
 }
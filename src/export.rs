@@ -0,0 +1,443 @@
+use crate::error::{Error, Result};
+use crate::timestamps;
+use crate::transcript::{Snippet, Transcript};
+
+/// Longest stretch merged into one sentence, for automatic captions that
+/// come without punctuation
+const MAX_SENTENCE_SECS: f64 = 30.0;
+
+/// A pause after which a Markdown transcript starts a new paragraph
+const PARAGRAPH_PAUSE_SECS: f64 = 2.0;
+
+/// Length in characters after which a Markdown paragraph ends with the
+/// next sentence
+const PARAGRAPH_CHARS: usize = 600;
+
+/// Format of an exported transcript
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One line per snippet
+    #[default]
+    Text,
+    Srt,
+    Vtt,
+    /// The snippets with their start and duration
+    Json,
+    /// Paragraphs headed by a timestamp
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "text" | "txt" => Ok(ExportFormat::Text),
+            "srt" => Ok(ExportFormat::Srt),
+            "vtt" | "webvtt" => Ok(ExportFormat::Vtt),
+            "json" => Ok(ExportFormat::Json),
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            other => Err(Error::Config(format!(
+                "Unknown transcript format '{}' (expected text, srt, vtt, json or markdown)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Writes a transcript in the given format
+pub fn render(transcript: &Transcript, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Text => Ok(render_text(transcript)),
+        ExportFormat::Srt => Ok(render_srt(transcript)),
+        ExportFormat::Vtt => Ok(render_vtt(transcript)),
+        ExportFormat::Json => serde_json::to_string_pretty(transcript)
+            .map(|json| json + "\n")
+            .map_err(|e| Error::Output(format!("Failed to serialize transcript: {}", e))),
+        ExportFormat::Markdown => Ok(render_markdown(transcript)),
+    }
+}
+
+fn render_text(transcript: &Transcript) -> String {
+    let mut text = String::new();
+    for snippet in &transcript.snippets {
+        text.push_str(&snippet.text);
+        text.push('\n');
+    }
+    text
+}
+
+fn render_srt(transcript: &Transcript) -> String {
+    let mut text = String::new();
+    for (i, snippet) in transcript.snippets.iter().enumerate() {
+        text.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            cue_time(snippet.start, ','),
+            cue_time(snippet.start + snippet.duration, ','),
+            snippet.text
+        ));
+    }
+    text
+}
+
+fn render_vtt(transcript: &Transcript) -> String {
+    let mut text = format!("WEBVTT\nLanguage: {}\n\n", transcript.language);
+    for snippet in &transcript.snippets {
+        text.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            cue_time(snippet.start, '.'),
+            cue_time(snippet.start + snippet.duration, '.'),
+            snippet.text
+        ));
+    }
+    text
+}
+
+/// Groups the sentences of a transcript into paragraphs, breaking at
+/// pauses and once a paragraph grows long
+fn render_markdown(transcript: &Transcript) -> String {
+    let mut text = match transcript.youtube_id() {
+        Some(video_id) => format!(
+            "# Transcript\n\nhttps://www.youtube.com/watch?v={} · {}\n\n",
+            video_id, transcript.language
+        ),
+        None => format!(
+            "# Transcript\n\n{} · {}\n\n",
+            transcript.video_id, transcript.language
+        ),
+    };
+
+    let sentences = merge_sentences(transcript);
+    let mut paragraph: Vec<&Snippet> = Vec::new();
+    for (i, sentence) in sentences.snippets.iter().enumerate() {
+        paragraph.push(sentence);
+
+        let length: usize = paragraph.iter().map(|s| s.text.len() + 1).sum();
+        let pause = sentences.snippets.get(i + 1).map_or(f64::INFINITY, |next| {
+            next.start - (sentence.start + sentence.duration)
+        });
+        if pause >= PARAGRAPH_PAUSE_SECS || length >= PARAGRAPH_CHARS {
+            text.push_str(&markdown_paragraph(transcript, &paragraph));
+            paragraph.clear();
+        }
+    }
+
+    text
+}
+
+fn markdown_paragraph(transcript: &Transcript, sentences: &[&Snippet]) -> String {
+    let Some(first) = sentences.first() else {
+        return String::new();
+    };
+    let start = first.start as u64;
+    let label = timestamps::format_timestamp(start);
+    let marker = match transcript.youtube_id() {
+        Some(video_id) => format!("[{}]({})", label, timestamps::video_link(video_id, start)),
+        None => format!("[{}]", label),
+    };
+    let body: Vec<&str> = sentences.iter().map(|s| s.text.as_str()).collect();
+    format!("**{}** {}\n\n", marker, body.join(" "))
+}
+
+/// Formats seconds as `hh:mm:ss,mmm` for SRT or `hh:mm:ss.mmm` for WebVTT
+fn cue_time(seconds: f64, separator: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+/// Removes sound markers such as `[Music]`, `[Applause]` or `[Laughter]`
+/// and music notes, dropping snippets left empty
+pub fn strip_markers(transcript: &Transcript) -> Transcript {
+    let snippets = transcript
+        .snippets
+        .iter()
+        .filter_map(|snippet| {
+            let text = strip_marker_text(&snippet.text);
+            (!text.is_empty()).then(|| Snippet {
+                text,
+                ..snippet.clone()
+            })
+        })
+        .collect();
+
+    Transcript {
+        snippets,
+        ..transcript.clone()
+    }
+}
+
+fn strip_marker_text(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(open) = rest.find('[') {
+        let after = &rest[open + 1..];
+        let Some(close) = after.find(']') else {
+            break;
+        };
+        let inner = after[..close].trim();
+        stripped.push_str(&rest[..open]);
+        // Only descriptions of sounds; YouTube writes censored words as
+        // "[ __ ]", which stay
+        if !is_marker(inner) {
+            stripped.push_str(&rest[open..open + close + 2]);
+        }
+        rest = &after[close + 1..];
+    }
+    stripped.push_str(rest);
+
+    stripped
+        .replace(['♪', '♫'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_marker(inner: &str) -> bool {
+    !inner.is_empty()
+        && inner.split_whitespace().count() <= 4
+        && inner
+            .chars()
+            .all(|c| c.is_alphabetic() || c.is_whitespace() || matches!(c, '-' | '\''))
+}
+
+/// Merges snippets into sentences. Snippets are split where a sentence
+/// ends within them, estimating the time from the position in the text;
+/// without punctuation, a sentence ends after 30 seconds.
+pub fn merge_sentences(transcript: &Transcript) -> Transcript {
+    let mut sentences: Vec<Snippet> = Vec::new();
+    let mut current: Option<Snippet> = None;
+
+    for snippet in &transcript.snippets {
+        let parts = split_sentences(&snippet.text);
+        let total = snippet.text.len().max(1) as f64;
+        let mut offset = 0;
+
+        for (part, ends_sentence) in parts {
+            let start = snippet.start + snippet.duration * offset as f64 / total;
+            offset += part.len() + 1;
+            let end = snippet.start + snippet.duration * (offset as f64 / total).min(1.0);
+
+            let sentence = current.get_or_insert_with(|| Snippet {
+                text: String::new(),
+                start,
+                duration: 0.0,
+            });
+            if !sentence.text.is_empty() {
+                sentence.text.push(' ');
+            }
+            sentence.text.push_str(part);
+            sentence.duration = end - sentence.start;
+
+            if ends_sentence || sentence.duration >= MAX_SENTENCE_SECS {
+                sentences.extend(current.take());
+            }
+        }
+    }
+    sentences.extend(current);
+
+    Transcript {
+        snippets: sentences,
+        ..transcript.clone()
+    }
+}
+
+/// Splits text after each `.`, `!` or `?` followed by a space, telling
+/// whether each part ends a sentence
+fn split_sentences(text: &str) -> Vec<(&str, bool)> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        let end = i + c.len_utf8();
+        if matches!(c, '.' | '!' | '?') && text[end..].starts_with(' ') {
+            parts.push((text[start..end].trim(), true));
+            start = end;
+        }
+    }
+
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        let ends = rest.ends_with(['.', '!', '?', '"', '\u{201d}']);
+        parts.push((rest, ends));
+    }
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(snippets: &[(&str, f64, f64)]) -> Transcript {
+        Transcript {
+            video_id: "dQw4w9WgXcQ".to_string(),
+            language: "en".to_string(),
+            snippets: snippets
+                .iter()
+                .map(|&(text, start, duration)| Snippet {
+                    text: text.to_string(),
+                    start,
+                    duration,
+                })
+                .collect(),
+            file: None,
+        }
+    }
+
+    fn texts(transcript: &Transcript) -> Vec<&str> {
+        transcript
+            .snippets
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn formats_cue_times() {
+        let cases = [
+            (0.0, ',', "00:00:00,000"),
+            (-1.0, ',', "00:00:00,000"),
+            (1.5, ',', "00:00:01,500"),
+            (1.5, '.', "00:00:01.500"),
+            (59.9996, '.', "00:01:00.000"),
+            (3599.5, ',', "00:59:59,500"),
+            (3600.0, '.', "01:00:00.000"),
+            (360_000.25, ',', "100:00:00,250"),
+        ];
+        for (seconds, separator, expected) in cases {
+            assert_eq!(cue_time(seconds, separator), expected, "{}", seconds);
+        }
+    }
+
+    #[test]
+    fn renders_srt_and_vtt() {
+        let transcript = transcript(&[("Hello", 1.5, 2.25), ("Late", 3599.5, 1.0)]);
+        assert_eq!(
+            render_srt(&transcript),
+            "1\n00:00:01,500 --> 00:00:03,750\nHello\n\n\
+             2\n00:59:59,500 --> 01:00:00,500\nLate\n\n"
+        );
+        assert_eq!(
+            render_vtt(&transcript),
+            "WEBVTT\nLanguage: en\n\n\
+             00:00:01.500 --> 00:00:03.750\nHello\n\n\
+             00:59:59.500 --> 01:00:00.500\nLate\n\n"
+        );
+    }
+
+    #[test]
+    fn renders_markdown_paragraphs() {
+        let mut transcript = transcript(&[
+            ("First one.", 0.0, 2.0),
+            ("Second.", 2.0, 2.0),
+            ("After pause.", 10.0, 2.0),
+        ]);
+        assert_eq!(
+            render_markdown(&transcript),
+            "# Transcript\n\nhttps://www.youtube.com/watch?v=dQw4w9WgXcQ · en\n\n\
+             **[00:00](https://youtu.be/dQw4w9WgXcQ?t=0)** First one. Second.\n\n\
+             **[00:10](https://youtu.be/dQw4w9WgXcQ?t=10)** After pause.\n\n"
+        );
+
+        // Local files have no video to link to
+        transcript.video_id = "talk".to_string();
+        transcript.file = Some("talk.srt".to_string());
+        assert_eq!(
+            render_markdown(&transcript),
+            "# Transcript\n\ntalk · en\n\n\
+             **[00:00]** First one. Second.\n\n**[00:10]** After pause.\n\n"
+        );
+    }
+
+    #[test]
+    fn strips_sound_markers() {
+        let transcript = transcript(&[
+            ("[Music]", 0.0, 1.0),
+            ("so [ __ ] that", 1.0, 1.0),
+            ("\u{266a} la la \u{266a} [Applause] yes", 2.0, 1.0),
+            ("[Laughter] see [1:30] and [a, b]", 3.0, 1.0),
+            ("[ Music ] \u{266b}", 4.0, 1.0),
+            ("a [b", 5.0, 1.0),
+            ("[Some very long bracketed remark here]", 6.0, 1.0),
+        ]);
+        let stripped = strip_markers(&transcript);
+        assert_eq!(
+            texts(&stripped),
+            [
+                "so [ __ ] that",
+                "la la yes",
+                "see [1:30] and [a, b]",
+                "a [b",
+                "[Some very long bracketed remark here]",
+            ]
+        );
+        assert_eq!(stripped.snippets[0].start, 1.0);
+    }
+
+    #[test]
+    fn splits_sentences() {
+        let cases: &[(&str, &[(&str, bool)])] = &[
+            ("", &[]),
+            ("Done.", &[("Done.", true)]),
+            (
+                "Hi there. How are you? Fine",
+                &[("Hi there.", true), ("How are you?", true), ("Fine", false)],
+            ),
+            ("It rose 3.5 percent", &[("It rose 3.5 percent", false)]),
+            ("He said \"yes.\"", &[("He said \"yes.\"", true)]),
+            ("Wow! ", &[("Wow!", true)]),
+        ];
+        for &(text, expected) in cases {
+            assert_eq!(split_sentences(text), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn merges_sentences_with_estimated_times() {
+        let merged = merge_sentences(&transcript(&[
+            ("Hello world. How are", 0.0, 10.0),
+            ("you? Bye", 10.0, 4.0),
+        ]));
+        let sentences: Vec<(&str, f64, f64)> = merged
+            .snippets
+            .iter()
+            .map(|s| (s.text.as_str(), s.start, s.duration))
+            .collect();
+        assert_eq!(
+            sentences,
+            [
+                ("Hello world.", 0.0, 6.5),
+                ("How are you?", 6.5, 6.0),
+                ("Bye", 12.5, 1.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn ends_unpunctuated_sentences_after_a_while() {
+        let merged = merge_sentences(&transcript(&[
+            ("a", 0.0, 10.0),
+            ("b", 10.0, 10.0),
+            ("c", 20.0, 10.0),
+            ("d", 30.0, 10.0),
+        ]));
+        assert_eq!(texts(&merged), ["a b c", "d"]);
+        assert_eq!(merged.snippets[0].duration, 30.0);
+        assert_eq!(merged.snippets[1].start, 30.0);
+    }
+
+    #[test]
+    fn parses_format_names() {
+        assert_eq!(ExportFormat::parse(" SRT ").unwrap(), ExportFormat::Srt);
+        assert_eq!(ExportFormat::parse("webvtt").unwrap(), ExportFormat::Vtt);
+        assert_eq!(ExportFormat::parse("md").unwrap(), ExportFormat::Markdown);
+        assert!(ExportFormat::parse("docx").is_err());
+    }
+}
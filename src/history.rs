@@ -0,0 +1,524 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::Serialize;
+use similar::TextDiff;
+
+use crate::error::{Error, Result};
+use crate::output::UsageReport;
+
+/// How long a write waits for another process holding the database, e.g.
+/// a concurrent batch
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    created_at INTEGER NOT NULL,
+    video_id TEXT NOT NULL,
+    title TEXT,
+    url TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    template TEXT,
+    prompt TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    transcript TEXT NOT NULL,
+    prompt_tokens INTEGER,
+    completion_tokens INTEGER,
+    cost REAL,
+    cached INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS runs_video ON runs (video_id, id);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS runs_fts USING fts5 (
+    title, summary, transcript,
+    content = 'runs', content_rowid = 'id'
+);
+CREATE TRIGGER IF NOT EXISTS runs_insert AFTER INSERT ON runs BEGIN
+    INSERT INTO runs_fts (rowid, title, summary, transcript)
+    VALUES (new.id, new.title, new.summary, new.transcript);
+END;
+CREATE TRIGGER IF NOT EXISTS runs_delete AFTER DELETE ON runs BEGIN
+    INSERT INTO runs_fts (runs_fts, rowid, title, summary, transcript)
+    VALUES ('delete', old.id, old.title, old.summary, old.transcript);
+END;
+";
+
+/// Columns read into a `Run`, in the order `run_from_row` expects
+const RUN_COLUMNS: &str = "id, created_at, video_id, title, url, provider, model, template, \
+     prompt, summary, prompt_tokens, completion_tokens, cost, cached";
+
+/// One summary run, as recorded in the history
+#[derive(Debug, Clone, Serialize)]
+pub struct Run {
+    pub id: i64,
+    /// Unix time in seconds
+    pub created_at: u64,
+    pub video_id: String,
+    pub title: Option<String>,
+    pub url: String,
+    pub provider: String,
+    pub model: String,
+    /// Name of the template the prompt came from, `None` for `--prompt`
+    pub template: Option<String>,
+    pub prompt: String,
+    pub summary: String,
+    pub usage: Option<UsageReport>,
+    /// Whether the summary was read from the cache
+    pub cached: bool,
+}
+
+impl Run {
+    /// The title, or the video ID if the title is unknown
+    pub fn name(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.video_id)
+    }
+}
+
+/// A run matching a search, with an excerpt around the match
+pub struct SearchHit {
+    pub run: Run,
+    /// Matched terms are marked as `[term]`
+    pub excerpt: String,
+}
+
+/// The database of past summaries
+pub struct History {
+    conn: Connection,
+}
+
+/// Returns `$XDG_DATA_HOME/youtube-summary`, or
+/// `~/.local/share/youtube-summary`
+pub fn data_dir() -> PathBuf {
+    let base = env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+            PathBuf::from(home).join(".local/share")
+        });
+
+    base.join("youtube-summary")
+}
+
+pub fn history_path() -> PathBuf {
+    data_dir().join("history.db")
+}
+
+fn db_error(e: rusqlite::Error) -> Error {
+    Error::History(e.to_string())
+}
+
+impl History {
+    /// Opens the history database, creating it if needed
+    pub fn open() -> Result<Self> {
+        History::open_at(&history_path())
+    }
+
+    /// Opens the history database at `path`, creating it and its directory
+    /// if needed
+    pub fn open_at(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| {
+                Error::History(format!("Failed to create {}: {}", dir.display(), e))
+            })?;
+        }
+
+        let conn = Connection::open(path)
+            .map_err(|e| Error::History(format!("Failed to open {}: {}", path.display(), e)))?;
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+
+        Ok(History { conn })
+    }
+
+    /// Records a run along with the transcript it summarized; the `id` of
+    /// `run` is ignored. Returns the ID of the new entry.
+    pub fn record(&self, run: &Run, transcript: &str) -> Result<i64> {
+        let usage = run.usage.as_ref();
+        self.conn
+            .execute(
+                "INSERT INTO runs (created_at, video_id, title, url, provider, model, template, \
+                 prompt, summary, transcript, prompt_tokens, completion_tokens, cost, cached) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                params![
+                    run.created_at as i64,
+                    run.video_id,
+                    run.title,
+                    run.url,
+                    run.provider,
+                    run.model,
+                    run.template,
+                    run.prompt,
+                    run.summary,
+                    transcript,
+                    usage.map(|u| u.prompt_tokens as i64),
+                    usage.map(|u| u.completion_tokens as i64),
+                    usage.and_then(|u| u.cost),
+                    run.cached,
+                ],
+            )
+            .map_err(db_error)?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Lists the most recent runs first, optionally only those of one video
+    pub fn list(&self, video_id: Option<&str>, limit: usize) -> Result<Vec<Run>> {
+        let sql = format!(
+            "SELECT {} FROM runs WHERE ?1 IS NULL OR video_id = ?1 ORDER BY id DESC LIMIT ?2",
+            RUN_COLUMNS
+        );
+        let mut statement = self.conn.prepare(&sql).map_err(db_error)?;
+        let runs = statement
+            .query_map(params![video_id, limit as i64], run_from_row)
+            .map_err(db_error)?
+            .collect::<rusqlite::Result<Vec<Run>>>()
+            .map_err(db_error)?;
+        Ok(runs)
+    }
+
+    pub fn get(&self, id: i64) -> Result<Run> {
+        let sql = format!("SELECT {} FROM runs WHERE id = ?1", RUN_COLUMNS);
+        self.conn
+            .query_row(&sql, params![id], run_from_row)
+            .optional()
+            .map_err(db_error)?
+            .ok_or_else(|| Error::History(format!("No history entry with ID {}", id)))
+    }
+
+    /// The latest run of a video
+    pub fn latest(&self, video_id: &str) -> Result<Run> {
+        self.list(Some(video_id), 1)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::History(format!("No history entries for video {}", video_id)))
+    }
+
+    /// The run of the same video before `run`, if any
+    pub fn previous(&self, run: &Run) -> Result<Option<Run>> {
+        let sql = format!(
+            "SELECT {} FROM runs WHERE video_id = ?1 AND id < ?2 ORDER BY id DESC LIMIT 1",
+            RUN_COLUMNS
+        );
+        self.conn
+            .query_row(&sql, params![run.video_id, run.id], run_from_row)
+            .optional()
+            .map_err(db_error)
+    }
+
+    /// Searches titles, summaries and transcripts, best matches first.
+    /// Every word of `query` must occur; words are matched as given, so
+    /// FTS syntax characters need no escaping.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+            .collect();
+        if terms.is_empty() {
+            return Err(Error::History("Empty search query".to_string()));
+        }
+
+        let columns: Vec<String> = RUN_COLUMNS
+            .split(", ")
+            .map(|column| format!("runs.{}", column))
+            .collect();
+        let sql = format!(
+            "SELECT {}, snippet(runs_fts, -1, '[', ']', '...', 16) FROM runs_fts \
+             JOIN runs ON runs.id = runs_fts.rowid \
+             WHERE runs_fts MATCH ?1 ORDER BY rank LIMIT ?2",
+            columns.join(", ")
+        );
+        let mut statement = self.conn.prepare(&sql).map_err(db_error)?;
+        let hits = statement
+            .query_map(params![terms.join(" "), limit as i64], |row| {
+                Ok(SearchHit {
+                    run: run_from_row(row)?,
+                    excerpt: row.get(14)?,
+                })
+            })
+            .map_err(db_error)?
+            .collect::<rusqlite::Result<Vec<SearchHit>>>()
+            .map_err(db_error)?;
+        Ok(hits)
+    }
+
+    /// All runs, oldest first
+    pub fn all(&self) -> Result<Vec<Run>> {
+        let mut runs = self.list(None, i64::MAX as usize)?;
+        runs.reverse();
+        Ok(runs)
+    }
+}
+
+fn run_from_row(row: &Row) -> rusqlite::Result<Run> {
+    let prompt_tokens: Option<i64> = row.get(10)?;
+    let completion_tokens: Option<i64> = row.get(11)?;
+    let usage = match (prompt_tokens, completion_tokens) {
+        (Some(prompt_tokens), Some(completion_tokens)) => Some(UsageReport {
+            prompt_tokens: prompt_tokens as u64,
+            completion_tokens: completion_tokens as u64,
+            total_tokens: (prompt_tokens + completion_tokens) as u64,
+            cost: row.get(12)?,
+        }),
+        _ => None,
+    };
+
+    Ok(Run {
+        id: row.get(0)?,
+        created_at: row.get::<_, i64>(1)? as u64,
+        video_id: row.get(2)?,
+        title: row.get(3)?,
+        url: row.get(4)?,
+        provider: row.get(5)?,
+        model: row.get(6)?,
+        template: row.get(7)?,
+        prompt: row.get(8)?,
+        summary: row.get(9)?,
+        usage,
+        cached: row.get(13)?,
+    })
+}
+
+/// The current Unix time in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// A unified diff between two summaries
+pub fn diff(old: &Run, new: &Run) -> String {
+    // Summaries are stored without a final newline
+    let old_text = format!("{}\n", old.summary.trim_end());
+    let new_text = format!("{}\n", new.summary.trim_end());
+    TextDiff::from_lines(&old_text, &new_text)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("#{}", old.id), &format!("#{}", new.id))
+        .to_string()
+}
+
+/// Writes runs as a Markdown document, one section per run
+pub fn to_markdown(runs: &[Run]) -> String {
+    let mut text = String::new();
+    for run in runs {
+        text.push_str(&format!(
+            "## {}\n\n#{} · {} · {} · {}\n\n{}\n\n",
+            run.name(),
+            run.id,
+            run.url,
+            run.model,
+            format_time(run.created_at),
+            run.summary.trim()
+        ));
+    }
+    text
+}
+
+/// Formats Unix time as `YYYY-MM-DD HH:MM` UTC
+pub fn format_time(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let minutes = secs % 86_400 / 60;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh database in the temp directory, removed when dropped
+    struct TempHistory {
+        history: History,
+        dir: PathBuf,
+    }
+
+    impl TempHistory {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!(
+                "youtube-summary-history-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            let history = History::open_at(&dir.join("nested/history.db")).unwrap();
+            TempHistory { history, dir }
+        }
+    }
+
+    impl Drop for TempHistory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn run(video_id: &str, title: Option<&str>, summary: &str) -> Run {
+        Run {
+            id: 0,
+            created_at: 1_700_000_000,
+            video_id: video_id.to_string(),
+            title: title.map(str::to_string),
+            url: format!("https://www.youtube.com/watch?v={}", video_id),
+            provider: "openai".to_string(),
+            model: "m1".to_string(),
+            template: Some("default".to_string()),
+            prompt: "Summarize".to_string(),
+            summary: summary.to_string(),
+            usage: Some(UsageReport {
+                prompt_tokens: 100,
+                completion_tokens: 20,
+                total_tokens: 120,
+                cost: Some(0.25),
+            }),
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn records_and_lists_runs() {
+        let temp = TempHistory::new("list");
+        let history = &temp.history;
+
+        let first = history
+            .record(&run("aaaaaaaaaaa", Some("First"), "One"), "hello")
+            .unwrap();
+        let second = history
+            .record(&run("bbbbbbbbbbb", None, "Two"), "world")
+            .unwrap();
+        let third = history
+            .record(&run("aaaaaaaaaaa", Some("First"), "Three"), "hello")
+            .unwrap();
+
+        let ids: Vec<i64> = history
+            .list(None, 10)
+            .unwrap()
+            .iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(ids, [third, second, first]);
+        let ids: Vec<i64> = history
+            .list(Some("aaaaaaaaaaa"), 10)
+            .unwrap()
+            .iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(ids, [third, first]);
+        assert_eq!(history.list(None, 1).unwrap().len(), 1);
+        let ids: Vec<i64> = history.all().unwrap().iter().map(|r| r.id).collect();
+        assert_eq!(ids, [first, second, third]);
+
+        let stored = history.get(second).unwrap();
+        assert_eq!(stored.name(), "bbbbbbbbbbb");
+        assert_eq!(stored.summary, "Two");
+        assert_eq!(stored.template.as_deref(), Some("default"));
+        let usage = stored.usage.unwrap();
+        assert_eq!((usage.prompt_tokens, usage.total_tokens), (100, 120));
+        assert_eq!(usage.cost, Some(0.25));
+        assert!(history.get(9999).is_err());
+
+        let latest = history.latest("aaaaaaaaaaa").unwrap();
+        assert_eq!(latest.id, third);
+        assert_eq!(history.previous(&latest).unwrap().unwrap().id, first);
+        assert!(
+            history
+                .previous(&history.get(first).unwrap())
+                .unwrap()
+                .is_none()
+        );
+        assert!(history.latest("ccccccccccc").is_err());
+    }
+
+    #[test]
+    fn searches_titles_summaries_and_transcripts() {
+        let temp = TempHistory::new("search");
+        let history = &temp.history;
+
+        let rust = history
+            .record(
+                &run("aaaaaaaaaaa", Some("Rust talk"), "About borrowing"),
+                "ownership",
+            )
+            .unwrap();
+        let quote = history
+            .record(
+                &run("bbbbbbbbbbb", None, r#"He said "hello" (twice) AND left"#),
+                "NEAR the end",
+            )
+            .unwrap();
+
+        let ids = |query: &str| -> Vec<i64> {
+            history
+                .search(query, 10)
+                .unwrap()
+                .iter()
+                .map(|hit| hit.run.id)
+                .collect()
+        };
+        assert_eq!(ids("rust"), [rust]);
+        assert_eq!(ids("borrowing ownership"), [rust]);
+        assert_eq!(ids("borrowing hello"), Vec::<i64>::new());
+        assert_eq!(ids(r#""hello""#), [quote]);
+        assert_eq!(ids(r#"said "hello"#), [quote]);
+        assert_eq!(ids("(twice) AND NEAR"), [quote]);
+        assert_eq!(ids("title: *"), Vec::<i64>::new());
+
+        let hits = history.search("borrowing", 10).unwrap();
+        assert_eq!(hits[0].excerpt, "About [borrowing]");
+        assert!(history.search("  ", 10).is_err());
+    }
+
+    #[test]
+    fn diffs_summaries() {
+        let mut old = run("aaaaaaaaaaa", None, "Same\nOld line\n");
+        old.id = 1;
+        let mut new = run("aaaaaaaaaaa", None, "Same\nNew line");
+        new.id = 2;
+
+        assert_eq!(
+            diff(&old, &new),
+            "--- #1\n+++ #2\n@@ -1,2 +1,2 @@\n Same\n-Old line\n+New line\n"
+        );
+        assert_eq!(diff(&old, &old), "");
+    }
+
+    #[test]
+    fn formats_times() {
+        let cases = [
+            (0, "1970-01-01 00:00"),
+            (59, "1970-01-01 00:00"),
+            (86_399, "1970-01-01 23:59"),
+            (951_782_400, "2000-02-29 00:00"),
+            (1_700_000_000, "2023-11-14 22:13"),
+            (1_709_251_199, "2024-02-29 23:59"),
+            (1_709_251_200, "2024-03-01 00:00"),
+            (4_107_542_400, "2100-03-01 00:00"),
+        ];
+        for (secs, expected) in cases {
+            assert_eq!(format_time(secs), expected, "{}", secs);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Summarizes YouTube videos from their transcripts with a language model.
+//!
+//! This crate is the library behind the `youtube-summary` command. The
+//! `Summarizer` builder covers the common case:
+//!
+//! ```no_run
+//! use youtube_summary::{Summarizer, VideoId};
+//!
+//! # async fn run() -> youtube_summary::Result<()> {
+//! let summarizer = Summarizer::builder()
+//!     .api_key(std::env::var("OPENROUTER_API_KEY").unwrap_or_default())
+//!     .model("openai/gpt-4o-mini")
+//!     .lang(["de", "en"])
+//!     .build()?;
+//!
+//! let video = VideoId::parse("https://youtu.be/dQw4w9WgXcQ")?;
+//! let summary = summarizer.summarize(&video).await?;
+//! println!("{}", summary.summary);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Transcripts can be fetched without a model using `fetch_transcript`.
+//! Nothing is read from the config file, the environment, the cache or the
+//! history unless asked for; the command line's config file layer builds a
+//! `Config` that `Summarizer::from_config` accepts. The modules expose the
+//! building blocks the command line is made of.
+
+pub mod cache;
+pub mod chunking;
+pub mod collection;
+pub mod config;
+pub mod cost;
+pub mod error;
+pub mod export;
+pub mod history;
+pub mod metadata;
+#[cfg(test)]
+mod mock_http;
+pub mod models;
+pub mod ollama;
+pub mod openai;
+pub mod openrouter;
+pub mod output;
+pub mod pipeline;
+pub mod provider;
+pub mod retry;
+pub mod sse;
+pub mod subtitles;
+pub mod summarize;
+pub mod summarizer;
+pub mod templates;
+pub mod timestamps;
+pub mod transcript;
+pub mod video_id;
+
+pub use config::Config;
+pub use error::{Error, Result};
+pub use pipeline::VideoSummary;
+pub use provider::ProviderKind;
+pub use retry::RetryPolicy;
+pub use summarizer::{Summarizer, SummarizerBuilder};
+pub use transcript::{Snippet, Transcript, TranscriptOptions};
+pub use video_id::VideoId;
+
+/// Fetches the transcript of a video from YouTube, bypassing the cache
+pub async fn fetch_transcript(video: &VideoId, options: &TranscriptOptions) -> Result<Transcript> {
+    transcript::fetch_transcript(video.as_str(), options).await
+}
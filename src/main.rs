@@ -1,17 +1,35 @@
+mod batch;
+mod chat;
 mod cli;
-mod config;
-mod error;
-mod openrouter;
-mod transcript;
+mod mcp;
+mod server;
+mod settings;
 
-use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use cli::Args;
-use config::Config;
-use error::Error;
+use youtube_summary::cache::{self, Cache, CachePolicy};
+use youtube_summary::collection::Collection;
+use youtube_summary::history::{self, History};
+use youtube_summary::provider::AnyProvider;
+use youtube_summary::video_id::VideoId;
+use youtube_summary::{cost, error, export, models, output, pipeline, templates, transcript};
+
+use cli::{
+    Args, CacheCommand, Command, ConfigCommand, ExportOptions, HistoryCommand, TemplatesCommand,
+};
+
+fn main() {
+    // Completion requests from the shell are answered before the runtime
+    // starts any threads, as they modify the environment
+    cli::complete();
+    start();
+}
 
 #[tokio::main]
-async fn main() {
+async fn start() {
     if let Err(e) = run().await {
         eprintln!("Error: {}", e);
         std::process::exit(1);
@@ -19,22 +37,119 @@ async fn main() {
 }
 
 async fn run() -> error::Result<()> {
-    // Parse CLI arguments
-    let args = Args::parse().map_err(|e| {
-        eprintln!("{}", e);
-        std::process::exit(0);
-    })?;
+    // Parse CLI arguments; help, --version and usage errors exit here
+    let args = Args::parse();
 
-    // Handle --list-models early (only needs API key)
-    if let Some(ref search) = args.list_models {
-        let api_key = get_api_key(&args)?;
-        return openrouter::list_models(&api_key, search.as_deref(), args.verbose).await;
+    match args.command {
+        // The cache needs no configuration
+        Command::Cache(ref command) => return run_cache_command(command),
+        // Listing models only needs the provider settings
+        Command::Models(ref search) => {
+            let provider = AnyProvider::new(&settings::load_provider(&args)?);
+            return models::list_models(&provider, search.as_deref(), args.verbose).await;
+        }
+        // Listing transcripts needs no API key
+        Command::ListTranscripts => {
+            let url = args.url.as_ref().unwrap();
+            let tracks = transcript::list_transcripts(url).await?;
+            print_tracks(&tracks);
+            return Ok(());
+        }
+        // Exporting a transcript needs no API key either
+        Command::ExportTranscript(ref options) => {
+            return run_export_command(&args, options).await;
+        }
+        Command::History(ref command) => return run_history_command(command),
+        Command::Templates(ref command) => return run_templates_command(command),
+        Command::Completions(ref shell) => {
+            return cli::write_completions(shell, &mut std::io::stdout())
+                .map_err(|e| error::Error::Output(format!("Failed to write completions: {}", e)));
+        }
+        Command::Man(ref output_dir) => return run_man_command(output_dir.as_deref()),
+        Command::Config(ConfigCommand::Path) => {
+            println!(
+                "{}",
+                settings::config_file_path(args.config_path.as_deref()).display()
+            );
+            return Ok(());
+        }
+        _ => {}
     }
 
     // Load full configuration for summarization
-    let config = Config::load(&args)?;
+    let mut config = settings::load(&args)?;
+
+    if let Command::Config(ConfigCommand::Show) = args.command {
+        print!("{}", settings::describe(&config));
+        return Ok(());
+    }
+
+    if let Command::Serve(ref options) = args.command {
+        return server::serve(config, options).await;
+    }
+
+    if let Command::Mcp = args.command {
+        return mcp::run(config).await;
+    }
+
+    if let Command::Chat = args.command {
+        // URL is guaranteed to be present here (required by the parser)
+        let url = args.url.as_ref().unwrap();
+        // Questions are read from stdin, so it can't hold the transcript
+        if url == "-" {
+            return Err(error::Error::Config(
+                "Chat needs a subtitle file or video, not stdin".to_string(),
+            ));
+        }
+        let cache = Cache::new(config.cache);
+        let transcript =
+            pipeline::load_input_transcript(url, &config.transcript, &cache, config.verbose)
+                .await?;
+        let provider = AnyProvider::new(&config.provider);
+        return chat::run(&provider, &config, transcript).await;
+    }
+
+    // Batches, playlists and channels summarize several videos
+    let inputs = match (&args.batch, &args.url) {
+        (Some(source), _) => Some(batch::read_inputs(source)?),
+        (None, Some(url)) if Collection::parse(url).is_some() => Some(vec![url.clone()]),
+        _ => None,
+    };
+
+    if let Some(inputs) = inputs {
+        // Interleaved streams from concurrent videos would be unreadable
+        config.stream = false;
+        let config = Arc::new(config);
+
+        let expansion = batch::expand(inputs, &config).await;
+        if config.verbose {
+            eprintln!(
+                "[verbose] Batch of {} videos, {} at a time",
+                expansion.videos.len(),
+                config.jobs
+            );
+        }
+
+        let output_dir = PathBuf::from(args.output_dir.as_deref().unwrap_or("."));
+        let mut results = expansion.failures;
+        results.extend(batch::run(expansion.videos, Arc::clone(&config), &output_dir).await?);
+
+        if config.overview {
+            let overviews =
+                batch::write_overviews(&expansion.collections, &results, &config, &output_dir)
+                    .await;
+            results.extend(overviews);
+        }
 
-    // URL is guaranteed to be present here (checked in Args::parse)
+        batch::print_report(&results);
+
+        if results.iter().any(|result| result.outcome.is_err()) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // URL is guaranteed to be present here (required without --batch)
     let url = args.url.as_ref().unwrap();
 
     if config.verbose {
@@ -42,56 +157,347 @@ async fn run() -> error::Result<()> {
         eprintln!("[verbose] Fetching transcript...");
     }
 
-    // Fetch transcript
-    let transcript = transcript::fetch_transcript(url).await?;
+    let cache = Cache::new(config.cache);
+    let provider = AnyProvider::new(&config.provider);
+    let result = pipeline::summarize_video(url, &config, &cache, &provider).await?;
 
-    if config.verbose {
-        eprintln!("[verbose] Transcript fetched: {} chars", transcript.len());
+    // Print the summary (already written to stdout when streaming)
+    if result.streamed {
+        println!();
+    } else {
+        println!("{}", output::render(&result, &config)?);
     }
 
-    // Send to OpenRouter for summarization
-    let summary = openrouter::summarize(&config, &transcript).await?;
+    Ok(())
+}
 
-    // Print the summary
-    println!("{}", summary);
+fn print_tracks(tracks: &[transcript::TrackInfo]) {
+    if tracks.is_empty() {
+        println!("No transcripts available");
+        return;
+    }
+
+    println!("{:<10} {:<15} {:<13} NAME", "CODE", "TYPE", "TRANSLATABLE");
+    println!("{}", "-".repeat(70));
+
+    for track in tracks {
+        let kind = if track.is_generated {
+            "auto-generated"
+        } else {
+            "manual"
+        };
+        let translatable = if track.is_translatable { "yes" } else { "no" };
+
+        println!(
+            "{:<10} {:<15} {:<13} {}",
+            track.language_code, kind, translatable, track.language
+        );
+    }
+}
+
+fn run_cache_command(command: &CacheCommand) -> error::Result<()> {
+    // The policy only affects reads and writes of entries
+    let cache = Cache::new(CachePolicy::Use);
+
+    match command {
+        CacheCommand::List => {
+            let entries = cache.entries()?;
+            if entries.is_empty() {
+                println!("Cache is empty ({})", cache::cache_dir().display());
+                return Ok(());
+            }
+
+            println!("{:<11} {:<45} {:>10} {:>6}", "KIND", "KEY", "SIZE", "AGE");
+            println!("{}", "-".repeat(75));
+
+            for entry in &entries {
+                println!(
+                    "{:<11} {:<45} {:>10} {:>6}",
+                    entry.kind,
+                    entry.key,
+                    cache::format_size(entry.size),
+                    cache::format_age(entry.modified)
+                );
+            }
+
+            let total: u64 = entries.iter().map(|entry| entry.size).sum();
+            println!(
+                "\n{} entries, {} in {}",
+                entries.len(),
+                cache::format_size(total),
+                cache::cache_dir().display()
+            );
+        }
+        CacheCommand::Show { key } => {
+            let entry = cache.find(key)?;
+            println!("{}", cache.describe(&entry)?);
+        }
+        CacheCommand::Prune {
+            older_than,
+            max_size,
+        } => {
+            let max_age = older_than.as_deref().map(cache::parse_age).transpose()?;
+            let max_size = max_size.as_deref().map(cache::parse_size).transpose()?;
+            let stats = cache.prune(max_age, max_size)?;
+            println!(
+                "Removed {} entries, freed {}",
+                stats.removed,
+                cache::format_size(stats.freed)
+            );
+        }
+        CacheCommand::Clear => {
+            let stats = cache.clear()?;
+            println!(
+                "Removed {} entries, freed {}",
+                stats.removed,
+                cache::format_size(stats.freed)
+            );
+        }
+    }
 
     Ok(())
 }
 
-/// Get API key for list-models command (simpler than full Config::load)
-fn get_api_key(args: &Args) -> error::Result<String> {
-    // Try CLI argument first
-    if let Some(ref key) = args.api_key {
-        return Ok(key.clone());
+async fn run_export_command(args: &Args, options: &ExportOptions) -> error::Result<()> {
+    let format = match options.format.as_deref() {
+        Some(name) => export::ExportFormat::parse(name)?,
+        None => export::ExportFormat::default(),
+    };
+
+    // URL is guaranteed to be present here (required by the parser)
+    let url = args.url.as_ref().unwrap();
+    let (transcript_options, policy) = settings::load_transcript_settings(args)?;
+    let cache = Cache::new(policy);
+    let mut transcript =
+        pipeline::load_input_transcript(url, &transcript_options, &cache, args.verbose).await?;
+
+    if options.strip_markers {
+        transcript = export::strip_markers(&transcript);
+    }
+    if options.sentences {
+        transcript = export::merge_sentences(&transcript);
     }
+    let text = export::render(&transcript, format)?;
 
-    // Try environment variable
-    if let Ok(key) = env::var("OPENROUTER_API_KEY") {
-        return Ok(key);
+    match options.output.as_deref() {
+        Some(path) if path != "-" => {
+            fs::write(path, text)
+                .map_err(|e| error::Error::Output(format!("Failed to write {}: {}", path, e)))?;
+            if args.verbose {
+                eprintln!(
+                    "[verbose] Wrote {} snippets to {}",
+                    transcript.snippets.len(),
+                    path
+                );
+            }
+        }
+        _ => print!("{}", text),
     }
 
-    // Try credentials file
-    let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    let creds_path = std::path::PathBuf::from(home).join(".config/youtube-summary/credentials");
+    Ok(())
+}
 
-    if creds_path.exists()
-        && let Ok(content) = std::fs::read_to_string(&creds_path)
-    {
-        for line in content.lines() {
-            let line = line.trim();
-            if line.starts_with('#') || line.is_empty() {
-                continue;
+fn run_history_command(command: &HistoryCommand) -> error::Result<()> {
+    let history = History::open()?;
+
+    match command {
+        HistoryCommand::List { video, limit } => {
+            let video_id = video.as_deref().map(history_video_id);
+            let runs = history.list(video_id.as_deref(), *limit)?;
+            if runs.is_empty() {
+                println!("History is empty ({})", history::history_path().display());
+                return Ok(());
             }
-            if let Some((key, value)) = line.split_once('=')
-                && key.trim() == "OPENROUTER_API_KEY"
-            {
-                let value = value.trim().trim_matches('"').trim_matches('\'');
-                return Ok(value.to_string());
+
+            println!(
+                "{:>5} {:<16} {:<11} {:<30} TITLE",
+                "ID", "DATE", "VIDEO", "MODEL"
+            );
+            println!("{}", "-".repeat(90));
+            for run in &runs {
+                println!(
+                    "{:>5} {:<16} {:<11} {:<30} {}",
+                    run.id,
+                    history::format_time(run.created_at),
+                    run.video_id,
+                    run.model,
+                    run.name()
+                );
+            }
+        }
+        HistoryCommand::Show { id, diff } => {
+            let run = match id.parse::<i64>() {
+                Ok(id) => history.get(id)?,
+                Err(_) => history.latest(&history_video_id(id))?,
+            };
+
+            if *diff {
+                match history.previous(&run)? {
+                    Some(previous) => print!("{}", history::diff(&previous, &run)),
+                    None => println!("No earlier summary of {} to compare with", run.video_id),
+                }
+                return Ok(());
+            }
+
+            println!("ID:       {}", run.id);
+            println!("Date:     {}", history::format_time(run.created_at));
+            println!("Title:    {}", run.name());
+            println!("URL:      {}", run.url);
+            println!("Model:    {} ({})", run.model, run.provider);
+            println!("Template: {}", run.template.as_deref().unwrap_or("-"));
+            if let Some(ref usage) = run.usage {
+                let cost = usage
+                    .cost
+                    .map(|cost| format!(", {}", cost::format_cost(cost)))
+                    .unwrap_or_default();
+                println!(
+                    "Usage:    {} prompt + {} completion tokens{}",
+                    usage.prompt_tokens, usage.completion_tokens, cost
+                );
+            }
+            println!("Prompt:   {}\n\n{}", run.prompt, run.summary);
+        }
+        HistoryCommand::Search { query, limit } => {
+            let hits = history.search(&query.join(" "), *limit)?;
+            if hits.is_empty() {
+                println!("No matches");
+                return Ok(());
+            }
+
+            for hit in &hits {
+                println!(
+                    "#{} {} ({}, {})\n    {}\n",
+                    hit.run.id,
+                    hit.run.name(),
+                    hit.run.video_id,
+                    history::format_time(hit.run.created_at),
+                    hit.excerpt.split_whitespace().collect::<Vec<_>>().join(" ")
+                );
+            }
+        }
+        HistoryCommand::Export { format, output } => {
+            let runs = history.all()?;
+            let text = match format.as_deref().unwrap_or("json") {
+                "json" => serde_json::to_string_pretty(&runs)
+                    .map(|json| json + "\n")
+                    .map_err(|e| {
+                        error::Error::Output(format!("Failed to serialize history: {}", e))
+                    })?,
+                "markdown" | "md" => history::to_markdown(&runs),
+                other => {
+                    return Err(error::Error::Config(format!(
+                        "Unknown history format '{}' (expected json or markdown)",
+                        other
+                    )));
+                }
+            };
+
+            match output.as_deref() {
+                Some(path) if path != "-" => fs::write(path, text).map_err(|e| {
+                    error::Error::Output(format!("Failed to write {}: {}", path, e))
+                })?,
+                _ => print!("{}", text),
             }
         }
     }
 
-    Err(Error::Config(
-        "No API key found. Set OPENROUTER_API_KEY env var or use --api-key".to_string(),
-    ))
+    Ok(())
+}
+
+/// The video ID recorded for a URL or video ID; local subtitle files are
+/// recorded under their name
+fn history_video_id(input: &str) -> String {
+    VideoId::parse(input)
+        .map(String::from)
+        .unwrap_or_else(|_| input.to_string())
+}
+
+fn run_templates_command(command: &TemplatesCommand) -> error::Result<()> {
+    match command {
+        TemplatesCommand::List => {
+            println!("{:<16} {:<26} DESCRIPTION", "NAME", "SOURCE");
+            println!("{}", "-".repeat(100));
+            for template in templates::list()? {
+                println!(
+                    "{:<16} {:<26} {}",
+                    template.name,
+                    template.source.name(),
+                    template.description()
+                );
+            }
+            println!(
+                "\nUser templates are stored in {}",
+                templates::templates_dir().display()
+            );
+        }
+        TemplatesCommand::Show { name } => {
+            let template = templates::find(name)?;
+            let variables = templates::variables(&template.body)?;
+            println!("Name:       {}", template.name);
+            println!("Source:     {}", template.source.name());
+            if !variables.is_empty() {
+                println!("Variables:  {}", variables.join(", "));
+            }
+            println!("\n{}", template.body);
+        }
+        TemplatesCommand::Edit { name } => {
+            let path = templates::edit(name)?;
+            println!("Saved {}", path.display());
+        }
+        TemplatesCommand::New { name, from } => {
+            let path = templates::create(name, from.as_deref())?;
+            println!(
+                "Created {}; edit it with `youtube-summary templates edit {}`",
+                path.display(),
+                name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the man page, or writes it and one page per subcommand into
+/// `output_dir`
+fn run_man_command(output_dir: Option<&str>) -> error::Result<()> {
+    let mut command = cli::command();
+    command.build();
+
+    let Some(output_dir) = output_dir else {
+        return clap_mangen::Man::new(command)
+            .render(&mut io::stdout())
+            .map_err(|e| error::Error::Output(format!("Failed to write man page: {}", e)));
+    };
+
+    let output_dir = Path::new(output_dir);
+    fs::create_dir_all(output_dir).map_err(|e| {
+        error::Error::Output(format!("Failed to create {}: {}", output_dir.display(), e))
+    })?;
+
+    let name = command.get_name().to_string();
+    let mut pages = vec![(name.clone(), command.clone())];
+    for subcommand in command
+        .get_subcommands()
+        .filter(|s| !s.is_hide_set() && s.get_name() != "help")
+    {
+        pages.push((
+            format!("{}-{}", name, subcommand.get_name()),
+            subcommand.clone(),
+        ));
+    }
+
+    for (page, command) in pages {
+        let path = output_dir.join(format!("{}.1", page));
+        let mut buffer = Vec::new();
+        clap_mangen::Man::new(command)
+            .title(page)
+            .render(&mut buffer)
+            .and_then(|()| fs::write(&path, buffer))
+            .map_err(|e| {
+                error::Error::Output(format!("Failed to write {}: {}", path.display(), e))
+            })?;
+        println!("Wrote {}", path.display());
+    }
+
+    Ok(())
 }
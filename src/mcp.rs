@@ -0,0 +1,375 @@
+use std::io::{self, BufRead, Write};
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use youtube_summary::cache::Cache;
+use youtube_summary::config::{Config, Overrides, parse_language_list};
+use youtube_summary::error::{Error, Result};
+use youtube_summary::export::{self, ExportFormat};
+use youtube_summary::models;
+use youtube_summary::output::{self, OutputFormat};
+use youtube_summary::pipeline;
+use youtube_summary::provider::{AnyProvider, Provider};
+use youtube_summary::video_id::VideoId;
+
+/// Protocol versions the server speaks, newest first
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const INSTRUCTIONS: &str = "Fetches transcripts of YouTube videos and summarizes them. \
+     Videos are given as a URL or an 11-character video ID. Transcripts contain [mm:ss] \
+     markers; cite them when referring to a part of the video.";
+
+// Standard JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+// Server-defined codes for tools that fail
+const TRANSCRIPT_ERROR: i64 = -32001;
+const PROVIDER_ERROR: i64 = -32002;
+const BUDGET_ERROR: i64 = -32003;
+
+/// A JSON-RPC error object
+struct RpcError {
+    code: i64,
+    message: String,
+    /// The `Error` variant, and the HTTP status for provider errors
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut error = json!({ "code": self.code, "message": self.message });
+        if let Some(ref data) = self.data {
+            error["data"] = data.clone();
+        }
+        error
+    }
+}
+
+impl From<Error> for RpcError {
+    fn from(e: Error) -> Self {
+        let code = match e {
+            Error::InvalidYoutubeUrl(_) | Error::Config(_) => INVALID_PARAMS,
+            Error::TranscriptFetch(_) => TRANSCRIPT_ERROR,
+            Error::ApiRequest(_) | Error::ApiStatus { .. } => PROVIDER_ERROR,
+            Error::Budget(_) => BUDGET_ERROR,
+            Error::Cache(_) | Error::History(_) | Error::Output(_) => INTERNAL_ERROR,
+        };
+        let mut data = json!({ "kind": e.kind() });
+        if let Error::ApiStatus { status, .. } = e {
+            data["status"] = json!(status);
+        }
+        RpcError {
+            code,
+            message: e.to_string(),
+            data: Some(data),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptArgs {
+    video: String,
+    lang: Option<String>,
+    translate: Option<String>,
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SummarizeArgs {
+    video: String,
+    #[serde(flatten)]
+    overrides: Overrides,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsArgs {
+    search: Option<String>,
+}
+
+struct Server {
+    config: Config,
+    provider: AnyProvider,
+    cache: Cache,
+}
+
+/// Answers JSON-RPC requests on stdin, one message per line, until stdin
+/// closes. Nothing but responses may be written to stdout; logs go to
+/// stderr.
+pub async fn run(mut config: Config) -> Result<()> {
+    // Streamed tokens would be written to stdout
+    config.stream = false;
+    config.format = OutputFormat::Text;
+
+    let server = Server {
+        provider: AnyProvider::new(&config.provider),
+        cache: Cache::new(config.cache),
+        config,
+    };
+    if server.config.verbose {
+        eprintln!("[verbose] MCP server ready on stdin");
+    }
+
+    let mut stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|e| Error::Output(format!("Failed to read stdin: {}", e)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle(&line).await {
+            writeln!(stdout, "{}", response)
+                .and_then(|_| stdout.flush())
+                .map_err(|e| Error::Output(format!("Failed to write response: {}", e)))?;
+        }
+    }
+
+    Ok(())
+}
+
+impl Server {
+    /// Handles one message; notifications get no response
+    async fn handle(&self, line: &str) -> Option<Value> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = RpcError::new(PARSE_ERROR, format!("Parse error: {}", e));
+                return Some(response(Value::Null, Err(error)));
+            }
+        };
+
+        let id = message.get("id").cloned();
+        let method = message.get("method").and_then(Value::as_str);
+        let (Some(method), Some("2.0")) = (method, message["jsonrpc"].as_str()) else {
+            let error = RpcError::new(INVALID_REQUEST, "Invalid request");
+            return Some(response(id.unwrap_or(Value::Null), Err(error)));
+        };
+
+        // Notifications such as `notifications/initialized` need no action
+        let id = id?;
+
+        if self.config.verbose {
+            eprintln!("[verbose] MCP request: {}", method);
+        }
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        Some(response(id, self.dispatch(method, params).await))
+    }
+
+    async fn dispatch(&self, method: &str, params: Value) -> std::result::Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => {
+                let call: ToolCall = parse_params(params)?;
+                let text = self.call_tool(&call).await?;
+                Ok(json!({
+                    "content": [{ "type": "text", "text": text }],
+                    "isError": false,
+                }))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", method),
+            )),
+        }
+    }
+
+    async fn call_tool(&self, call: &ToolCall) -> std::result::Result<String, RpcError> {
+        match call.name.as_str() {
+            "get_transcript" => {
+                let args: TranscriptArgs = parse_params(call.arguments.clone())?;
+                Ok(self.get_transcript(&args).await?)
+            }
+            "summarize_video" => {
+                let args: SummarizeArgs = parse_params(call.arguments.clone())?;
+                Ok(self.summarize_video(&args).await?)
+            }
+            "list_models" => {
+                let args: ModelsArgs = parse_params(call.arguments.clone())?;
+                Ok(self.list_models(&args).await?)
+            }
+            name => Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("Unknown tool: {}", name),
+            )),
+        }
+    }
+
+    async fn get_transcript(&self, args: &TranscriptArgs) -> Result<String> {
+        // Only YouTube videos; clients can't have local files read
+        let video = VideoId::parse(&args.video)?;
+        let format = match args.format.as_deref() {
+            Some(name) => ExportFormat::parse(name)?,
+            None => ExportFormat::Markdown,
+        };
+
+        let mut options = self.config.transcript.clone();
+        if let Some(ref languages) = args.lang {
+            options.languages = parse_language_list(languages)?;
+        }
+        if args.translate.is_some() {
+            options.translate_to = args.translate.clone();
+        }
+
+        let transcript =
+            pipeline::load_transcript(video.as_str(), &options, &self.cache, self.config.verbose)
+                .await?;
+        export::render(&transcript, format)
+    }
+
+    async fn summarize_video(&self, args: &SummarizeArgs) -> Result<String> {
+        // Only YouTube videos; clients can't have local files read
+        let video = VideoId::parse(&args.video)?;
+        let config = self.config.with_overrides(&args.overrides)?;
+        let result =
+            pipeline::summarize_video(&video.url(), &config, &self.cache, &self.provider).await?;
+        output::render(&result, &config)
+    }
+
+    async fn list_models(&self, args: &ModelsArgs) -> Result<String> {
+        let all_models = self.provider.list_models().await?;
+        let models = models::filter_models(&all_models, args.search.as_deref());
+        serde_json::to_string_pretty(&models)
+            .map_err(|e| Error::Output(format!("Failed to serialize models: {}", e)))
+    }
+}
+
+fn response(id: Value, result: std::result::Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error.to_json() }),
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    // Tools without arguments may be called without the object
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid params: {}", e)))
+}
+
+/// Agrees on the client's protocol version if supported, else offers the
+/// newest one
+fn initialize(params: &Value) -> Value {
+    let requested = params["protocolVersion"].as_str();
+    let version = PROTOCOL_VERSIONS
+        .iter()
+        .find(|&&version| Some(version) == requested)
+        .unwrap_or(&PROTOCOL_VERSIONS[0]);
+
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": { "listChanged": false } },
+        "serverInfo": {
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "instructions": INSTRUCTIONS,
+    })
+}
+
+/// The tools with their input schemas
+fn tools() -> Value {
+    let video = json!({
+        "type": "string",
+        "description": "YouTube video URL or 11-character video ID",
+    });
+    let lang = json!({
+        "type": "string",
+        "description": "Preferred transcript languages, comma separated, e.g. \"de,en\"",
+    });
+
+    json!([
+        {
+            "name": "get_transcript",
+            "title": "Get transcript",
+            "description": "Fetches the transcript of a YouTube video",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "video": video,
+                    "lang": lang,
+                    "translate": {
+                        "type": "string",
+                        "description": "Language code to have YouTube translate the transcript into",
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["text", "srt", "vtt", "json", "markdown"],
+                        "description": "Output format; markdown has timestamped paragraphs",
+                        "default": "markdown",
+                    },
+                },
+                "required": ["video"],
+            },
+        },
+        {
+            "name": "summarize_video",
+            "title": "Summarize video",
+            "description": "Summarizes a YouTube video from its transcript with the configured language model",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "video": video,
+                    "prompt": {
+                        "type": "string",
+                        "description": "Instructions for the summary; may use {{title}}, {{channel}} and other template variables, and \\{{ for literal braces",
+                    },
+                    "template": {
+                        "type": "string",
+                        "description": "Name of a prompt template, e.g. tldr; instead of prompt",
+                    },
+                    "model": {
+                        "type": "string",
+                        "description": "Model ID; see list_models",
+                    },
+                    "lang": lang,
+                    "output_lang": {
+                        "type": "string",
+                        "description": "Language to write the summary in",
+                    },
+                    "vars": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                        "description": "Values of custom template variables",
+                    },
+                },
+                "required": ["video"],
+            },
+        },
+        {
+            "name": "list_models",
+            "title": "List models",
+            "description": "Lists the models of the configured provider as JSON",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "search": {
+                        "type": "string",
+                        "description": "Only models whose ID or name contains this term",
+                    },
+                },
+            },
+        },
+    ])
+}
@@ -0,0 +1,355 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::collection;
+use crate::error::{Error, Result};
+use crate::timestamps;
+use crate::transcript::{CONSENT_COOKIE, USER_AGENT};
+
+/// Longest part of the description included in the prompt; descriptions
+/// often end in long lists of links and sponsors
+const MAX_PROMPT_DESCRIPTION: usize = 1500;
+
+/// Minimum number of timestamped description lines YouTube requires
+/// before it shows them as chapters
+const MIN_DESCRIPTION_CHAPTERS: usize = 3;
+
+/// What YouTube knows about a video besides its captions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VideoMetadata {
+    pub video_id: String,
+    pub title: String,
+    /// Name of the channel that published the video
+    pub channel: String,
+    pub channel_id: Option<String>,
+    /// Upload date in YYYY-MM-DD form
+    pub upload_date: Option<String>,
+    /// Length in seconds
+    pub duration: u64,
+    pub description: String,
+    pub chapters: Vec<Chapter>,
+}
+
+/// A chapter of a video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chapter {
+    pub title: String,
+    /// Start time in seconds
+    pub start: u64,
+}
+
+impl VideoMetadata {
+    /// Describes the video for the model, placed before the transcript
+    pub fn prompt_context(&self) -> String {
+        let mut text = format!(
+            "Video information:\nTitle: {}\nChannel: {}\n",
+            self.title, self.channel
+        );
+        if let Some(ref date) = self.upload_date {
+            text.push_str(&format!("Uploaded: {}\n", date));
+        }
+        if self.duration > 0 {
+            text.push_str(&format!(
+                "Duration: {}\n",
+                timestamps::format_timestamp(self.duration)
+            ));
+        }
+        if !self.chapters.is_empty() {
+            text.push_str("Chapters:\n");
+            for chapter in &self.chapters {
+                text.push_str(&format!(
+                    "- [{}] {}\n",
+                    timestamps::format_timestamp(chapter.start),
+                    chapter.title
+                ));
+            }
+        }
+
+        let description = self.description.trim();
+        if !description.is_empty() {
+            let mut chars = description.chars();
+            let short: String = chars.by_ref().take(MAX_PROMPT_DESCRIPTION).collect();
+            let more = if chars.next().is_some() { " [...]" } else { "" };
+            text.push_str(&format!("Description:\n{}{}\n", short, more));
+        }
+
+        text
+    }
+
+    /// A Markdown header naming the video, printed above its summary
+    pub fn header(&self, url: &str) -> String {
+        let mut details = vec![self.channel.clone()];
+        if let Some(ref date) = self.upload_date {
+            details.push(date.clone());
+        }
+        if self.duration > 0 {
+            details.push(timestamps::format_timestamp(self.duration));
+        }
+
+        format!(
+            "# {}\n\n{}\n{}\n\n",
+            self.title,
+            details.join(" · "),
+            watch_url(url, &self.video_id)
+        )
+    }
+}
+
+/// The canonical watch URL, unless the input already is a URL
+fn watch_url(input: &str, video_id: &str) -> String {
+    if input.starts_with("http://") || input.starts_with("https://") {
+        input.to_string()
+    } else {
+        format!("https://www.youtube.com/watch?v={}", video_id)
+    }
+}
+
+/// Fetches the watch page of a video and reads its metadata
+pub async fn fetch_metadata(video_id: &str) -> Result<VideoMetadata> {
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to create HTTP client: {}", e)))?;
+
+    let html = client
+        .get(format!("https://www.youtube.com/watch?v={}", video_id))
+        .header("Accept-Language", "en-US,en")
+        .header("Cookie", CONSENT_COOKIE)
+        .send()
+        .await
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to fetch watch page: {}", e)))?
+        .error_for_status()
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to fetch watch page: {}", e)))?
+        .text()
+        .await
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to read watch page: {}", e)))?;
+
+    parse_watch_page(&html)
+}
+
+/// Reads the metadata from the HTML of a watch page: details from the
+/// embedded player response, chapters from the initial data or, failing
+/// that, from timestamps in the description
+pub fn parse_watch_page(html: &str) -> Result<VideoMetadata> {
+    let player =
+        collection::extract_variable(html, "ytInitialPlayerResponse").ok_or_else(|| {
+            Error::TranscriptFetch("Watch page contains no player response".to_string())
+        })?;
+
+    let details = &player["videoDetails"];
+    let video_id = details["videoId"]
+        .as_str()
+        .ok_or_else(|| Error::TranscriptFetch("Watch page has no video details".to_string()))?
+        .to_string();
+
+    let microformat = &player["microformat"]["playerMicroformatRenderer"];
+    let upload_date = microformat["uploadDate"]
+        .as_str()
+        .or(microformat["publishDate"].as_str())
+        // Dates may carry a time, e.g. "2009-10-24T23:57:33-07:00"
+        .map(|date| date.chars().take(10).collect());
+
+    let description = details["shortDescription"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+
+    let mut chapters = collection::extract_variable(html, "ytInitialData")
+        .map(|data| parse_chapters(&data))
+        .unwrap_or_default();
+    if chapters.is_empty() {
+        chapters = parse_description_chapters(&description);
+    }
+
+    Ok(VideoMetadata {
+        video_id,
+        title: details["title"].as_str().unwrap_or_default().to_string(),
+        channel: details["author"].as_str().unwrap_or_default().to_string(),
+        channel_id: details["channelId"].as_str().map(str::to_string),
+        upload_date,
+        duration: details["lengthSeconds"]
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default(),
+        description,
+        chapters,
+    })
+}
+
+/// Collects the chapters shown in the player bar
+pub fn parse_chapters(data: &Value) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+    walk_chapters(data, &mut chapters);
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters.dedup_by_key(|chapter| chapter.start);
+    chapters
+}
+
+fn walk_chapters(value: &Value, chapters: &mut Vec<Chapter>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(renderer) = map.get("chapterRenderer")
+                && let Some(title) = collection::text(&renderer["title"])
+                && let Some(millis) = renderer["timeRangeStartMillis"].as_u64()
+            {
+                chapters.push(Chapter {
+                    title,
+                    start: millis / 1000,
+                });
+                return;
+            }
+            for child in map.values() {
+                walk_chapters(child, chapters);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                walk_chapters(item, chapters);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Reads chapters from description lines such as "0:00 Intro" or
+/// "12:34 - Results", following YouTube's rule that the list starts at
+/// 0:00 and has at least three entries
+pub fn parse_description_chapters(description: &str) -> Vec<Chapter> {
+    let chapters = parse_chapter_lines(description);
+
+    let valid = chapters.first().is_some_and(|first| first.start == 0)
+        && chapters.len() >= MIN_DESCRIPTION_CHAPTERS;
+    if valid { chapters } else { Vec::new() }
+}
+
+/// Reads the lines of `text` that start with a timestamp, optionally in
+/// brackets, as chapters. Stops at the first timestamp that isn't later
+/// than the one before.
+pub fn parse_chapter_lines(text: &str) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches(['-', '*']).trim_start();
+        let Some((time, title)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let time = time
+            .trim_start_matches(['(', '['])
+            .trim_end_matches([')', ']']);
+        let Some(start) = timestamps::parse_timestamp(time) else {
+            continue;
+        };
+        let title = title
+            .trim()
+            .trim_start_matches(['-', '–', '—', ':', '|'])
+            .trim();
+        if title.is_empty() {
+            continue;
+        }
+
+        // Timestamps out of order start some other list, e.g. a tracklist
+        if chapters.last().is_some_and(|last| start <= last.start) {
+            break;
+        }
+        chapters.push(Chapter {
+            title: title.to_string(),
+            start,
+        });
+    }
+
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(html: &str) -> VideoMetadata {
+        parse_watch_page(html).unwrap()
+    }
+
+    fn chapters(metadata: &VideoMetadata) -> Vec<(u64, &str)> {
+        metadata
+            .chapters
+            .iter()
+            .map(|c| (c.start, c.title.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_a_page_with_chapters() {
+        let metadata = fixture(include_str!("../tests/fixtures/watch_chapters.html"));
+        assert_eq!(metadata.video_id, "dQw4w9WgXcQ");
+        assert_eq!(metadata.title, "Ownership explained");
+        assert_eq!(metadata.channel, "Example Channel");
+        assert_eq!(metadata.channel_id.as_deref(), Some("UCexample"));
+        assert_eq!(metadata.upload_date.as_deref(), Some("2023-04-30"));
+        assert_eq!(metadata.duration, 723);
+        assert!(metadata.description.starts_with("How ownership works."));
+        assert_eq!(
+            chapters(&metadata),
+            [(0, "Intro"), (95, "Moves"), (400, "Borrowing")]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_description_chapters() {
+        let metadata = fixture(include_str!(
+            "../tests/fixtures/watch_description_chapters.html"
+        ));
+        assert_eq!(metadata.video_id, "a-b_c-d_e-f");
+        assert_eq!(metadata.upload_date.as_deref(), Some("2024-01-15"));
+        assert_eq!(metadata.duration, 3725);
+        assert_eq!(
+            chapters(&metadata),
+            [
+                (0, "Intro"),
+                (150, "Tokens"),
+                (900, "Grammar"),
+                (3665, "Wrap-up")
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_a_page_without_chapters() {
+        let metadata = fixture(include_str!("../tests/fixtures/watch_no_chapters.html"));
+        assert_eq!(metadata.title, "Short clip");
+        assert_eq!(metadata.channel_id, None);
+        assert_eq!(metadata.upload_date, None);
+        assert_eq!(metadata.duration, 42);
+        assert!(metadata.chapters.is_empty());
+    }
+
+    #[test]
+    fn rejects_a_consent_wall() {
+        let error = parse_watch_page(include_str!("../tests/fixtures/consent.html")).unwrap_err();
+        assert!(matches!(error, Error::TranscriptFetch(_)));
+    }
+
+    #[test]
+    fn rejects_a_player_response_without_details() {
+        let html = r#"<script>var ytInitialPlayerResponse = {"playabilityStatus":{"status":"ERROR"}};</script>"#;
+        assert!(parse_watch_page(html).is_err());
+    }
+
+    #[test]
+    fn description_chapters_need_three_entries_from_zero() {
+        assert!(parse_description_chapters("0:00 A\n1:00 B").is_empty());
+        assert!(parse_description_chapters("0:10 A\n1:00 B\n2:00 C").is_empty());
+        assert_eq!(
+            parse_description_chapters("0:00 A\n1:00 B\n2:00 C").len(),
+            3
+        );
+        assert!(parse_description_chapters("0:00\n1:00\n2:00").is_empty());
+    }
+
+    #[test]
+    fn builds_the_header() {
+        let metadata = fixture(include_str!("../tests/fixtures/watch_chapters.html"));
+        assert_eq!(
+            metadata.header("dQw4w9WgXcQ"),
+            "# Ownership explained\n\nExample Channel · 2023-04-30 · 12:03\nhttps://www.youtube.com/watch?v=dQw4w9WgXcQ\n\n"
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! A local HTTP server answering with scripted responses, for testing the
+//! backends without a real one
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use crate::retry::RetryPolicy;
+
+/// Pause after each part of a body, so that the client reads the parts
+/// as separate chunks
+const PART_PAUSE: Duration = Duration::from_millis(20);
+
+/// Answers every request with `status` and a body written in `parts`,
+/// closing the connection after the last one. Returns the base URL.
+pub fn serve(status: &str, content_type: &str, parts: &[&str]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nConnection: close\r\n\r\n",
+        status, content_type
+    );
+    let parts: Vec<String> = parts.iter().map(|part| part.to_string()).collect();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    length = value.trim().parse().unwrap_or(0);
+                }
+                line.clear();
+            }
+            let mut body = vec![0; length];
+            let _ = std::io::Read::read_exact(&mut reader, &mut body);
+
+            let _ = stream.write_all(head.as_bytes());
+            for part in &parts {
+                let _ = stream.write_all(part.as_bytes());
+                let _ = stream.flush();
+                thread::sleep(PART_PAUSE);
+            }
+        }
+    });
+
+    url
+}
+
+/// A policy that sends each request once
+pub fn no_retries() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 1,
+        ..RetryPolicy::default()
+    }
+}
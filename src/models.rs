@@ -0,0 +1,147 @@
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::error::Result;
+use crate::provider::{ModelInfo, Pricing, Provider};
+
+pub async fn list_models<P: Provider>(
+    provider: &P,
+    search: Option<&str>,
+    verbose: bool,
+) -> Result<()> {
+    if verbose {
+        eprintln!("[verbose] Fetching models from {}...", provider.name());
+    }
+
+    let all_models = provider.list_models().await?;
+
+    let models = filter_models(&all_models, search);
+
+    if models.is_empty() {
+        if let Some(term) = search {
+            println!("No models found matching '{}'", term);
+        } else {
+            println!("No models found");
+        }
+        return Ok(());
+    }
+
+    // Print header
+    println!(
+        "{:<45} {:<40} {:>8}   PRICING (per 1M tokens)",
+        "MODEL ID", "NAME", "CONTEXT"
+    );
+    println!("{}", "-".repeat(120));
+
+    // Print models
+    for model in &models {
+        let context = model
+            .context_length
+            .map(format_context)
+            .unwrap_or_else(|| "N/A".to_string());
+
+        let pricing = model
+            .pricing
+            .as_ref()
+            .map(format_pricing)
+            .unwrap_or_else(|| "N/A".to_string());
+
+        // Truncate long names
+        let id = truncate(&model.id, 44);
+        let name = truncate(&model.name, 39);
+
+        println!("{:<45} {:<40} {:>8}   {}", id, name, context, pricing);
+    }
+
+    if verbose {
+        eprintln!("\n[verbose] Total models displayed: {}", models.len());
+    }
+
+    Ok(())
+}
+
+/// The models whose ID or name contains `search`, ignoring case; all
+/// models without a search term
+pub fn filter_models<'a>(models: &'a [ModelInfo], search: Option<&str>) -> Vec<&'a ModelInfo> {
+    models
+        .iter()
+        .filter(|m| {
+            if let Some(term) = search {
+                let term_lower = term.to_lowercase();
+                m.id.to_lowercase().contains(&term_lower)
+                    || m.name.to_lowercase().contains(&term_lower)
+            } else {
+                true
+            }
+        })
+        .collect()
+}
+
+/// Looks up a model in the provider's model list. The list is cached on
+/// disk for a day, so pricing and context lengths are known without a
+/// round trip on every run.
+pub async fn model_info<P: Provider>(
+    provider: &P,
+    config: &Config,
+    model: &str,
+) -> Result<Option<ModelInfo>> {
+    let cache = Cache::new(config.cache);
+    let kind = config.provider.kind.name();
+    let base_url = config.provider.base_url.as_deref().unwrap_or_default();
+
+    let models = match cache.get_models(kind, base_url) {
+        Some(models) => models,
+        None => {
+            if config.verbose {
+                eprintln!("[verbose] Fetching models from {}...", provider.name());
+            }
+            let models = provider.list_models().await?;
+            if let Err(e) = cache.put_models(kind, base_url, &models)
+                && config.verbose
+            {
+                eprintln!("[verbose] {}", e);
+            }
+            models
+        }
+    };
+
+    Ok(models.into_iter().find(|m| m.id == model))
+}
+
+fn format_context(context_length: u64) -> String {
+    if context_length >= 1_000_000 {
+        format!("{}M", context_length / 1_000_000)
+    } else if context_length >= 1_000 {
+        format!("{}k", context_length / 1_000)
+    } else {
+        format!("{}", context_length)
+    }
+}
+
+fn format_pricing(pricing: &Pricing) -> String {
+    // Parse the pricing strings (they're in dollars per token)
+    // Convert to per million tokens for readability
+    let prompt_per_million = parse_price(&pricing.prompt);
+    let completion_per_million = parse_price(&pricing.completion);
+
+    match (prompt_per_million, completion_per_million) {
+        (Some(p), Some(c)) => format!("${:.2} / ${:.2}", p, c),
+        _ => "N/A".to_string(),
+    }
+}
+
+fn parse_price(price_str: &str) -> Option<f64> {
+    let price: f64 = price_str.parse().ok()?;
+    if price < 0.0 {
+        return None; // Free or special pricing
+    }
+    // Price is per token, convert to per million
+    Some(price * 1_000_000.0)
+}
+
+fn truncate(s: &str, max_len: usize) -> String {
+    if s.len() <= max_len {
+        s.to_string()
+    } else {
+        format!("{}...", &s[..max_len - 3])
+    }
+}
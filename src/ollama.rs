@@ -0,0 +1,435 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::provider::{self, ChatRequest, Completion, ModelInfo, Pricing, Provider, Usage};
+use crate::retry::RetryPolicy;
+
+pub const BASE_URL: &str = "http://localhost:11434";
+
+/// Upper bound for the context window requested from Ollama. Ollama
+/// allocates the full window up front, so very large contexts can exhaust
+/// memory on local machines.
+const MAX_CONTEXT_LENGTH: u64 = 32_768;
+
+/// Backend for a local Ollama server
+pub struct Ollama {
+    client: reqwest::Client,
+    base_url: String,
+    retry: RetryPolicy,
+    verbose: bool,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    model: &'a str,
+    messages: Vec<Message<'a>>,
+    stream: bool,
+    options: Options,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct Options {
+    num_predict: u32,
+    num_ctx: u64,
+}
+
+/// Non-streaming responses and each line of a streaming response share
+/// the same shape
+#[derive(Deserialize)]
+struct Response {
+    model: Option<String>,
+    message: Option<ResponseMessage>,
+    #[serde(default)]
+    done: bool,
+    error: Option<String>,
+    /// Token counts, present on the final message
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
+}
+
+impl Response {
+    fn usage(&self) -> Option<Usage> {
+        if self.prompt_eval_count.is_none() && self.eval_count.is_none() {
+            return None;
+        }
+        Some(Usage {
+            prompt_tokens: self.prompt_eval_count.unwrap_or_default(),
+            completion_tokens: self.eval_count.unwrap_or_default(),
+            cost: None,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    models: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct ShowRequest<'a> {
+    model: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ShowResponse {
+    #[serde(default)]
+    model_info: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Ollama {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: Option<&str>,
+        retry: RetryPolicy,
+        verbose: bool,
+    ) -> Self {
+        Ollama {
+            client,
+            base_url: base_url
+                .unwrap_or(BASE_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            retry,
+            verbose,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn error_from_response(response: reqwest::Response) -> Error {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+
+        let message = serde_json::from_str::<ErrorResponse>(&error_text)
+            .map(|e| e.error)
+            .unwrap_or(error_text);
+
+        Error::ApiStatus {
+            status: status.as_u16(),
+            message: format!("API error ({}): {}", status, message),
+        }
+    }
+}
+
+impl Provider for Ollama {
+    fn name(&self) -> &str {
+        "Ollama"
+    }
+
+    async fn complete(&self, request: &ChatRequest, stream: bool) -> Result<Completion> {
+        let body = Request {
+            model: &request.model,
+            messages: request
+                .messages
+                .iter()
+                .map(|m| Message {
+                    role: &m.role,
+                    content: &m.content,
+                })
+                .collect(),
+            stream,
+            options: Options {
+                num_predict: request.max_tokens,
+                num_ctx: request.context_length.min(MAX_CONTEXT_LENGTH),
+            },
+        };
+
+        let request = self.client.post(self.url("/api/chat")).json(&body);
+        let response = self
+            .retry
+            .send(request, self.verbose)
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to send request: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(Self::error_from_response(response).await);
+        }
+
+        if stream {
+            return read_stream(response).await;
+        }
+
+        let response: Response = response
+            .json()
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to parse response: {}", e)))?;
+
+        if let Some(error) = response.error {
+            return Err(Error::ApiRequest(format!("API error: {}", error)));
+        }
+
+        Ok(Completion {
+            model: response.model.clone(),
+            usage: response.usage(),
+            text: response.message.map(|m| m.content).unwrap_or_default(),
+        })
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let request = self.client.get(self.url("/api/tags"));
+        let response = self
+            .retry
+            .send(request, self.verbose)
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to fetch models: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(Self::error_from_response(response).await);
+        }
+
+        let tags: TagsResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to parse models response: {}", e)))?;
+
+        Ok(tags
+            .models
+            .into_iter()
+            .map(|tag| ModelInfo {
+                id: tag.name.clone(),
+                name: tag.name,
+                context_length: None,
+                // Local models cost nothing per token
+                pricing: Some(Pricing {
+                    prompt: "0".to_string(),
+                    completion: "0".to_string(),
+                }),
+            })
+            .collect())
+    }
+
+    async fn context_length(&self, model: &str) -> Result<Option<u64>> {
+        let request = self
+            .client
+            .post(self.url("/api/show"))
+            .json(&ShowRequest { model });
+        let response = self
+            .retry
+            .send(request, self.verbose)
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to fetch model details: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(Self::error_from_response(response).await);
+        }
+
+        let show: ShowResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to parse model details: {}", e)))?;
+
+        // The key is prefixed with the model architecture, e.g. "llama.context_length"
+        let context_length = show
+            .model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64());
+
+        Ok(context_length.map(|length| length.min(MAX_CONTEXT_LENGTH)))
+    }
+}
+
+/// Reads Ollama's newline-delimited JSON stream, writing each token to
+/// stdout as it arrives and returning the full text
+async fn read_stream(mut response: reqwest::Response) -> Result<Completion> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut text = String::new();
+    let mut usage = None;
+    let mut model = None;
+
+    loop {
+        let chunk = response
+            .chunk()
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to read stream: {}", e)))?;
+
+        let finished = chunk.is_none();
+        match chunk {
+            Some(bytes) => buffer.extend_from_slice(&bytes),
+            // Treat a trailing unterminated line as complete
+            None => buffer.push(b'\n'),
+        }
+
+        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let parsed: Response = serde_json::from_str(line)
+                .map_err(|e| Error::ApiRequest(format!("Failed to parse stream chunk: {}", e)))?;
+
+            if let Some(error) = parsed.error {
+                return Err(Error::ApiRequest(format!("Stream error: {}", error)));
+            }
+
+            if let Some(reported) = parsed.usage() {
+                usage = Some(reported);
+            }
+            if parsed.model.is_some() {
+                model = parsed.model;
+            }
+
+            if let Some(message) = parsed.message {
+                provider::emit_token(&mut text, &message.content)?;
+            }
+
+            if parsed.done {
+                return Ok(Completion { text, model, usage });
+            }
+        }
+
+        if finished {
+            return Ok(Completion { text, model, usage });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mock_http;
+    use crate::provider::ChatMessage;
+
+    fn backend(url: &str) -> Ollama {
+        Ollama::new(
+            reqwest::Client::new(),
+            Some(url),
+            mock_http::no_retries(),
+            false,
+        )
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            model: "llama3".to_string(),
+            fallback_models: Vec::new(),
+            messages: vec![ChatMessage::user("Hello".to_string())],
+            max_tokens: 100,
+            context_length: 8192,
+        }
+    }
+
+    async fn stream(parts: &[&str]) -> Result<Completion> {
+        let url = mock_http::serve("200 OK", "application/x-ndjson", parts);
+        backend(&url).complete(&request(), true).await
+    }
+
+    #[tokio::test]
+    async fn maps_errors() {
+        let cases = [
+            (
+                "404 Not Found",
+                r#"{"error":"model 'llama9' not found"}"#,
+                404,
+                "API error (404 Not Found): model 'llama9' not found",
+            ),
+            (
+                "500 Internal Server Error",
+                "runner crashed",
+                500,
+                "API error (500 Internal Server Error): runner crashed",
+            ),
+        ];
+        for (status_line, body, expected_status, expected) in cases {
+            let url = mock_http::serve(status_line, "application/json", &[body]);
+            match backend(&url).complete(&request(), false).await {
+                Err(Error::ApiStatus { status, message }) => {
+                    assert_eq!(status, expected_status);
+                    assert_eq!(message, expected);
+                }
+                other => panic!("expected a status error, got {:?}", other.map(|c| c.text)),
+            }
+        }
+
+        // Errors reported with a success status
+        let url = mock_http::serve(
+            "200 OK",
+            "application/json",
+            &[r#"{"error":"out of memory"}"#],
+        );
+        match backend(&url).complete(&request(), false).await {
+            Err(Error::ApiRequest(message)) => assert_eq!(message, "API error: out of memory"),
+            other => panic!("expected an error, got {:?}", other.map(|c| c.text)),
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_a_streamed_completion() {
+        let completion = stream(&[
+            "{\"model\":\"llama3\",\"message\":{\"content\":\"Hel\"},\"done\":false}\n",
+            "\n",
+            // A line split across chunks
+            "{\"model\":\"llama3\",\"message\":{\"con",
+            "tent\":\"lo\"},\"done\":false}\r\n",
+            "{\"model\":\"llama3\",\"message\":{\"content\":\"\"},\"done\":true,",
+            "\"prompt_eval_count\":5,\"eval_count\":2}\n",
+            "{\"message\":{\"content\":\" ignored\"},\"done\":false}\n",
+        ])
+        .await
+        .unwrap();
+
+        assert_eq!(completion.text, "Hello");
+        assert_eq!(completion.model.as_deref(), Some("llama3"));
+        assert_eq!(
+            completion.usage,
+            Some(Usage {
+                prompt_tokens: 5,
+                completion_tokens: 2,
+                cost: None,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_an_unterminated_last_line() {
+        let completion = stream(&["{\"message\":{\"content\":\"Partial\"},\"done\":false}"])
+            .await
+            .unwrap();
+        assert_eq!(completion.text, "Partial");
+    }
+
+    #[tokio::test]
+    async fn fails_on_a_mid_stream_error() {
+        let result = stream(&[
+            "{\"message\":{\"content\":\"Par\"},\"done\":false}\n",
+            "{\"error\":\"model runner has unexpectedly stopped\"}\n",
+        ])
+        .await;
+        match result {
+            Err(Error::ApiRequest(message)) => {
+                assert_eq!(
+                    message,
+                    "Stream error: model runner has unexpectedly stopped"
+                )
+            }
+            other => panic!("expected a stream error, got {:?}", other.map(|c| c.text)),
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::provider::{self, ChatRequest, Completion, ModelInfo, Provider, Usage};
+use crate::retry::RetryPolicy;
+use crate::sse::SseParser;
+
+/// Backend for any server exposing the OpenAI chat completions API,
+/// such as the llama.cpp server, vLLM or LM Studio
+pub struct OpenAiCompatible {
+    client: reqwest::Client,
+    name: String,
+    base_url: String,
+    api_key: Option<String>,
+    retry: RetryPolicy,
+    verbose: bool,
+    /// Whether fallback models are sent in the `models` routing field
+    model_routing: bool,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    model: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    models: Vec<&'a str>,
+    max_tokens: u32,
+    messages: Vec<Message<'a>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    /// Asks for a final chunk carrying the usage of the whole stream
+    include_usage: bool,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    model: Option<String>,
+    choices: Vec<Choice>,
+    usage: Option<RawUsage>,
+}
+
+#[derive(Deserialize)]
+struct RawUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+    /// Reported by OpenRouter
+    cost: Option<f64>,
+}
+
+impl From<RawUsage> for Usage {
+    fn from(usage: RawUsage) -> Self {
+        Usage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            cost: usage.cost,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamChunk {
+    model: Option<String>,
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    usage: Option<RawUsage>,
+    error: Option<ApiError>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    delta: Option<Delta>,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+/// Error payloads differ between servers: OpenAI and llama.cpp nest an
+/// object under `error`, some servers use a plain string, and vLLM puts
+/// the message at the top level
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ErrorResponse {
+    Nested { error: ApiError },
+    Flat { error: String },
+    TopLevel { message: String },
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ModelsResponse {
+    data: Vec<Model>,
+}
+
+#[derive(Deserialize)]
+struct Model {
+    id: String,
+    /// Reported by vLLM
+    max_model_len: Option<u64>,
+}
+
+impl OpenAiCompatible {
+    pub fn new(
+        client: reqwest::Client,
+        name: &str,
+        base_url: &str,
+        api_key: Option<String>,
+        retry: RetryPolicy,
+        verbose: bool,
+    ) -> Self {
+        OpenAiCompatible {
+            client,
+            name: name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            retry,
+            verbose,
+            model_routing: false,
+        }
+    }
+
+    /// Lets the server fall back to the request's fallback models on its
+    /// own, through OpenRouter's `models` routing field
+    pub fn with_model_routing(mut self) -> Self {
+        self.model_routing = true;
+        self
+    }
+
+    /// Builds a request to an endpoint below the base URL, authenticated
+    /// with the API key if one is configured
+    pub fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let builder = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path));
+
+        match &self.api_key {
+            Some(key) => builder.header("Authorization", format!("Bearer {}", key)),
+            None => builder,
+        }
+    }
+
+    /// Sends a request according to the retry policy
+    pub async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        self.retry.send(request, self.verbose).await
+    }
+
+    /// Turns a non-success response into an `Error::ApiStatus`
+    pub async fn error_from_response(response: reqwest::Response) -> Error {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+
+        let message = match serde_json::from_str::<ErrorResponse>(&error_text) {
+            Ok(ErrorResponse::Nested { error }) => error.message,
+            Ok(ErrorResponse::Flat { error }) => error,
+            Ok(ErrorResponse::TopLevel { message }) => message,
+            Err(_) => error_text,
+        };
+
+        Error::ApiStatus {
+            status: status.as_u16(),
+            message: format!("API error ({}): {}", status, message),
+        }
+    }
+}
+
+impl Provider for OpenAiCompatible {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn complete(&self, request: &ChatRequest, stream: bool) -> Result<Completion> {
+        let models = if self.model_routing && !request.fallback_models.is_empty() {
+            std::iter::once(&request.model)
+                .chain(&request.fallback_models)
+                .map(String::as_str)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let body = Request {
+            model: &request.model,
+            models,
+            max_tokens: request.max_tokens,
+            messages: request
+                .messages
+                .iter()
+                .map(|m| Message {
+                    role: &m.role,
+                    content: &m.content,
+                })
+                .collect(),
+            stream,
+            stream_options: stream.then_some(StreamOptions {
+                include_usage: true,
+            }),
+        };
+
+        let response = self
+            .send(
+                self.request(reqwest::Method::POST, "/chat/completions")
+                    .header("Content-Type", "application/json")
+                    .json(&body),
+            )
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to send request: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(Self::error_from_response(response).await);
+        }
+
+        if stream {
+            return read_stream(response).await;
+        }
+
+        let response: Response = response
+            .json()
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to parse response: {}", e)))?;
+
+        Ok(Completion {
+            model: response.model,
+            text: response
+                .choices
+                .into_iter()
+                .filter_map(|choice| choice.message.content)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            usage: response.usage.map(Usage::from),
+        })
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let response = self
+            .send(self.request(reqwest::Method::GET, "/models"))
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to fetch models: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(Self::error_from_response(response).await);
+        }
+
+        let models_response: ModelsResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to parse models response: {}", e)))?;
+
+        Ok(models_response
+            .data
+            .into_iter()
+            .map(|m| ModelInfo {
+                name: m.id.clone(),
+                id: m.id,
+                context_length: m.max_model_len,
+                pricing: None,
+            })
+            .collect())
+    }
+}
+
+/// Reads a server-sent event completion stream, writing each token to
+/// stdout as it arrives and returning the full text
+async fn read_stream(mut response: reqwest::Response) -> Result<Completion> {
+    let mut parser = SseParser::new();
+    let mut text = String::new();
+    let mut usage = None;
+    let mut model = None;
+
+    loop {
+        let chunk = response
+            .chunk()
+            .await
+            .map_err(|e| Error::ApiRequest(format!("Failed to read stream: {}", e)))?;
+
+        let finished = chunk.is_none();
+        let events = match chunk {
+            Some(bytes) => parser.feed(&bytes),
+            None => parser.finish().into_iter().collect(),
+        };
+
+        for event in events {
+            if event.trim() == "[DONE]" {
+                return Ok(Completion { text, model, usage });
+            }
+
+            let parsed: StreamChunk = serde_json::from_str(&event)
+                .map_err(|e| Error::ApiRequest(format!("Failed to parse stream chunk: {}", e)))?;
+
+            if let Some(error) = parsed.error {
+                return Err(Error::ApiRequest(format!(
+                    "Stream error: {}",
+                    error.message
+                )));
+            }
+
+            if let Some(reported) = parsed.usage {
+                usage = Some(Usage::from(reported));
+            }
+            if parsed.model.is_some() {
+                model = parsed.model;
+            }
+
+            for content in parsed
+                .choices
+                .into_iter()
+                .filter_map(|choice| choice.delta.and_then(|delta| delta.content))
+            {
+                provider::emit_token(&mut text, &content)?;
+            }
+        }
+
+        // Some servers close the connection without sending [DONE]
+        if finished {
+            return Ok(Completion { text, model, usage });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mock_http;
+    use crate::provider::ChatMessage;
+
+    fn backend(url: &str) -> OpenAiCompatible {
+        let base_url = format!("{}/v1", url);
+        let retry = mock_http::no_retries();
+        OpenAiCompatible::new(
+            reqwest::Client::new(),
+            "test",
+            &base_url,
+            None,
+            retry,
+            false,
+        )
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            model: "m1".to_string(),
+            fallback_models: Vec::new(),
+            messages: vec![ChatMessage::user("Hello".to_string())],
+            max_tokens: 100,
+            context_length: 8192,
+        }
+    }
+
+    async fn stream(parts: &[&str]) -> Result<Completion> {
+        let url = mock_http::serve("200 OK", "text/event-stream", parts);
+        backend(&url).complete(&request(), true).await
+    }
+
+    #[tokio::test]
+    async fn reads_every_error_shape() {
+        let cases = [
+            (
+                r#"{"error":{"message":"Invalid model","type":"invalid_request_error"}}"#,
+                "Invalid model",
+            ),
+            (r#"{"error":"Invalid model"}"#, "Invalid model"),
+            (
+                r#"{"object":"error","message":"Invalid model","code":400}"#,
+                "Invalid model",
+            ),
+            ("Bad request body", "Bad request body"),
+            (r#"{"detail":"other"}"#, r#"{"detail":"other"}"#),
+        ];
+        for (body, expected) in cases {
+            let url = mock_http::serve("400 Bad Request", "application/json", &[body]);
+            match backend(&url).complete(&request(), false).await {
+                Err(Error::ApiStatus { status, message }) => {
+                    assert_eq!(status, 400);
+                    assert_eq!(
+                        message,
+                        format!("API error (400 Bad Request): {}", expected)
+                    );
+                }
+                other => panic!("expected a status error, got {:?}", other.map(|c| c.text)),
+            }
+        }
+
+        let url = mock_http::serve("404 Not Found", "application/json", &[r#"{"error":"x"}"#]);
+        match backend(&url).list_models().await {
+            Err(Error::ApiStatus { status, .. }) => assert_eq!(status, 404),
+            other => panic!("expected a status error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_a_streamed_completion() {
+        let completion = stream(&[
+            ": keep-alive\n\n",
+            "data: {\"model\":\"m1-2024\",\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+            ": keep-alive\n\n",
+            // An event split within a line and between its lines
+            "data: {\"choices\":[{\"delta\":",
+            "{\"content\":\"lo\"}}]}\r\n",
+            "\r\ndata: {\"choices\":[],\"usage\":{\"prompt_tokens\":5,\"completion_tokens\":2}}\n\nda",
+            "ta: [DONE]\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" ignored\"}}]}\n\n",
+        ])
+        .await
+        .unwrap();
+
+        assert_eq!(completion.text, "Hello");
+        assert_eq!(completion.model.as_deref(), Some("m1-2024"));
+        assert_eq!(
+            completion.usage,
+            Some(Usage {
+                prompt_tokens: 5,
+                completion_tokens: 2,
+                cost: None,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn ends_a_stream_closed_without_done() {
+        let completion = stream(&["data: {\"choices\":[{\"delta\":{\"content\":\"Partial\"}}]}"])
+            .await
+            .unwrap();
+        assert_eq!(completion.text, "Partial");
+        assert_eq!(completion.usage, None);
+    }
+
+    #[tokio::test]
+    async fn fails_on_a_mid_stream_error() {
+        let result = stream(&[
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Par\"}}]}\n\n",
+            "data: {\"error\":{\"message\":\"Model overloaded\",\"code\":502}}\n\n",
+            "data: [DONE]\n\n",
+        ])
+        .await;
+        match result {
+            Err(Error::ApiRequest(message)) => {
+                assert_eq!(message, "Stream error: Model overloaded")
+            }
+            other => panic!("expected a stream error, got {:?}", other.map(|c| c.text)),
+        }
+
+        let result = stream(&["data: {not json}\n\n"]).await;
+        assert!(matches!(result, Err(Error::ApiRequest(_))));
+    }
+}
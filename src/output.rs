@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::metadata::VideoMetadata;
+use crate::pipeline::VideoSummary;
+use crate::provider::Usage;
+
+/// Version of the JSON document. Fields may be added within a version;
+/// renaming or removing a field bumps it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How summaries are printed and written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "text" | "markdown" | "md" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(Error::Config(format!(
+                "Unknown output format '{}' (expected text or json)",
+                other
+            ))),
+        }
+    }
+
+    /// File extension used for summaries written in batch mode
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "md",
+            OutputFormat::Json => "json",
+        }
+    }
+}
+
+/// The document printed by `--format json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummaryDocument {
+    pub schema_version: u32,
+    pub video_id: String,
+    pub url: String,
+    pub transcript_language: String,
+    /// Title, channel, upload date, duration, description and chapters;
+    /// `null` if the watch page couldn't be read
+    pub metadata: Option<VideoMetadata>,
+    pub provider: String,
+    /// The model that produced the summary, after any fallbacks
+    pub model: String,
+    pub prompt: String,
+    pub summary: String,
+    /// Whether the summary was read from the cache instead of generated
+    pub cached: bool,
+    /// Summed over all requests of a chunked summary; `null` for cached
+    /// summaries and providers that report no usage
+    pub usage: Option<UsageReport>,
+    pub timings: TimingsReport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    /// Cost in dollars as reported by the provider, or else estimated from
+    /// the model's pricing; `null` if neither is known
+    pub cost: Option<f64>,
+}
+
+impl From<Usage> for UsageReport {
+    fn from(usage: Usage) -> Self {
+        UsageReport {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.prompt_tokens + usage.completion_tokens,
+            cost: usage.cost,
+        }
+    }
+}
+
+/// Wall clock times in milliseconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimingsReport {
+    pub transcript_ms: u64,
+    pub summary_ms: u64,
+    pub total_ms: u64,
+}
+
+impl SummaryDocument {
+    pub fn new(result: &VideoSummary, config: &Config) -> Self {
+        SummaryDocument {
+            schema_version: SCHEMA_VERSION,
+            video_id: result.video_id.clone(),
+            url: result.url.clone(),
+            transcript_language: result.transcript_language.clone(),
+            metadata: result.metadata.clone(),
+            provider: config.provider.kind.name().to_string(),
+            model: result.model.clone(),
+            prompt: result.prompt.clone(),
+            summary: result.summary.clone(),
+            cached: result.cached,
+            usage: result.usage.map(UsageReport::from),
+            timings: TimingsReport {
+                transcript_ms: result.timings.transcript.as_millis() as u64,
+                summary_ms: result.timings.summary.as_millis() as u64,
+                total_ms: result.timings.total.as_millis() as u64,
+            },
+        }
+    }
+}
+
+/// Renders a video summary in the configured format
+pub fn render(result: &VideoSummary, config: &Config) -> Result<String> {
+    match config.format {
+        OutputFormat::Text => Ok(match result.metadata {
+            Some(ref metadata) => format!("{}{}", metadata.header(&result.url), result.summary),
+            None => result.summary.clone(),
+        }),
+        OutputFormat::Json => serde_json::to_string_pretty(&SummaryDocument::new(result, config))
+            .map_err(|e| Error::Output(format!("Failed to serialize summary: {}", e))),
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::cache::{Cache, SummaryKey};
+use crate::config::Config;
+use crate::error::Result;
+use crate::history::{self, History, Run};
+use crate::metadata::{self, VideoMetadata};
+use crate::output::UsageReport;
+use crate::provider::{Provider, Usage};
+use crate::subtitles;
+use crate::summarize;
+use crate::templates;
+use crate::timestamps;
+use crate::transcript::{self, Transcript, TranscriptOptions};
+use crate::video_id::VideoId;
+
+/// The final summary of one video
+pub struct VideoSummary {
+    pub video_id: String,
+    pub url: String,
+    pub transcript_language: String,
+    /// `None` if the watch page couldn't be read
+    pub metadata: Option<VideoMetadata>,
+    /// The prompt sent, with the template variables filled in
+    pub prompt: String,
+    pub summary: String,
+    /// The model that produced the summary, which differs from the
+    /// configured one if a fallback model was used
+    pub model: String,
+    /// `None` for cached summaries and providers that report no usage
+    pub usage: Option<Usage>,
+    /// Whether the summary was read from the cache
+    pub cached: bool,
+    /// Whether the summary was already written to stdout while generated
+    pub streamed: bool,
+    pub timings: Timings,
+}
+
+/// Wall clock time spent in each stage of the pipeline
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timings {
+    pub transcript: Duration,
+    pub summary: Duration,
+    pub total: Duration,
+}
+
+/// Fetches the transcript of a video, unless it is cached
+pub async fn load_transcript(
+    video_id: &str,
+    options: &TranscriptOptions,
+    cache: &Cache,
+    verbose: bool,
+) -> Result<Transcript> {
+    if let Some(transcript) = cache.get_transcript(video_id, options) {
+        if verbose {
+            eprintln!("[verbose] Using cached transcript");
+        }
+        return Ok(transcript);
+    }
+
+    let transcript = transcript::fetch_transcript(video_id, options).await?;
+    if let Err(e) = cache.put_transcript(&transcript, options)
+        && verbose
+    {
+        eprintln!("[verbose] {}", e);
+    }
+    Ok(transcript)
+}
+
+/// Reads the transcript of a local subtitle file, or fetches that of a
+/// YouTube video
+pub async fn load_input_transcript(
+    input: &str,
+    options: &TranscriptOptions,
+    cache: &Cache,
+    verbose: bool,
+) -> Result<Transcript> {
+    if subtitles::is_local(input) {
+        return subtitles::read_subtitles(input, options);
+    }
+    let video = VideoId::parse(input)?;
+    load_transcript(video.as_str(), options, cache, verbose).await
+}
+
+/// Fetches the metadata of a video, unless it is cached. Summaries work
+/// without metadata, so failures are only logged.
+pub async fn load_metadata(
+    video_id: &str,
+    config: &Config,
+    cache: &Cache,
+) -> Option<VideoMetadata> {
+    if let Some(metadata) = cache.get_metadata(video_id) {
+        if config.verbose {
+            eprintln!("[verbose] Using cached metadata");
+        }
+        return Some(metadata);
+    }
+
+    match metadata::fetch_metadata(video_id).await {
+        Ok(metadata) => {
+            if let Err(e) = cache.put_metadata(&metadata)
+                && config.verbose
+            {
+                eprintln!("[verbose] {}", e);
+            }
+            Some(metadata)
+        }
+        Err(e) => {
+            if config.verbose {
+                eprintln!("[verbose] Continuing without video metadata: {}", e);
+            }
+            None
+        }
+    }
+}
+
+/// Runs the whole pipeline for one video: fetches the transcript,
+/// summarizes it and post-processes the result, using the cache for both
+/// the transcript and the summary
+pub async fn summarize_video<P: Provider>(
+    url: &str,
+    config: &Config,
+    cache: &Cache,
+    provider: &P,
+) -> Result<VideoSummary> {
+    let started = Instant::now();
+
+    // Local subtitle files have neither a watch page nor cached summaries,
+    // as the file may have changed since
+    let local = subtitles::is_local(url);
+    let (video, transcript, metadata) = if local {
+        let transcript = subtitles::read_subtitles(url, &config.transcript)?;
+        (None, transcript, None)
+    } else {
+        let video = VideoId::parse(url)?;
+        let (transcript, metadata) = tokio::join!(
+            load_transcript(video.as_str(), &config.transcript, cache, config.verbose),
+            load_metadata(video.as_str(), config, cache)
+        );
+        (Some(video), transcript?, metadata)
+    };
+    let video_id = transcript.video_id.clone();
+    let transcript_time = started.elapsed();
+
+    if config.verbose {
+        eprintln!("[verbose] Transcript language: {}", transcript.language);
+        eprintln!(
+            "[verbose] Transcript fetched: {} chars",
+            transcript.text_len()
+        );
+    }
+
+    let prompt = templates::render(
+        &config.prompt,
+        &variables(url, video.as_ref(), &transcript, metadata.as_ref(), config),
+    )?;
+    let config = &Config {
+        prompt,
+        ..config.clone()
+    };
+
+    let summary_key = SummaryKey {
+        video_id: &video_id,
+        language: &transcript.language,
+        provider: config.provider.kind.name(),
+        base_url: config.provider.base_url.as_deref().unwrap_or_default(),
+        model: &config.model,
+        prompt: &config.prompt,
+        timestamps: config.timestamps,
+        output_language: config.output_language.as_deref(),
+        chapters: config
+            .chapters
+            .source(metadata.as_ref())
+            .map(|source| source.name()),
+    };
+
+    // Send to the configured provider for summarization, unless cached
+    let summary_started = Instant::now();
+    let cached_summary = if local {
+        None
+    } else {
+        cache.get_summary(&summary_key)
+    };
+    let cached = cached_summary.is_some();
+    let streamed = config.stream && !cached;
+    let (mut summary, model, usage) = match cached_summary {
+        Some((summary, model)) => {
+            if config.verbose {
+                eprintln!("[verbose] Using cached summary by {}", model);
+            }
+            (summary, model, None)
+        }
+        None => {
+            // The header goes out before the streamed text
+            if streamed && let Some(ref metadata) = metadata {
+                print!("{}", metadata.header(url));
+            }
+            let summary =
+                summarize::summarize(provider, config, &transcript, metadata.as_ref()).await?;
+            if !local
+                && let Err(e) = cache.put_summary(&summary_key, &summary.text, &summary.model)
+                && config.verbose
+            {
+                eprintln!("[verbose] {}", e);
+            }
+            if config.verbose {
+                eprintln!("[verbose] Summary produced by {}", summary.model);
+            }
+            (summary.text, summary.model, summary.usage)
+        }
+    };
+    let summary_time = summary_started.elapsed();
+
+    // Turn cited timestamps into links, dropping any outside the video
+    if config.timestamps {
+        let linked =
+            timestamps::link_timestamps(&summary, transcript.youtube_id(), transcript.duration());
+        if !linked.invalid.is_empty() {
+            eprintln!(
+                "Warning: removed {} timestamp(s) beyond the end of video {}: {}",
+                linked.invalid.len(),
+                video_id,
+                linked.invalid.join(", ")
+            );
+        }
+        summary = linked.text;
+    }
+
+    let result = VideoSummary {
+        video_id,
+        url: url.to_string(),
+        transcript_language: transcript.language.clone(),
+        metadata,
+        prompt: config.prompt.clone(),
+        summary,
+        model,
+        usage,
+        cached,
+        streamed,
+        timings: Timings {
+            transcript: transcript_time,
+            summary: summary_time,
+            total: started.elapsed(),
+        },
+    };
+
+    if config.history {
+        record_history(&result, &transcript, config);
+    }
+
+    Ok(result)
+}
+
+/// Records a summary in the history database. The history is a
+/// convenience, so failures are only reported.
+fn record_history(result: &VideoSummary, transcript: &Transcript, config: &Config) {
+    let run = Run {
+        id: 0,
+        created_at: history::now(),
+        video_id: result.video_id.clone(),
+        title: result.metadata.as_ref().map(|m| m.title.clone()),
+        url: result.url.clone(),
+        provider: config.provider.kind.name().to_string(),
+        model: result.model.clone(),
+        template: config.template.clone(),
+        prompt: result.prompt.clone(),
+        summary: result.summary.clone(),
+        usage: result.usage.map(UsageReport::from),
+        cached: result.cached,
+    };
+    let text: Vec<&str> = transcript
+        .snippets
+        .iter()
+        .map(|s| s.text.as_str())
+        .collect();
+
+    match History::open().and_then(|history| history.record(&run, &text.join(" "))) {
+        Ok(id) => {
+            if config.verbose {
+                eprintln!("[verbose] Recorded in history as #{}", id);
+            }
+        }
+        Err(e) => eprintln!("Warning: {}", e),
+    }
+}
+
+/// Values of the template variables for a video, overridden by `--var`.
+/// Without metadata, the title falls back to the video ID.
+fn variables(
+    url: &str,
+    video: Option<&VideoId>,
+    transcript: &Transcript,
+    metadata: Option<&VideoMetadata>,
+    config: &Config,
+) -> BTreeMap<String, String> {
+    let duration = match metadata {
+        Some(metadata) if metadata.duration > 0 => metadata.duration,
+        _ => transcript.duration() as u64,
+    };
+
+    let mut values = BTreeMap::from([
+        (
+            "title".to_string(),
+            metadata.map_or_else(|| transcript.video_id.clone(), |m| m.title.clone()),
+        ),
+        (
+            "channel".to_string(),
+            metadata.map_or_else(|| "an unknown channel".to_string(), |m| m.channel.clone()),
+        ),
+        (
+            "upload_date".to_string(),
+            metadata
+                .and_then(|m| m.upload_date.clone())
+                .unwrap_or_else(|| "an unknown date".to_string()),
+        ),
+        (
+            "duration".to_string(),
+            timestamps::format_timestamp(duration),
+        ),
+        ("language".to_string(), transcript.language.clone()),
+        ("video_id".to_string(), transcript.video_id.clone()),
+        ("url".to_string(), url.to_string()),
+        (
+            "start".to_string(),
+            timestamps::format_timestamp(video.and_then(VideoId::start).unwrap_or(0)),
+        ),
+        (
+            "playlist".to_string(),
+            video
+                .and_then(VideoId::playlist)
+                .unwrap_or_default()
+                .to_string(),
+        ),
+    ]);
+    values.extend(config.variables.iter().cloned());
+    values
+}
@@ -0,0 +1,247 @@
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ProviderConfig;
+use crate::error::{Error, Result};
+use crate::ollama::Ollama;
+use crate::openai::OpenAiCompatible;
+use crate::openrouter::{self, OpenRouter};
+
+/// The LLM backends summaries can be generated with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    OpenRouter,
+    Ollama,
+    OpenAi,
+}
+
+impl ProviderKind {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "openrouter" => Ok(ProviderKind::OpenRouter),
+            "ollama" => Ok(ProviderKind::Ollama),
+            "openai" | "openai-compatible" => Ok(ProviderKind::OpenAi),
+            other => Err(Error::Config(format!(
+                "Unknown provider '{}' (expected openrouter, ollama or openai)",
+                other
+            ))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProviderKind::OpenRouter => "openrouter",
+            ProviderKind::Ollama => "ollama",
+            ProviderKind::OpenAi => "openai",
+        }
+    }
+
+    /// Model used when none is configured. Local backends have no sensible
+    /// default since it depends on what the user has installed.
+    pub fn default_model(&self) -> Option<&'static str> {
+        match self {
+            ProviderKind::OpenRouter => Some(openrouter::DEFAULT_MODEL),
+            ProviderKind::Ollama | ProviderKind::OpenAi => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: String) -> Self {
+        ChatMessage {
+            role: "system".to_string(),
+            content,
+        }
+    }
+
+    pub fn user(content: String) -> Self {
+        ChatMessage {
+            role: "user".to_string(),
+            content,
+        }
+    }
+
+    pub fn assistant(content: String) -> Self {
+        ChatMessage {
+            role: "assistant".to_string(),
+            content,
+        }
+    }
+}
+
+/// A provider-neutral chat completion request
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    pub model: String,
+    /// Models the provider may route to if `model` fails. Only backends
+    /// that route natively (OpenRouter) use them.
+    pub fallback_models: Vec<String>,
+    pub messages: Vec<ChatMessage>,
+    pub max_tokens: u32,
+    /// Context window the request was budgeted for. Backends that size
+    /// their context per request (Ollama) use it, others ignore it.
+    pub context_length: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub name: String,
+    pub context_length: Option<u64>,
+    pub pricing: Option<Pricing>,
+}
+
+/// Model pricing in dollars per token, as reported by the provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pricing {
+    pub prompt: String,
+    pub completion: String,
+}
+
+/// Token usage reported by the provider for one or more completions
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Cost in dollars, if the provider reports it
+    pub cost: Option<f64>,
+}
+
+impl Usage {
+    /// Adds the usage of another completion
+    pub fn add(&mut self, other: Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost = match (self.cost, other.cost) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+/// The response to a chat completion
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub text: String,
+    /// Model that answered, if the backend reports it
+    pub model: Option<String>,
+    /// `None` if the backend didn't report usage
+    pub usage: Option<Usage>,
+}
+
+/// An LLM backend that can answer chat completions and list its models.
+///
+/// Each backend turns error responses into `Error::ApiStatus`, with the
+/// HTTP status and the message of its own error payload; the fallback
+/// chain decides by the status. Requests that fail without a response
+/// are `Error::ApiRequest`.
+/// The futures of the backends in this crate are `Send`, as seen through
+/// `AnyProvider`; other implementations need not be.
+#[allow(async_fn_in_trait)]
+pub trait Provider {
+    /// Human readable backend name used in log output
+    fn name(&self) -> &str;
+
+    /// Sends a chat completion and returns the response text and usage.
+    /// When `stream` is set, tokens are written to stdout as they arrive.
+    async fn complete(&self, request: &ChatRequest, stream: bool) -> Result<Completion>;
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>>;
+
+    /// Returns the context length of a model, if the backend reports it
+    async fn context_length(&self, model: &str) -> Result<Option<u64>> {
+        let models = self.list_models().await?;
+        Ok(models
+            .into_iter()
+            .find(|m| m.id == model)
+            .and_then(|m| m.context_length))
+    }
+}
+
+/// Runtime-selected provider
+pub enum AnyProvider {
+    OpenRouter(OpenRouter),
+    Ollama(Ollama),
+    OpenAi(OpenAiCompatible),
+}
+
+impl AnyProvider {
+    /// Creates the configured backend. Required settings such as the
+    /// OpenRouter API key are validated when the config is loaded.
+    pub fn new(config: &ProviderConfig) -> Self {
+        let client = reqwest::Client::new();
+        let base_url = config.base_url.as_deref();
+
+        match config.kind {
+            ProviderKind::OpenRouter => AnyProvider::OpenRouter(OpenRouter::new(
+                client,
+                base_url,
+                config.api_key.clone().unwrap_or_default(),
+                config.retry,
+                config.verbose,
+            )),
+            ProviderKind::Ollama => {
+                AnyProvider::Ollama(Ollama::new(client, base_url, config.retry, config.verbose))
+            }
+            ProviderKind::OpenAi => AnyProvider::OpenAi(OpenAiCompatible::new(
+                client,
+                "OpenAI-compatible API",
+                base_url.unwrap_or_default(),
+                config.api_key.clone(),
+                config.retry,
+                config.verbose,
+            )),
+        }
+    }
+}
+
+impl Provider for AnyProvider {
+    fn name(&self) -> &str {
+        match self {
+            AnyProvider::OpenRouter(p) => p.name(),
+            AnyProvider::Ollama(p) => p.name(),
+            AnyProvider::OpenAi(p) => p.name(),
+        }
+    }
+
+    async fn complete(&self, request: &ChatRequest, stream: bool) -> Result<Completion> {
+        match self {
+            AnyProvider::OpenRouter(p) => p.complete(request, stream).await,
+            AnyProvider::Ollama(p) => p.complete(request, stream).await,
+            AnyProvider::OpenAi(p) => p.complete(request, stream).await,
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        match self {
+            AnyProvider::OpenRouter(p) => p.list_models().await,
+            AnyProvider::Ollama(p) => p.list_models().await,
+            AnyProvider::OpenAi(p) => p.list_models().await,
+        }
+    }
+
+    async fn context_length(&self, model: &str) -> Result<Option<u64>> {
+        match self {
+            AnyProvider::OpenRouter(p) => p.context_length(model).await,
+            AnyProvider::Ollama(p) => p.context_length(model).await,
+            AnyProvider::OpenAi(p) => p.context_length(model).await,
+        }
+    }
+}
+
+/// Writes a streamed token to stdout and appends it to the collected text
+pub fn emit_token(text: &mut String, token: &str) -> Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{}", token)
+        .and_then(|_| stdout.flush())
+        .map_err(|e| Error::ApiRequest(format!("Failed to write output: {}", e)))?;
+    text.push_str(token);
+    Ok(())
+}
@@ -0,0 +1,323 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use reqwest::StatusCode;
+
+use crate::cache;
+use crate::error::{Error, Result};
+
+/// How failed provider requests are retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further retry
+    pub base_delay: Duration,
+    /// Randomizes each delay between half and all of its value, so that
+    /// concurrent batch jobs don't retry in lockstep
+    pub jitter: bool,
+    /// No retry is started once this much time has passed since the
+    /// first attempt
+    pub deadline: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            jitter: true,
+            deadline: Duration::from_secs(120),
+        }
+    }
+}
+
+/// Upper bound for a single backoff delay
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+impl RetryPolicy {
+    /// Sends a request, retrying connection failures and retryable status
+    /// codes with exponential backoff. A `Retry-After` header overrides
+    /// the computed delay.
+    ///
+    /// Once the attempts or the deadline are used up, the last response is
+    /// returned as is, so callers report errors as for a single attempt.
+    pub async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        verbose: bool,
+    ) -> reqwest::Result<reqwest::Response> {
+        let started = Instant::now();
+        let mut attempt = 1;
+
+        loop {
+            // Requests with streaming bodies can't be repeated
+            let Some(retry_request) = request.try_clone() else {
+                return request.send().await;
+            };
+
+            let outcome = retry_request.send().await;
+
+            let (reason, retry_after) = match &outcome {
+                Ok(response) if is_retryable_status(response.status()) => {
+                    (response.status().to_string(), retry_after(response))
+                }
+                Ok(_) => return outcome,
+                Err(e) if is_retryable_error(e) => (e.to_string(), None),
+                Err(_) => return outcome,
+            };
+
+            // A server may ask for any delay; one past the deadline ends
+            // the retries rather than being shortened
+            let delay = retry_after.unwrap_or_else(|| self.backoff(attempt));
+            let out_of_time = started
+                .elapsed()
+                .checked_add(delay)
+                .is_none_or(|end| end > self.deadline);
+            if attempt >= self.max_attempts || out_of_time {
+                if verbose {
+                    eprintln!(
+                        "[verbose] Request failed ({}), giving up after {} attempt(s)",
+                        reason, attempt
+                    );
+                }
+                return outcome;
+            }
+
+            if verbose {
+                eprintln!(
+                    "[verbose] Request failed ({}), retrying in {:.1}s (attempt {}/{})",
+                    reason,
+                    delay.as_secs_f64(),
+                    attempt + 1,
+                    self.max_attempts
+                );
+            }
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Delay before retry number `attempt`
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(MAX_DELAY);
+
+        if self.jitter {
+            delay.mul_f64(0.5 + random_fraction() / 2.0)
+        } else {
+            delay
+        }
+    }
+}
+
+/// Rate limits, timeouts and server errors are usually transient; other
+/// client errors such as an invalid key or model are not
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request()
+}
+
+/// Reads a `Retry-After` header given in seconds
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()
+        .and_then(parse_retry_after)
+}
+
+/// Parses a `Retry-After` value in seconds. HTTP dates are not supported
+/// and fall back to the computed backoff; delays too long to represent
+/// become `Duration::MAX`.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let seconds: f64 = value.trim().parse().ok()?;
+    if seconds.is_nan() || seconds < 0.0 {
+        return None;
+    }
+    Some(Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX))
+}
+
+/// Parses a delay such as "500ms", "2s" or "1m"
+pub fn parse_delay(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    if let Some(millis) = value.strip_suffix("ms") {
+        return millis
+            .parse()
+            .map(Duration::from_millis)
+            .map_err(|_| Error::Config(format!("Invalid delay: '{}'", value)));
+    }
+    cache::parse_age(value)
+}
+
+/// A value in [0, 1) that differs between calls; good enough for jitter
+fn random_fraction() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    // Scramble the low bits, which change fastest
+    let mixed = nanos.wrapping_mul(2_654_435_761);
+    f64::from(mixed) / f64::from(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    /// Serves the scripted responses in order, one per connection, and
+    /// counts the requests received
+    fn mock_server(responses: &[&str]) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let responses: Vec<String> = responses.iter().map(|r| r.to_string()).collect();
+
+        let counter = Arc::clone(&requests);
+        thread::spawn(move || {
+            for (stream, response) in listener.incoming().zip(responses) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                let (status, headers) = response.split_once('|').unwrap_or((&response, ""));
+                let reply = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+                    status, headers
+                );
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn policy(max_attempts: u32, deadline: Duration) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            jitter: false,
+            deadline,
+        }
+    }
+
+    async fn send(policy: RetryPolicy, url: &str) -> StatusCode {
+        let request = reqwest::Client::new().get(url);
+        policy.send(request, false).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limits_after_the_requested_delay() {
+        let (url, requests) = mock_server(&["429 Too Many Requests|Retry-After: 0\r\n", "200 OK"]);
+        let status = send(policy(4, Duration::from_secs(10)), &url).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let (url, requests) = mock_server(&[
+            "500 Internal Server Error",
+            "503 Service Unavailable|Retry-After: 0.01\r\n",
+            "502 Bad Gateway",
+            "200 OK",
+        ]);
+        let status = send(policy(4, Duration::from_secs(10)), &url).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn returns_the_last_response_after_the_attempts() {
+        let (url, requests) = mock_server(&["500 Internal Server Error"; 3]);
+        let status = send(policy(3, Duration::from_secs(10)), &url).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_at_the_deadline() {
+        let (url, requests) = mock_server(&["429 Too Many Requests|Retry-After: 5\r\n", "200 OK"]);
+        let status = send(policy(4, Duration::from_secs(1)), &url).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn survives_huge_retry_after_values() {
+        for value in ["1e20", "18446744073709551616", "1e308"] {
+            let header = format!("503 Service Unavailable|Retry-After: {}\r\n", value);
+            let (url, requests) = mock_server(&[&header, "200 OK"]);
+            let status = send(policy(4, Duration::MAX), &url).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}", value);
+            assert_eq!(requests.load(Ordering::SeqCst), 1, "{}", value);
+        }
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        for status in ["400 Bad Request", "401 Unauthorized", "404 Not Found"] {
+            let (url, requests) = mock_server(&[status, "200 OK"]);
+            let received = send(policy(4, Duration::from_secs(10)), &url).await;
+            assert!(received.is_client_error(), "{}", status);
+            assert_eq!(requests.load(Ordering::SeqCst), 1, "{}", status);
+        }
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        let cases: &[(&str, Option<Duration>)] = &[
+            ("0", Some(Duration::ZERO)),
+            ("5", Some(Duration::from_secs(5))),
+            (" 1.5 ", Some(Duration::from_millis(1500))),
+            ("1e20", Some(Duration::MAX)),
+            ("inf", Some(Duration::MAX)),
+            ("-1", None),
+            ("NaN", None),
+            ("", None),
+            ("Wed, 21 Oct 2015 07:28:00 GMT", None),
+        ];
+        for &(value, expected) in cases {
+            assert_eq!(
+                parse_retry_after(value),
+                expected,
+                "Retry-After {:?}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_secs(1),
+            ..policy(10, Duration::MAX)
+        };
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(40), MAX_DELAY);
+    }
+}
@@ -0,0 +1,655 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::{Semaphore, watch};
+
+use crate::cli::ServeOptions;
+use youtube_summary::cache::Cache;
+use youtube_summary::config::{self, Config, Overrides};
+use youtube_summary::error::{Error, Result};
+use youtube_summary::export::{self, ExportFormat};
+use youtube_summary::history;
+use youtube_summary::output::{OutputFormat, SummaryDocument};
+use youtube_summary::pipeline;
+use youtube_summary::provider::{AnyProvider, Provider};
+use youtube_summary::video_id::VideoId;
+
+/// Address the server listens on when not configured
+pub const DEFAULT_BIND: &str = "127.0.0.1:8080";
+
+/// Longest time a summary may take, from the start of its work until it
+/// is done, when not configured
+pub const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// Jobs waiting for a worker beyond which new jobs are refused
+const MAX_QUEUED_JOBS: usize = 100;
+
+/// How long finished jobs can still be looked up
+const JOB_RETENTION_SECS: u64 = 3600;
+
+/// A request to summarize a video. Unset fields fall back to the server's
+/// configuration.
+#[derive(Debug, Deserialize)]
+struct SummarizeRequest {
+    /// YouTube video URL or ID
+    url: String,
+    #[serde(flatten)]
+    overrides: Overrides,
+    /// Answer with the finished summary instead of a job to poll
+    #[serde(default)]
+    wait: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptQuery {
+    lang: Option<String>,
+    translate: Option<String>,
+    /// text, srt, vtt, json or markdown [default: json]
+    format: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    TimedOut,
+}
+
+impl JobState {
+    fn is_finished(&self) -> bool {
+        matches!(self, JobState::Done | JobState::Failed | JobState::TimedOut)
+    }
+}
+
+/// A summary job as reported by `GET /jobs/{id}`
+#[derive(Debug, Clone, Serialize)]
+struct Job {
+    id: u64,
+    url: String,
+    status: JobState,
+    /// Unix time in seconds
+    created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<SummaryDocument>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorBody>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ErrorBody {
+    kind: &'static str,
+    message: String,
+}
+
+struct AppState {
+    config: Config,
+    /// Time limit for each summary
+    timeout: Duration,
+    provider: AnyProvider,
+    cache: Cache,
+    /// Limits the summaries running at once to `config.jobs`
+    workers: Semaphore,
+    jobs: Mutex<HashMap<u64, watch::Sender<Job>>>,
+    next_id: AtomicU64,
+}
+
+impl AppState {
+    fn new(config: Config, timeout: Duration, cache: Cache) -> Self {
+        AppState {
+            provider: AnyProvider::new(&config.provider),
+            cache,
+            workers: Semaphore::new(config.jobs.max(1)),
+            jobs: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            timeout,
+            config,
+        }
+    }
+
+    fn job(&self, id: u64) -> Option<watch::Receiver<Job>> {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.get(&id).map(watch::Sender::subscribe)
+    }
+
+    fn update(&self, id: u64, change: impl FnOnce(&mut Job)) {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(job) = jobs.get(&id) {
+            job.send_modify(change);
+        }
+    }
+}
+
+/// An error answered as JSON with a fitting status code
+struct ApiError(Error);
+
+impl From<Error> for ApiError {
+    fn from(e: Error) -> Self {
+        ApiError(e)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self.0 {
+            Error::InvalidYoutubeUrl(_) | Error::Config(_) => StatusCode::BAD_REQUEST,
+            Error::TranscriptFetch(_) => StatusCode::NOT_FOUND,
+            Error::ApiRequest(_) | Error::ApiStatus { .. } => StatusCode::BAD_GATEWAY,
+            Error::Budget(_) => StatusCode::PAYMENT_REQUIRED,
+            Error::Cache(_) | Error::History(_) | Error::Output(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        (status, Json(error_body(&self.0))).into_response()
+    }
+}
+
+fn error_body(e: &Error) -> ErrorBody {
+    ErrorBody {
+        kind: e.kind(),
+        message: e.to_string(),
+    }
+}
+
+/// Serves the HTTP API until interrupted
+pub async fn serve(config: Config, options: &ServeOptions) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(&options.bind)
+        .await
+        .map_err(|e| Error::Config(format!("Failed to listen on {}: {}", options.bind, e)))?;
+    let addr = listener
+        .local_addr()
+        .map_err(|e| Error::Config(format!("Failed to listen on {}: {}", options.bind, e)))?;
+    if config.serve_token.is_none() && !addr.ip().is_loopback() {
+        eprintln!(
+            "Warning: serving on {} without a token; anyone who can reach it can spend your API credits",
+            addr
+        );
+    }
+
+    let cache = Cache::new(config.cache);
+    let timeout = Duration::from_secs(options.timeout);
+    let app = router(Arc::new(AppState::new(config, timeout, cache)));
+
+    eprintln!("Listening on http://{}", addr);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await
+        .map_err(|e| Error::Output(format!("Server failed: {}", e)))
+}
+
+fn router(state: Arc<AppState>) -> Router {
+    let api = Router::new()
+        .route("/summarize", post(summarize))
+        .route("/jobs/{id}", get(job_status))
+        .route("/transcript/{id}", get(transcript))
+        .route("/models", get(models))
+        .route_layer(middleware::from_fn_with_state(
+            Arc::clone(&state),
+            authorize,
+        ));
+    Router::new()
+        .route("/health", get(health))
+        .merge(api)
+        .with_state(state)
+}
+
+/// Rejects requests without the configured bearer token
+async fn authorize(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let Some(ref token) = state.config.serve_token else {
+        return next.run(request).await;
+    };
+
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if given.is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes())) {
+        return next.run(request).await;
+    }
+
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(json!({ "kind": "Unauthorized", "message": "Missing or wrong bearer token" })),
+    )
+        .into_response()
+}
+
+/// Compares without stopping at the first difference, so response times
+/// don't reveal how much of a guessed token is right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+async fn health(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    let queued = {
+        let jobs = state.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.values()
+            .filter(|job| !job.borrow().status.is_finished())
+            .count()
+    };
+    Json(json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+        "provider": state.config.provider.kind.name(),
+        "model": state.config.model,
+        "jobs": queued,
+    }))
+}
+
+async fn models(State(state): State<Arc<AppState>>) -> std::result::Result<Response, ApiError> {
+    let models = state.provider.list_models().await?;
+    Ok(Json(models).into_response())
+}
+
+async fn transcript(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<TranscriptQuery>,
+) -> std::result::Result<Response, ApiError> {
+    // Only YouTube videos; the server never reads local files
+    let video = VideoId::parse(&id)?;
+    let format = match query.format.as_deref() {
+        Some(name) => ExportFormat::parse(name)?,
+        None => ExportFormat::Json,
+    };
+
+    let mut options = state.config.transcript.clone();
+    if let Some(ref languages) = query.lang {
+        options.languages = config::parse_language_list(languages)?;
+    }
+    if query.translate.is_some() {
+        options.translate_to = query.translate;
+    }
+
+    let transcript =
+        pipeline::load_transcript(video.as_str(), &options, &state.cache, state.config.verbose)
+            .await?;
+    if format == ExportFormat::Json {
+        return Ok(Json(transcript).into_response());
+    }
+
+    let text = export::render(&transcript, format)?;
+    let content_type = match format {
+        ExportFormat::Vtt => "text/vtt; charset=utf-8",
+        ExportFormat::Markdown => "text/markdown; charset=utf-8",
+        _ => "text/plain; charset=utf-8",
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], text).into_response())
+}
+
+/// Queues a summary. Answers 202 with the job to poll at `/jobs/{id}`, or
+/// with the finished job if the request asked to wait.
+async fn summarize(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SummarizeRequest>,
+) -> std::result::Result<Response, ApiError> {
+    // Reject bad input right away rather than in a failed job
+    let mut config = state.config.with_overrides(&request.overrides)?;
+    // Jobs run concurrently and answer with JSON
+    config.stream = false;
+    config.format = OutputFormat::Json;
+    // Only YouTube videos; the server never reads local files
+    let video = VideoId::parse(&request.url)?;
+
+    let id = state.next_id.fetch_add(1, Ordering::Relaxed);
+    let job = Job {
+        id,
+        url: request.url.clone(),
+        status: JobState::Queued,
+        created_at: history::now(),
+        result: None,
+        error: None,
+    };
+    let (sender, mut receiver) = watch::channel(job.clone());
+    {
+        let mut jobs = state.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let now = history::now();
+        jobs.retain(|_, job| {
+            let job = job.borrow();
+            !job.status.is_finished() || now.saturating_sub(job.created_at) < JOB_RETENTION_SECS
+        });
+        let queued = jobs
+            .values()
+            .filter(|job| job.borrow().status == JobState::Queued)
+            .count();
+        if queued >= MAX_QUEUED_JOBS {
+            let body = Json(json!({ "kind": "Busy", "message": "Too many queued jobs" }));
+            return Ok((StatusCode::SERVICE_UNAVAILABLE, body).into_response());
+        }
+        jobs.insert(id, sender);
+    }
+
+    if state.config.verbose {
+        eprintln!("[verbose] Job {}: queued {}", id, request.url);
+    }
+    tokio::spawn(run_job(Arc::clone(&state), id, video.url(), config));
+
+    if !request.wait {
+        return Ok((StatusCode::ACCEPTED, Json(job)).into_response());
+    }
+
+    // The job always finishes, at the latest when it times out
+    let finished = receiver
+        .wait_for(|job| job.status.is_finished())
+        .await
+        .map(|job| job.clone())
+        .map_err(|_| Error::Output("Job ended unexpectedly".to_string()))?;
+    let status = match finished.status {
+        JobState::Done => StatusCode::OK,
+        JobState::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+        _ => StatusCode::BAD_GATEWAY,
+    };
+    Ok((status, Json(finished)).into_response())
+}
+
+async fn run_job(state: Arc<AppState>, id: u64, url: String, config: Config) {
+    // The semaphore is never closed, so acquiring can't fail
+    let _permit = state.workers.acquire().await.ok();
+    state.update(id, |job| job.status = JobState::Running);
+
+    let outcome = tokio::time::timeout(
+        state.timeout,
+        pipeline::summarize_video(&url, &config, &state.cache, &state.provider),
+    )
+    .await;
+
+    state.update(id, |job| match outcome {
+        Ok(Ok(result)) => {
+            job.status = JobState::Done;
+            job.result = Some(SummaryDocument::new(&result, &config));
+        }
+        Ok(Err(e)) => {
+            job.status = JobState::Failed;
+            job.error = Some(error_body(&e));
+        }
+        Err(_) => {
+            job.status = JobState::TimedOut;
+            job.error = Some(ErrorBody {
+                kind: "Timeout",
+                message: format!("Summary took longer than {}s", state.timeout.as_secs()),
+            });
+        }
+    });
+
+    if state.config.verbose {
+        eprintln!("[verbose] Job {}: finished", id);
+    }
+}
+
+async fn job_status(State(state): State<Arc<AppState>>, Path(id): Path<u64>) -> Response {
+    let Some(job) = state.job(id) else {
+        let body = Json(json!({ "kind": "NotFound", "message": format!("No job with ID {}", id) }));
+        return (StatusCode::NOT_FOUND, body).into_response();
+    };
+    let job = job.borrow().clone();
+    Json(job).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::body::{self, Body};
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+    use youtube_summary::cache::CachePolicy;
+    use youtube_summary::metadata::VideoMetadata;
+    use youtube_summary::provider::ProviderKind;
+    use youtube_summary::summarizer::Summarizer;
+    use youtube_summary::transcript::{Snippet, Transcript};
+
+    const VIDEO: &str = "dQw4w9WgXcQ";
+    const TOKEN: &str = "secret";
+
+    /// An OpenAI-compatible backend answering every request with a fixed
+    /// summary. Requests for the model "slow" never finish.
+    async fn mock_llm() -> String {
+        async fn complete(Json(request): Json<Value>) -> Json<Value> {
+            if request["model"] == "slow" {
+                std::future::pending::<()>().await;
+            }
+            Json(json!({
+                "model": request["model"],
+                "choices": [{ "message": { "content": "A short summary." } }],
+                "usage": { "prompt_tokens": 100, "completion_tokens": 10 },
+            }))
+        }
+        async fn models() -> Json<Value> {
+            Json(json!({ "data": [{ "id": "mock", "max_model_len": 8000 }] }))
+        }
+
+        let app = Router::new()
+            .route("/v1/chat/completions", post(complete))
+            .route("/v1/models", get(models));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    /// A router whose cache holds the transcript and metadata of `VIDEO`,
+    /// so that no request goes to YouTube
+    async fn app(jobs: usize) -> Router {
+        let summarizer = Summarizer::builder()
+            .provider(ProviderKind::OpenAi)
+            .base_url(mock_llm().await)
+            .model("mock")
+            .build()
+            .unwrap();
+        let mut config = summarizer.config().clone();
+        config.serve_token = Some(TOKEN.to_string());
+        config.jobs = jobs;
+
+        let dir = std::env::temp_dir().join(format!(
+            "youtube-summary-server-test-{}-{}",
+            std::process::id(),
+            rand_suffix()
+        ));
+        let cache = Cache::in_dir(dir, CachePolicy::Use);
+        let transcript = Transcript {
+            video_id: VIDEO.to_string(),
+            language: "en".to_string(),
+            snippets: vec![Snippet {
+                text: "Hello and welcome.".to_string(),
+                start: 0.0,
+                duration: 2.0,
+            }],
+            file: None,
+        };
+        cache
+            .put_transcript(&transcript, &config.transcript)
+            .unwrap();
+        cache
+            .put_metadata(&VideoMetadata {
+                video_id: VIDEO.to_string(),
+                title: "A video".to_string(),
+                channel: "A channel".to_string(),
+                duration: 2,
+                ..Default::default()
+            })
+            .unwrap();
+
+        router(Arc::new(AppState::new(
+            config,
+            Duration::from_secs(10),
+            cache,
+        )))
+    }
+
+    fn rand_suffix() -> u64 {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn get_request(uri: &str, token: Option<&str>) -> Request<Body> {
+        let mut request = Request::get(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    fn summarize_request(body: Value) -> Request<Body> {
+        Request::post("/summarize")
+            .header(header::AUTHORIZATION, format!("Bearer {}", TOKEN))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn requires_the_bearer_token() {
+        let app = app(1).await;
+
+        let (status, body) = send(&app, get_request("/health", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+
+        for token in [None, Some("wrong"), Some("secre")] {
+            let (status, body) = send(&app, get_request("/jobs/1", token)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", token);
+            assert_eq!(body["kind"], "Unauthorized");
+        }
+
+        let (status, body) = send(&app, get_request("/jobs/1", Some(TOKEN))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["kind"], "NotFound");
+    }
+
+    #[tokio::test]
+    async fn runs_a_job_to_completion() {
+        let app = app(1).await;
+
+        let (status, job) = send(&app, summarize_request(json!({ "url": VIDEO }))).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(job["status"], "queued");
+        let uri = format!("/jobs/{}", job["id"]);
+
+        let mut job = job;
+        for _ in 0..500 {
+            (_, job) = send(&app, get_request(&uri, Some(TOKEN))).await;
+            if job["status"] == "done" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(job["status"], "done", "{}", job);
+        assert_eq!(job["result"]["summary"], "A short summary.");
+        assert_eq!(job["result"]["video_id"], VIDEO);
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_summary() {
+        let app = app(1).await;
+
+        let request = json!({ "url": format!("https://youtu.be/{}", VIDEO), "wait": true });
+        let (status, job) = send(&app, summarize_request(request)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(job["status"], "done");
+        assert_eq!(job["result"]["model"], "mock");
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_requests() {
+        let app = app(1).await;
+
+        let (status, body) = send(&app, summarize_request(json!({ "url": "nope" }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["kind"], "InvalidYoutubeUrl");
+
+        let request = json!({ "url": VIDEO, "template": "tldr", "prompt": "x" });
+        let (status, _) = send(&app, summarize_request(request)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn never_reads_local_files() {
+        let app = app(1).await;
+
+        // Links that look like paths are summarized as the video
+        for url in [
+            format!("//youtu.be/{}", VIDEO),
+            format!("https://youtu.be/{}?si=a.srt", VIDEO),
+            format!("www.youtube.com/watch?v={}&x=/etc/passwd.txt", VIDEO),
+        ] {
+            let request = json!({ "url": url, "wait": true });
+            let (status, job) = send(&app, summarize_request(request)).await;
+            assert_eq!(status, StatusCode::OK, "{}", url);
+            assert_eq!(job["status"], "done", "{}: {}", url, job);
+            assert_eq!(job["result"]["video_id"], VIDEO);
+        }
+
+        for url in [
+            "./talk.srt",
+            "/etc/passwd",
+            "file:/etc/hostname",
+            "talk.srt",
+            "-",
+        ] {
+            let (status, body) = send(&app, summarize_request(json!({ "url": url }))).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", url);
+            assert_eq!(body["kind"], "InvalidYoutubeUrl");
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_jobs_when_the_queue_is_full() {
+        let app = app(1).await;
+        let slow = json!({ "url": VIDEO, "model": "slow" });
+
+        // One job runs and never finishes; the rest wait for its worker
+        let (status, _) = send(&app, summarize_request(slow.clone())).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let mut running = false;
+        for _ in 0..500 {
+            let (_, job) = send(&app, get_request("/jobs/1", Some(TOKEN))).await;
+            running = job["status"] == "running";
+            if running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(running);
+
+        for _ in 0..MAX_QUEUED_JOBS {
+            let (status, _) = send(&app, summarize_request(slow.clone())).await;
+            assert_eq!(status, StatusCode::ACCEPTED);
+        }
+        let (status, body) = send(&app, summarize_request(slow)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["kind"], "Busy");
+
+        let (_, health) = send(&app, get_request("/health", None)).await;
+        assert_eq!(health["jobs"], MAX_QUEUED_JOBS + 1);
+    }
+}
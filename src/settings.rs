@@ -0,0 +1,523 @@
+use crate::batch::DEFAULT_JOBS;
+use crate::cli::{Args, ENV_PREFIX};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use youtube_summary::cache::CachePolicy;
+use youtube_summary::collection::{self, ExpandOptions};
+use youtube_summary::config::{Config, ProviderConfig, parse_language_list};
+use youtube_summary::cost;
+use youtube_summary::error::{Error, Result};
+use youtube_summary::output::OutputFormat;
+use youtube_summary::provider::ProviderKind;
+use youtube_summary::retry::{self, RetryPolicy};
+use youtube_summary::summarize::ChapterMode;
+use youtube_summary::templates;
+use youtube_summary::transcript::TranscriptOptions;
+
+/// Loads the configuration from the config file, the environment and the
+/// command line
+pub fn load(args: &Args) -> Result<Config> {
+    // Load config file if it exists
+    let file_config = load_config_file(args)?;
+
+    let provider = resolve_provider(args, &file_config)?;
+    let transcript = resolve_transcript_options(args, &file_config)?;
+    let cache = resolve_cache_policy(args, &file_config);
+
+    // Model precedence: CLI > config file > provider default. Repeated
+    // --model flags replace the configured fallbacks as well.
+    let (model, fallback_models) = match args.models.split_first() {
+        Some((model, fallbacks)) => (Some(model.clone()), fallbacks.to_vec()),
+        None => (
+            file_config.model,
+            file_config.fallback_models.unwrap_or_default(),
+        ),
+    };
+    let model = model
+        .or_else(|| provider.kind.default_model().map(str::to_string))
+        .ok_or_else(|| {
+            Error::Config(format!(
+                "No model configured for the {} provider. Use --model or set default_model in the config file",
+                provider.kind.name()
+            ))
+        })?;
+
+    // Prompt: --prompt > --template > config file template > default
+    let (prompt, template) = match args.prompt {
+        Some(ref prompt) => (prompt.clone(), None),
+        None => {
+            let name = args
+                .template
+                .as_deref()
+                .or(file_config.template.as_deref())
+                .unwrap_or(templates::DEFAULT_TEMPLATE);
+            (templates::find(name)?.body, Some(name.to_string()))
+        }
+    };
+    templates::validate(&prompt, &args.vars)?;
+
+    // Streaming: CLI flag > config file > off
+    let stream = args.stream || file_config.stream.unwrap_or(false);
+
+    // Timestamps: CLI flag > config file > off
+    let timestamps = args.timestamps || file_config.timestamps.unwrap_or(false);
+
+    // Output format: CLI > config file > text
+    let format = match args.format.as_deref().or(file_config.format.as_deref()) {
+        Some(name) => OutputFormat::parse(name)?,
+        None => OutputFormat::default(),
+    };
+
+    // Timestamped summaries are post-processed once complete, and JSON
+    // is printed as one document, so neither can be streamed. A fallback
+    // model would start over after part of a summary was printed.
+    let stream =
+        stream && !timestamps && format == OutputFormat::Text && fallback_models.is_empty();
+
+    // Output language: CLI > config file > model's choice
+    let output_language = args.output_language.clone().or(file_config.output_language);
+
+    // Chapters: CLI > config file > auto
+    let chapters = match args.chapters.as_deref().or(file_config.chapters.as_deref()) {
+        Some(name) => ChapterMode::parse(name)?,
+        None => ChapterMode::default(),
+    };
+
+    // History: --no-history > config file > on
+    let history = !args.no_history && file_config.history.unwrap_or(true);
+
+    // Batch concurrency: CLI > config file > default
+    let jobs = match (args.jobs, file_config.jobs.as_deref()) {
+        (Some(jobs), _) => jobs,
+        (None, Some(value)) => value
+            .parse::<usize>()
+            .ok()
+            .filter(|&jobs| jobs > 0)
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "Invalid number of jobs: '{}' (expected a positive number)",
+                    value
+                ))
+            })?,
+        (None, None) => DEFAULT_JOBS,
+    };
+
+    // Playlist and channel expansion
+    let max_age = args
+        .since
+        .as_deref()
+        .map(collection::parse_since)
+        .transpose()?;
+
+    // Cost limit: CLI > config file > none
+    let max_cost = match (args.max_cost, file_config.max_cost.as_deref()) {
+        (Some(cost), _) => Some(cost),
+        (None, Some(value)) => Some(cost::parse_max_cost(value).map_err(Error::Config)?),
+        (None, None) => None,
+    };
+
+    Ok(Config {
+        provider,
+        model,
+        fallback_models,
+        prompt,
+        template,
+        variables: args.vars.clone(),
+        verbose: args.verbose,
+        stream,
+        timestamps,
+        transcript,
+        output_language,
+        chapters,
+        cache,
+        history,
+        jobs,
+        expand: ExpandOptions {
+            limit: args.limit,
+            max_age,
+        },
+        overview: args.overview,
+        format,
+        max_cost,
+        serve_token: args.serve_token.clone().or(file_config.serve_token),
+    })
+}
+
+/// The effective settings in config file syntax, with the API key
+/// masked. Unset optional settings are commented out.
+pub fn describe(config: &Config) -> String {
+    let retry = &config.provider.retry;
+    let optional = |key: &str, value: Option<String>| match value {
+        Some(value) => format!("{} = {}\n", key, value),
+        None => format!("# {} =\n", key),
+    };
+
+    let mut text = String::new();
+    text.push_str(&format!("provider = {}\n", config.provider.kind.name()));
+    text.push_str(&optional("base_url", config.provider.base_url.clone()));
+    text.push_str(&optional(
+        "api_key",
+        config.provider.api_key.as_deref().map(mask_secret),
+    ));
+    text.push_str(&format!("default_model = {}\n", config.model));
+    text.push_str(&optional(
+        "fallback_models",
+        (!config.fallback_models.is_empty()).then(|| config.fallback_models.join(", ")),
+    ));
+    text.push_str(&optional("template", config.template.clone()));
+    text.push_str(&format!("stream = {}\n", config.stream));
+    text.push_str(&format!("timestamps = {}\n", config.timestamps));
+    text.push_str(&format!(
+        "lang = {}\n",
+        config.transcript.languages.join(",")
+    ));
+    text.push_str(&optional(
+        "translate",
+        config.transcript.translate_to.clone(),
+    ));
+    text.push_str(&optional("output_lang", config.output_language.clone()));
+    text.push_str(&format!("chapters = {}\n", config.chapters.name()));
+    text.push_str(&format!(
+        "cache = {}\n",
+        config.cache != CachePolicy::Bypass
+    ));
+    text.push_str(&format!("history = {}\n", config.history));
+    text.push_str(&format!("jobs = {}\n", config.jobs));
+    text.push_str(&format!(
+        "format = {}\n",
+        match config.format {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+        }
+    ));
+    text.push_str(&optional(
+        "max_cost",
+        config.max_cost.map(|cost| cost.to_string()),
+    ));
+    text.push_str(&optional(
+        "serve_token",
+        config.serve_token.as_deref().map(mask_secret),
+    ));
+    text.push_str(&format!("retry_attempts = {}\n", retry.max_attempts));
+    text.push_str(&format!(
+        "retry_delay = {}ms\n",
+        retry.base_delay.as_millis()
+    ));
+    text.push_str(&format!("retry_jitter = {}\n", retry.jitter));
+    text.push_str(&format!("retry_deadline = {}s\n", retry.deadline.as_secs()));
+    text
+}
+
+/// Reads the config file and applies the `YOUTUBE_SUMMARY_*`
+/// overrides from the environment on top of it
+fn load_config_file(args: &Args) -> Result<FileConfig> {
+    let path = config_file_path(args.config_path.as_deref());
+
+    let mut config = if path.exists() {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| Error::Config(format!("Failed to read config file: {}", e)))?;
+        parse_config(&content)?
+    } else {
+        FileConfig::default()
+    };
+
+    for (key, value) in &args.env_overrides {
+        if !config.set(key, value.trim())? && args.verbose {
+            eprintln!(
+                "[verbose] Ignoring {}{}: not a config setting",
+                ENV_PREFIX,
+                key.to_uppercase()
+            );
+        }
+    }
+
+    Ok(config)
+}
+
+fn parse_config(content: &str) -> Result<FileConfig> {
+    let mut config = FileConfig::default();
+
+    for line in content.lines() {
+        let line = line.trim();
+
+        // Skip empty lines and comments
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            // Unknown keys are ignored
+            config.set(key.trim(), value.trim())?;
+        }
+    }
+
+    Ok(config)
+}
+
+/// Path of the config file, `~/.config/youtube-summary/config` unless
+/// given with `--config`
+pub fn config_file_path(custom_path: Option<&str>) -> PathBuf {
+    match custom_path {
+        Some(p) => PathBuf::from(p),
+        None => {
+            let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+            PathBuf::from(home).join(".config/youtube-summary/config")
+        }
+    }
+}
+
+/// Loads only the provider settings, for commands that don't summarize
+pub fn load_provider(args: &Args) -> Result<ProviderConfig> {
+    let file_config = load_config_file(args)?;
+    resolve_provider(args, &file_config)
+}
+
+fn resolve_provider(args: &Args, file_config: &FileConfig) -> Result<ProviderConfig> {
+    // Provider precedence: CLI > config file > OpenRouter
+    let kind = match args.provider.as_deref().or(file_config.provider.as_deref()) {
+        Some(name) => ProviderKind::parse(name)?,
+        None => ProviderKind::OpenRouter,
+    };
+
+    // Base URL precedence: CLI > config file > provider default
+    let base_url = args.base_url.clone().or(file_config.base_url.clone());
+
+    // Load credentials from ~/.config/youtube-summary/credentials
+    let credentials = Credentials::load()?;
+
+    // API key precedence: CLI > env > credentials > config file
+    let api_key = match kind {
+        ProviderKind::OpenRouter => Some(
+            args.api_key
+                .clone()
+                .or_else(|| env::var("OPENROUTER_API_KEY").ok())
+                .or(credentials.openrouter_api_key)
+                .or(file_config.api_key.clone())
+                .ok_or_else(|| {
+                    Error::Config(
+                        "No API key found. Set OPENROUTER_API_KEY env var, use --api-key, or add to ~/.config/youtube-summary/credentials"
+                            .to_string(),
+                    )
+                })?,
+        ),
+        // Local servers usually don't need a key, so it is optional
+        ProviderKind::OpenAi => args
+            .api_key
+            .clone()
+            .or_else(|| env::var("OPENAI_API_KEY").ok())
+            .or(credentials.openai_api_key),
+        ProviderKind::Ollama => None,
+    };
+
+    if kind == ProviderKind::OpenAi && base_url.is_none() {
+        return Err(Error::Config(
+            "The openai provider requires a base URL. Use --base-url or set base_url in the config file"
+                .to_string(),
+        ));
+    }
+
+    // Retries: config file > defaults
+    let mut retry = RetryPolicy::default();
+    if let Some(ref value) = file_config.retry_attempts {
+        retry.max_attempts = value
+            .parse::<u32>()
+            .ok()
+            .filter(|&attempts| attempts > 0)
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "Invalid value for retry_attempts: '{}' (expected a positive number)",
+                    value
+                ))
+            })?;
+    }
+    if let Some(ref value) = file_config.retry_delay {
+        retry.base_delay = retry::parse_delay(value)?;
+    }
+    if let Some(jitter) = file_config.retry_jitter {
+        retry.jitter = jitter;
+    }
+    if let Some(ref value) = file_config.retry_deadline {
+        retry.deadline = retry::parse_delay(value)?;
+    }
+
+    Ok(ProviderConfig {
+        kind,
+        base_url,
+        api_key,
+        retry,
+        verbose: args.verbose,
+    })
+}
+
+/// Keeps only the first and last four characters of a secret
+fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 12 {
+        return "*".repeat(chars.len());
+    }
+    let start: String = chars[..4].iter().collect();
+    let end: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", start, end)
+}
+
+/// Loads only the transcript and cache settings, for commands that need
+/// no provider
+pub fn load_transcript_settings(args: &Args) -> Result<(TranscriptOptions, CachePolicy)> {
+    let file_config = load_config_file(args)?;
+    Ok((
+        resolve_transcript_options(args, &file_config)?,
+        resolve_cache_policy(args, &file_config),
+    ))
+}
+
+fn resolve_transcript_options(args: &Args, file_config: &FileConfig) -> Result<TranscriptOptions> {
+    // Transcript languages: CLI > config file > English
+    let mut transcript = TranscriptOptions::default();
+    if let Some(languages) = args
+        .languages
+        .as_deref()
+        .or(file_config.languages.as_deref())
+    {
+        transcript.languages = parse_language_list(languages)?;
+    }
+    transcript.translate_to = args.translate.clone().or(file_config.translate.clone());
+    Ok(transcript)
+}
+
+fn resolve_cache_policy(args: &Args, file_config: &FileConfig) -> CachePolicy {
+    // Cache: --no-cache > --refresh > config file > on
+    if args.no_cache {
+        CachePolicy::Bypass
+    } else if args.refresh {
+        CachePolicy::Refresh
+    } else if file_config.cache == Some(false) {
+        CachePolicy::Bypass
+    } else {
+        CachePolicy::Use
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(Error::Config(format!(
+            "Invalid value for {}: '{}' (expected true or false)",
+            key, value
+        ))),
+    }
+}
+
+#[derive(Debug, Default)]
+struct FileConfig {
+    api_key: Option<String>,
+    model: Option<String>,
+    fallback_models: Option<Vec<String>>,
+    provider: Option<String>,
+    base_url: Option<String>,
+    stream: Option<bool>,
+    timestamps: Option<bool>,
+    languages: Option<String>,
+    translate: Option<String>,
+    output_language: Option<String>,
+    chapters: Option<String>,
+    cache: Option<bool>,
+    history: Option<bool>,
+    jobs: Option<String>,
+    format: Option<String>,
+    template: Option<String>,
+    max_cost: Option<String>,
+    serve_token: Option<String>,
+    retry_attempts: Option<String>,
+    retry_delay: Option<String>,
+    retry_jitter: Option<bool>,
+    retry_deadline: Option<String>,
+}
+
+impl FileConfig {
+    /// Sets the setting named `key`; returns false for unknown keys
+    fn set(&mut self, key: &str, value: &str) -> Result<bool> {
+        match key {
+            "api_key" => self.api_key = Some(value.to_string()),
+            "default_model" => self.model = Some(value.to_string()),
+            "fallback_models" => {
+                self.fallback_models = Some(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|model| !model.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                )
+            }
+            "provider" => self.provider = Some(value.to_string()),
+            "base_url" => self.base_url = Some(value.to_string()),
+            "stream" => self.stream = Some(parse_bool(key, value)?),
+            "timestamps" => self.timestamps = Some(parse_bool(key, value)?),
+            "lang" => self.languages = Some(value.to_string()),
+            "translate" => self.translate = Some(value.to_string()),
+            "output_lang" => self.output_language = Some(value.to_string()),
+            "chapters" => self.chapters = Some(value.to_string()),
+            "cache" => self.cache = Some(parse_bool(key, value)?),
+            "history" => self.history = Some(parse_bool(key, value)?),
+            "jobs" => self.jobs = Some(value.to_string()),
+            "format" => self.format = Some(value.to_string()),
+            "template" => self.template = Some(value.to_string()),
+            "max_cost" => self.max_cost = Some(value.to_string()),
+            "serve_token" => self.serve_token = Some(value.to_string()),
+            "retry_attempts" => self.retry_attempts = Some(value.to_string()),
+            "retry_delay" => self.retry_delay = Some(value.to_string()),
+            "retry_jitter" => self.retry_jitter = Some(parse_bool(key, value)?),
+            "retry_deadline" => self.retry_deadline = Some(value.to_string()),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Credentials {
+    openrouter_api_key: Option<String>,
+    openai_api_key: Option<String>,
+}
+
+impl Credentials {
+    fn load() -> Result<Self> {
+        let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        let path = PathBuf::from(home).join(".config/youtube-summary/credentials");
+
+        if !path.exists() {
+            return Ok(Credentials::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| Error::Config(format!("Failed to read credentials file: {}", e)))?;
+
+        let mut credentials = Credentials::default();
+
+        for line in content.lines() {
+            let line = line.trim();
+
+            // Skip empty lines and comments
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once('=') {
+                let key = key.trim();
+                let value = value.trim().trim_matches('"').trim_matches('\'');
+
+                match key {
+                    "OPENROUTER_API_KEY" => {
+                        credentials.openrouter_api_key = Some(value.to_string())
+                    }
+                    "OPENAI_API_KEY" => credentials.openai_api_key = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(credentials)
+    }
+}
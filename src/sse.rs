@@ -0,0 +1,155 @@
+/// Incremental parser for server-sent event streams.
+///
+/// Bytes are fed in as they arrive from the network; complete events are
+/// returned as their joined `data:` payloads. Comment lines (starting with
+/// `:`) used as keep-alives and fields other than `data` are ignored.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a chunk of bytes and returns the payloads of all events
+    /// completed by it
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if let Some(event) = self.process_line(line) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    /// Flushes any event left unterminated when the stream ends
+    pub fn finish(&mut self) -> Option<String> {
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&self.buffer).into_owned();
+            self.buffer.clear();
+            if let Some(event) = self.process_line(line.trim_end_matches('\r')) {
+                return Some(event);
+            }
+        }
+
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<String> {
+        // A blank line terminates the current event
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        // Comments are used as keep-alives
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        if field == "data" {
+            self.data.push(value.to_string());
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<String> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let event = self.data.join("\n");
+        self.data.clear();
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `stream` in chunks of `size` bytes and collects every event
+    fn parse_in_chunks(stream: &[u8], size: usize) -> Vec<String> {
+        let mut parser = SseParser::new();
+        let mut events: Vec<String> = stream.chunks(size).flat_map(|c| parser.feed(c)).collect();
+        events.extend(parser.finish());
+        events
+    }
+
+    #[test]
+    fn parses_events_split_at_any_boundary() {
+        let stream = "data: {\"a\":1}\n\ndata: {\"b\":\"é\"}\n\ndata: [DONE]\n\n".as_bytes();
+        for size in 1..=stream.len() {
+            assert_eq!(
+                parse_in_chunks(stream, size),
+                ["{\"a\":1}", "{\"b\":\"é\"}", "[DONE]"],
+                "chunks of {} bytes",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn parses_crlf_lines() {
+        let stream = b"data: one\r\n\r\ndata: two\r\n\r\n";
+        for size in 1..=stream.len() {
+            assert_eq!(parse_in_chunks(stream, size), ["one", "two"]);
+        }
+    }
+
+    #[test]
+    fn ignores_comments_and_other_fields() {
+        let stream = b": OPENROUTER PROCESSING\n\nevent: message\nid: 7\nretry: 100\ndata: one\n:keep-alive\n\n";
+        assert_eq!(parse_in_chunks(stream, 5), ["one"]);
+    }
+
+    #[test]
+    fn joins_multi_line_data() {
+        let stream = b"data: first\ndata:second\ndata:  third\ndata\n\n";
+        assert_eq!(parse_in_chunks(stream, 3), ["first\nsecond\n third\n"]);
+    }
+
+    #[test]
+    fn returns_done_as_a_payload() {
+        let mut parser = SseParser::new();
+        assert_eq!(parser.feed(b"data: [DONE]\n"), Vec::<String>::new());
+        assert_eq!(parser.feed(b"\n"), ["[DONE]"]);
+        assert_eq!(parser.finish(), None);
+    }
+
+    #[test]
+    fn flushes_an_unterminated_event_at_the_end() {
+        assert_eq!(
+            parse_in_chunks(b"data: one\n\ndata: two", 4),
+            ["one", "two"]
+        );
+        assert_eq!(parse_in_chunks(b"data: two\n", 4), ["two"]);
+        assert_eq!(parse_in_chunks(b"data: two\r", 4), ["two"]);
+    }
+
+    #[test]
+    fn skips_blank_lines_without_data() {
+        assert_eq!(
+            parse_in_chunks(b"\n\n\r\n: ping\n\n", 2),
+            Vec::<String>::new()
+        );
+        assert_eq!(parse_in_chunks(b"data:\n\n", 2), [""]);
+    }
+}
@@ -0,0 +1,637 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::timestamps;
+use crate::transcript::{Snippet, Transcript, TranscriptOptions};
+use crate::video_id::VideoId;
+
+/// Video ID given to transcripts read from stdin
+const STDIN_ID: &str = "stdin";
+
+/// A subtitle file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Sbv,
+    /// YouTube's JSON caption format
+    Json3,
+    /// JSON written by `transcript export`, or json3 captions
+    Json,
+    /// Plain text, optionally with a timestamp at the start of each line
+    Text,
+}
+
+impl SubtitleFormat {
+    /// The format belonging to a file extension
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            "sbv" => Some(SubtitleFormat::Sbv),
+            "json3" => Some(SubtitleFormat::Json3),
+            "json" => Some(SubtitleFormat::Json),
+            "txt" | "text" => Some(SubtitleFormat::Text),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format of subtitles without a file name
+    pub fn detect(content: &str) -> Self {
+        let content = content.trim_start_matches('\u{feff}').trim_start();
+        if content.starts_with("WEBVTT") {
+            SubtitleFormat::Vtt
+        } else if content.starts_with('{') {
+            SubtitleFormat::Json
+        } else if content.contains("-->") {
+            SubtitleFormat::Srt
+        } else if content
+            .lines()
+            .next()
+            .is_some_and(|line| parse_sbv_timing(line).is_some())
+        {
+            SubtitleFormat::Sbv
+        } else {
+            SubtitleFormat::Text
+        }
+    }
+}
+
+/// Whether an input names a local subtitle file, or stdin as "-", rather
+/// than a YouTube video. Anything that parses as a video is a video unless
+/// it is given as a path, such as `./captions.srt` or `file:captions.srt`.
+/// Other inputs count as files if they are absolute or end in a subtitle
+/// extension, even if they don't exist.
+pub fn is_local(input: &str) -> bool {
+    if input == "-"
+        || input.starts_with("file:")
+        || input.starts_with("./")
+        || input.starts_with("../")
+    {
+        return true;
+    }
+    if VideoId::parse(input).is_ok() {
+        return false;
+    }
+
+    let path = Path::new(input);
+    path.is_absolute()
+        || path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| SubtitleFormat::from_extension(e).is_some())
+}
+
+/// The path of a local input, without a `file:` or `file://` prefix
+fn file_path(input: &str) -> &str {
+    input
+        .strip_prefix("file://")
+        .or_else(|| input.strip_prefix("file:"))
+        .unwrap_or(input)
+}
+
+/// Name of the transcript read from a local input: the file name without
+/// its extension
+pub fn input_name(input: &str) -> String {
+    Path::new(file_path(input))
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(STDIN_ID)
+        .to_string()
+}
+
+/// Reads a local subtitle file, or stdin if `input` is "-", into a
+/// transcript named after the file. Local files don't say which language
+/// they are in, except for some WebVTT files, so the first `--lang`
+/// language is assumed.
+pub fn read_subtitles(input: &str, options: &TranscriptOptions) -> Result<Transcript> {
+    if options.translate_to.is_some() {
+        return Err(Error::Config(
+            "--translate only works with YouTube transcripts, not local files".to_string(),
+        ));
+    }
+
+    let (content, format, video_id) = if input == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| Error::TranscriptFetch(format!("Failed to read stdin: {}", e)))?;
+        let format = SubtitleFormat::detect(&content);
+        (content, format, STDIN_ID.to_string())
+    } else {
+        let path = Path::new(file_path(input));
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::TranscriptFetch(format!("Failed to read {}: {}", input, e)))?;
+        let format = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(SubtitleFormat::from_extension)
+            .unwrap_or_else(|| SubtitleFormat::detect(&content));
+        (content, format, input_name(input))
+    };
+
+    let content = content.trim_start_matches('\u{feff}');
+    let mut file_language = None;
+    let snippets = match format {
+        SubtitleFormat::Srt => parse_srt(content),
+        SubtitleFormat::Vtt => {
+            file_language = vtt_language(content);
+            parse_vtt(content)
+        }
+        SubtitleFormat::Sbv => parse_sbv(content),
+        SubtitleFormat::Json3 => parse_json3(content)?,
+        SubtitleFormat::Json => {
+            let (snippets, language) = parse_json(content)?;
+            file_language = language;
+            snippets
+        }
+        SubtitleFormat::Text => parse_text(content),
+    };
+    if snippets.is_empty() {
+        return Err(Error::TranscriptFetch(format!(
+            "No captions found in {}",
+            input
+        )));
+    }
+
+    let language = file_language
+        .or_else(|| options.languages.first().cloned())
+        .unwrap_or_else(|| "en".to_string());
+
+    Ok(Transcript {
+        video_id,
+        language,
+        snippets,
+        file: Some(input.to_string()),
+    })
+}
+
+/// Parses SubRip subtitles: numbered cues with `00:00:01,000 --> ...`
+/// timings
+pub fn parse_srt(content: &str) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    for cue in parse_cues(content) {
+        push_snippet(&mut snippets, &cue.lines.join(" "), cue.start, cue.duration);
+    }
+    snippets
+}
+
+/// Parses WebVTT subtitles. YouTube's automatic captions repeat the last
+/// line of the previous cue at the top of each cue, which is dropped.
+pub fn parse_vtt(content: &str) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    let mut previous: Option<String> = None;
+
+    for cue in parse_cues(content) {
+        let mut lines = cue.lines.as_slice();
+        if let Some((first, rest)) = lines.split_first()
+            && previous.as_ref() == Some(first)
+        {
+            lines = rest;
+        }
+        if let Some(last) = lines.last() {
+            previous = Some(last.clone());
+        }
+        push_snippet(&mut snippets, &lines.join(" "), cue.start, cue.duration);
+    }
+
+    snippets
+}
+
+/// A caption with its text lines, as read from SRT or WebVTT
+struct Cue {
+    start: f64,
+    duration: f64,
+    lines: Vec<String>,
+}
+
+/// Reads blocks of a timing line with `-->` followed by text lines, as
+/// used by SRT and WebVTT. Blocks without a timing line, such as the
+/// WebVTT header and notes, are skipped.
+fn parse_cues(content: &str) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    let mut lines = content.lines();
+
+    while let Some(line) = lines.next() {
+        let Some((start, end)) = parse_arrow_timing(line) else {
+            continue;
+        };
+        let text = lines
+            .by_ref()
+            .take_while(|line| !line.trim().is_empty())
+            .map(clean_text)
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        cues.push(Cue {
+            start,
+            duration: end - start,
+            lines: text,
+        });
+    }
+
+    cues
+}
+
+/// Parses SubViewer subtitles: `0:00:01.000,0:00:04.000` followed by text
+pub fn parse_sbv(content: &str) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    let mut lines = content.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let Some((start, end)) = parse_sbv_timing(line) else {
+            continue;
+        };
+        let mut text = Vec::new();
+        while let Some(line) = lines.next_if(|line| !line.trim().is_empty()) {
+            text.push(clean_text(line));
+        }
+        push_snippet(&mut snippets, &text.join(" "), start, end - start);
+    }
+
+    snippets
+}
+
+/// Parses a transcript written by `transcript export --format json`, with
+/// its language, or json3 captions
+pub fn parse_json(content: &str) -> Result<(Vec<Snippet>, Option<String>)> {
+    let data: Value = serde_json::from_str(content)
+        .map_err(|e| Error::TranscriptFetch(format!("Invalid JSON captions: {}", e)))?;
+    if data.get("events").is_some() {
+        return Ok((parse_json3(content)?, None));
+    }
+
+    let transcript: Transcript = serde_json::from_value(data).map_err(|e| {
+        Error::TranscriptFetch(format!(
+            "JSON captions are neither an exported transcript nor json3: {}",
+            e
+        ))
+    })?;
+    let mut snippets = Vec::new();
+    for snippet in transcript.snippets {
+        push_snippet(
+            &mut snippets,
+            &snippet.text,
+            snippet.start,
+            snippet.duration,
+        );
+    }
+    Ok((snippets, Some(transcript.language)))
+}
+
+/// Parses YouTube's json3 caption format, a list of events with segments
+pub fn parse_json3(content: &str) -> Result<Vec<Snippet>> {
+    let data: Value = serde_json::from_str(content)
+        .map_err(|e| Error::TranscriptFetch(format!("Invalid json3 captions: {}", e)))?;
+    let events = data["events"]
+        .as_array()
+        .ok_or_else(|| Error::TranscriptFetch("json3 captions have no events".to_string()))?;
+
+    let mut snippets = Vec::new();
+    for event in events {
+        let Some(segments) = event["segs"].as_array() else {
+            continue;
+        };
+        let text: String = segments
+            .iter()
+            .filter_map(|segment| segment["utf8"].as_str())
+            .collect();
+        let start = event["tStartMs"].as_f64().unwrap_or_default() / 1000.0;
+        let duration = event["dDurationMs"].as_f64().unwrap_or_default() / 1000.0;
+        push_snippet(&mut snippets, &text.replace('\n', " "), start, duration);
+    }
+
+    Ok(snippets)
+}
+
+/// Parses plain text, one snippet per line. Lines may start with a
+/// timestamp such as `[01:23]` or `1:23`, as in `transcript` output; each
+/// timed snippet lasts until the next one starts.
+pub fn parse_text(content: &str) -> Vec<Snippet> {
+    let mut snippets: Vec<Snippet> = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        let (start, text) = match line.split_once(char::is_whitespace) {
+            Some((time, text)) => {
+                let time = time.trim_start_matches('[').trim_end_matches(']');
+                match timestamps::parse_timestamp(time) {
+                    Some(start) => (Some(start as f64), text),
+                    None => (None, line),
+                }
+            }
+            None => (None, line),
+        };
+
+        // Untimed lines continue from the previous snippet
+        let start = start.unwrap_or_else(|| snippets.last().map_or(0.0, |last| last.start));
+        if let Some(last) = snippets.last_mut()
+            && start > last.start
+        {
+            last.duration = start - last.start;
+        }
+        push_snippet(&mut snippets, text, start, 0.0);
+    }
+
+    snippets
+}
+
+fn push_snippet(snippets: &mut Vec<Snippet>, text: &str, start: f64, duration: f64) {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() {
+        snippets.push(Snippet {
+            text,
+            start,
+            duration: duration.max(0.0),
+        });
+    }
+}
+
+/// Parses a `00:00:01,000 --> 00:00:04,000` timing line, ignoring WebVTT
+/// cue settings after the end time
+fn parse_arrow_timing(line: &str) -> Option<(f64, f64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_time(start.trim())?, parse_time(end)?))
+}
+
+fn parse_sbv_timing(line: &str) -> Option<(f64, f64)> {
+    let (start, end) = line.trim().split_once(',')?;
+    Some((parse_time(start)?, parse_time(end)?))
+}
+
+/// Parses `h:mm:ss.mmm`, `mm:ss.mmm` or `hh:mm:ss,mmm` into seconds
+fn parse_time(s: &str) -> Option<f64> {
+    let s = s.replace(',', ".");
+    let parts: Vec<&str> = s.split(':').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return None;
+    }
+
+    let (seconds, whole) = parts.split_last()?;
+    let mut total = 0.0;
+    for part in whole {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        total = total * 60.0 + part.parse::<f64>().ok()?;
+    }
+    if !seconds.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    Some(total * 60.0 + seconds.parse::<f64>().ok()?)
+}
+
+/// Removes markup from a caption line: `<i>` and WebVTT tags such as
+/// `<c>` or `<00:00:01.000>`, and SSA overrides such as `{\an8}`
+fn clean_text(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let end = match c {
+            '<' => '>',
+            '{' if chars.peek() == Some(&'\\') => '}',
+            _ => {
+                text.push(c);
+                continue;
+            }
+        };
+        for c in chars.by_ref() {
+            if c == end {
+                break;
+            }
+        }
+    }
+
+    text.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+}
+
+/// Reads the `Language:` header of a WebVTT file
+fn vtt_language(content: &str) -> Option<String> {
+    content
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .find_map(|line| line.strip_prefix("Language:"))
+        .map(|language| language.trim().to_string())
+        .filter(|language| !language.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(snippets: &[Snippet]) -> Vec<(f64, f64, &str)> {
+        snippets
+            .iter()
+            .map(|s| (s.start, s.duration, s.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_srt() {
+        let srt = "1\n00:00:01,000 --> 00:00:04,500\nHello <i>world</i>\n\n\
+                   2\n00:00:05,250 --> 00:00:07,000\n{\\an8}Two\nlines\n\n\
+                   10\n01:00:00,000 --> 01:00:02,000\n&amp; more\n";
+        assert_eq!(
+            texts(&parse_srt(srt)),
+            [
+                (1.0, 3.5, "Hello world"),
+                (5.25, 1.75, "Two lines"),
+                (3600.0, 2.0, "& more")
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_srt_with_crlf_and_dot_separators() {
+        let srt = "1\r\n00:00:01.000 --> 00:00:02.000\r\nDot\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\nComma\r\n";
+        assert_eq!(
+            texts(&parse_srt(srt)),
+            [(1.0, 1.0, "Dot"), (3.0, 1.0, "Comma")]
+        );
+    }
+
+    #[test]
+    fn skips_srt_cues_without_text() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n\n2\n00:00:03,000 --> 00:00:04,000\n<b></b>\n\n3\nnot a timing\ntext\n";
+        assert!(parse_srt(srt).is_empty());
+    }
+
+    #[test]
+    fn parses_vtt_with_headers_notes_and_styles() {
+        let vtt = "WEBVTT - Example\nKind: captions\nLanguage: de\n\n\
+                   STYLE\n::cue {\n  color: yellow;\n}\n\n\
+                   NOTE This is a comment\nspanning two lines\n\n\
+                   intro\n00:01.000 --> 00:04.000 align:start position:0%\n<v Speaker>Hallo</v> <c.yellow>Welt</c>\n\n\
+                   00:00:05.000 --> 00:00:06.000\nZweite\nZeile\n";
+        assert_eq!(
+            texts(&parse_vtt(vtt)),
+            [(1.0, 3.0, "Hallo Welt"), (5.0, 1.0, "Zweite Zeile")]
+        );
+        assert_eq!(vtt_language(vtt).as_deref(), Some("de"));
+    }
+
+    #[test]
+    fn drops_lines_repeated_by_automatic_captions() {
+        let vtt = "WEBVTT\n\n\
+                   00:00:00.000 --> 00:00:02.000\nfirst line\n\n\
+                   00:00:02.000 --> 00:00:04.000\nfirst line\nsecond<00:00:02.500><c> line</c>\n\n\
+                   00:00:04.000 --> 00:00:06.000\nsecond line\nthird line\n";
+        assert_eq!(
+            texts(&parse_vtt(vtt)),
+            [
+                (0.0, 2.0, "first line"),
+                (2.0, 2.0, "second line"),
+                (4.0, 2.0, "third line")
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_sbv() {
+        let sbv = "0:00:01.000,0:00:03.000\nFirst\ncue\n\n0:01:00.500,0:01:02.000\nSecond\n";
+        assert_eq!(
+            texts(&parse_sbv(sbv)),
+            [(1.0, 2.0, "First cue"), (60.5, 1.5, "Second")]
+        );
+    }
+
+    #[test]
+    fn parses_json3() {
+        let json = r#"{"wireMagic":"pb3","events":[
+            {"tStartMs":0,"dDurationMs":5000,"id":1,"wpWinPosId":1},
+            {"tStartMs":1000,"dDurationMs":2500,"segs":[{"utf8":"Hello"},{"utf8":" world","tOffsetMs":400}]},
+            {"tStartMs":3500,"segs":[{"utf8":"\n"}]},
+            {"tStartMs":4000,"dDurationMs":1000,"segs":[{"utf8":"two\nlines"}]}
+        ]}"#;
+        assert_eq!(
+            texts(&parse_json3(json).unwrap()),
+            [(1.0, 2.5, "Hello world"), (4.0, 1.0, "two lines")]
+        );
+        assert!(parse_json3("{}").is_err());
+        assert!(parse_json3("not json").is_err());
+    }
+
+    #[test]
+    fn parses_text_with_and_without_timestamps() {
+        let text = "[00:00] Intro\n[00:05] Second\ncontinued\n1:02:03 Late\n";
+        assert_eq!(
+            texts(&parse_text(text)),
+            [
+                (0.0, 5.0, "Intro"),
+                (5.0, 0.0, "Second"),
+                (5.0, 3718.0, "continued"),
+                (3723.0, 0.0, "Late")
+            ]
+        );
+
+        let plain = "Just words\n\nmore words";
+        assert_eq!(
+            texts(&parse_text(plain)),
+            [(0.0, 0.0, "Just words"), (0.0, 0.0, "more words")]
+        );
+    }
+
+    #[test]
+    fn parses_times() {
+        let cases: &[(&str, Option<f64>)] = &[
+            ("00:00:01,000", Some(1.0)),
+            ("00:00:01.250", Some(1.25)),
+            ("01:02:03.5", Some(3723.5)),
+            ("0:00:01.000", Some(1.0)),
+            ("02:03.000", Some(123.0)),
+            ("1", None),
+            ("a:00:01", None),
+            ("00:00:0x", None),
+            ("1:2:3:4", None),
+            ("", None),
+        ];
+        for &(value, expected) in cases {
+            assert_eq!(parse_time(value), expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn detects_formats() {
+        let cases = [
+            ("\u{feff}WEBVTT\n\n", SubtitleFormat::Vtt),
+            ("1\n00:00:01,000 --> 00:00:02,000\nx", SubtitleFormat::Srt),
+            ("0:00:01.000,0:00:02.000\nx", SubtitleFormat::Sbv),
+            ("{\"events\": []}", SubtitleFormat::Json),
+            ("[00:00] words", SubtitleFormat::Text),
+        ];
+        for (content, format) in cases {
+            assert_eq!(SubtitleFormat::detect(content), format, "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn reads_back_exported_json() {
+        let transcript = Transcript {
+            video_id: "dQw4w9WgXcQ".to_string(),
+            language: "de".to_string(),
+            snippets: vec![
+                Snippet {
+                    text: "Hallo".to_string(),
+                    start: 1.0,
+                    duration: 2.0,
+                },
+                Snippet {
+                    text: "Welt".to_string(),
+                    start: 3.0,
+                    duration: 1.5,
+                },
+            ],
+            file: None,
+        };
+        let json = crate::export::render(&transcript, crate::export::ExportFormat::Json).unwrap();
+        let (snippets, language) = parse_json(&json).unwrap();
+        assert_eq!(texts(&snippets), [(1.0, 2.0, "Hallo"), (3.0, 1.5, "Welt")]);
+        assert_eq!(language.as_deref(), Some("de"));
+
+        let json3 = r#"{"events":[{"tStartMs":0,"dDurationMs":1000,"segs":[{"utf8":"x"}]}]}"#;
+        let (snippets, language) = parse_json(json3).unwrap();
+        assert_eq!(texts(&snippets), [(0.0, 1.0, "x")]);
+        assert_eq!(language, None);
+
+        assert!(parse_json(r#"{"something": "else"}"#).is_err());
+    }
+
+    #[test]
+    fn recognizes_local_inputs() {
+        for input in [
+            "-",
+            "talk.srt",
+            "dir/talk.VTT",
+            "captions.json",
+            "captions.json3",
+            "notes.txt",
+            "./dQw4w9WgXcQ",
+            "../captions",
+            "/tmp/dQw4w9WgXcQ",
+            "file:dQw4w9WgXcQ",
+            "file:///tmp/captions",
+        ] {
+            assert!(is_local(input), "{:?}", input);
+        }
+        for input in [
+            "dQw4w9WgXcQ",
+            "https://youtu.be/dQw4w9WgXcQ",
+            "youtube.com/watch?v=dQw4w9WgXcQ",
+            "captions.mp4",
+            "-2BcDeFgHiJ",
+        ] {
+            assert!(!is_local(input), "{:?}", input);
+        }
+        assert_eq!(file_path("file:///tmp/a.srt"), "/tmp/a.srt");
+        assert_eq!(file_path("file:a.srt"), "a.srt");
+    }
+}
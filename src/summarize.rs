@@ -0,0 +1,1087 @@
+use std::future::Future;
+use std::iter;
+
+use crate::chunking;
+use crate::config::Config;
+use crate::cost::{self, Estimate, Prices};
+use crate::error::{Error, Result};
+use crate::metadata::{self, Chapter, VideoMetadata};
+use crate::models;
+use crate::provider::{ChatMessage, ChatRequest, Completion, Provider, Usage};
+use crate::timestamps;
+use crate::transcript::Transcript;
+
+/// Maximum number of tokens requested for each completion
+const MAX_TOKENS: u32 = 4096;
+
+/// Appended to the prompt when the transcript carries `[mm:ss]` markers
+const TIMESTAMP_INSTRUCTIONS: &str = "The transcript contains [mm:ss] markers giving the time \
+     at which each passage starts. After each key point, cite the time where it is discussed \
+     as a marker in exactly the same [mm:ss] format, copied from the nearest preceding marker \
+     in the transcript. Only cite times that appear in the transcript.";
+
+/// Asks for chapters covering a stretch of a timestamped transcript
+const PROPOSE_INSTRUCTIONS: &str = "Divide the following YouTube video transcript into \
+     chapters, the way a creator would for the video's chapter list. Start a new chapter \
+     wherever the topic changes; a chapter usually spans several minutes. Reply with one line \
+     per chapter and nothing else, each line being the [mm:ss] marker from the transcript \
+     where the chapter begins followed by a short title, e.g. \"[04:10] Setting up the \
+     project\".";
+
+/// How chapters shape a summary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChapterMode {
+    /// Summarize chapter by chapter if the video has chapters
+    #[default]
+    Auto,
+    /// Always summarize the whole video at once
+    Off,
+    /// Like `Auto`, but have the model propose chapters for videos
+    /// without them
+    Propose,
+}
+
+impl ChapterMode {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "auto" => Ok(ChapterMode::Auto),
+            "off" | "none" => Ok(ChapterMode::Off),
+            "propose" => Ok(ChapterMode::Propose),
+            other => Err(Error::Config(format!(
+                "Unknown chapter mode '{}' (expected auto, off or propose)",
+                other
+            ))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChapterMode::Auto => "auto",
+            ChapterMode::Off => "off",
+            ChapterMode::Propose => "propose",
+        }
+    }
+
+    /// Where the chapters of a video with the given metadata come from,
+    /// or `None` if it is summarized as a whole
+    pub fn source(&self, metadata: Option<&VideoMetadata>) -> Option<ChapterSource> {
+        let has_chapters = metadata.is_some_and(|m| m.chapters.len() > 1);
+        match self {
+            ChapterMode::Off => None,
+            _ if has_chapters => Some(ChapterSource::Video),
+            ChapterMode::Propose => Some(ChapterSource::Model),
+            ChapterMode::Auto => None,
+        }
+    }
+}
+
+/// Where the chapters of a chapter-by-chapter summary come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChapterSource {
+    /// The chapters shown on YouTube
+    Video,
+    /// Chapters proposed by the model
+    Model,
+}
+
+impl ChapterSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChapterSource::Video => "video",
+            ChapterSource::Model => "model",
+        }
+    }
+}
+
+/// A generated summary with the usage of all requests it took
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub text: String,
+    /// The model that produced the summary
+    pub model: String,
+    /// `None` if the provider reported no usage
+    pub usage: Option<Usage>,
+}
+
+/// Summarizes a transcript, giving the model the video's metadata as
+/// context if known
+pub async fn summarize<P: Provider>(
+    provider: &P,
+    config: &Config,
+    transcript: &Transcript,
+    metadata: Option<&VideoMetadata>,
+) -> Result<Summary> {
+    with_fallbacks(config, |config| async move {
+        summarize_with_model(provider, &config, transcript, metadata).await
+    })
+    .await
+}
+
+async fn summarize_with_model<P: Provider>(
+    provider: &P,
+    config: &Config,
+    transcript: &Transcript,
+    metadata: Option<&VideoMetadata>,
+) -> Result<Summary> {
+    if config.verbose {
+        eprintln!("[verbose] Model: {}", config.model);
+        eprintln!(
+            "[verbose] Transcript length: {} chars",
+            transcript.text_len()
+        );
+    }
+
+    let mut prompt = config.prompt.clone();
+    if config.timestamps {
+        prompt = format!("{}\n\n{}", prompt, TIMESTAMP_INSTRUCTIONS);
+    }
+    if let Some(ref language) = config.output_language {
+        prompt = format!(
+            "{}\n\nWrite your response in {}, regardless of the language of the transcript.",
+            prompt, language
+        );
+    }
+    if let Some(metadata) = metadata {
+        prompt = format!("{}\n\n{}", prompt, metadata.prompt_context());
+    }
+
+    let context_length = fetch_context_length(provider, config).await;
+    let budget = chunking::chunk_budget(context_length, &map_prompt(&prompt, 0, 0), MAX_TOKENS);
+
+    let mut config = config.clone();
+    let mut usage = None;
+    let mut chapters = Vec::new();
+    match config.chapters.source(metadata) {
+        Some(ChapterSource::Video) => {
+            chapters = metadata.map(|m| m.chapters.clone()).unwrap_or_default();
+        }
+        Some(ChapterSource::Model) => {
+            let (proposal, prices) =
+                propose_chapters(provider, &config, transcript, context_length).await?;
+
+            // The proposal counts against the cost limit of the summary
+            if let (Some(max_cost), Some(prices), Some(spent)) =
+                (config.max_cost, prices, proposal.usage)
+            {
+                config.max_cost = Some((max_cost - prices.usage_cost(&spent)).max(0.0));
+            }
+            add_usage(&mut usage, proposal.usage);
+            chapters = proposal.chapters;
+        }
+        None => {}
+    }
+    let proposed = config.chapters.source(metadata) == Some(ChapterSource::Model);
+
+    let (mut summary, prices) = if chapters.len() > 1 {
+        let chaptered = Chaptered {
+            chapters: &chapters,
+            proposed,
+        };
+        summarize_by_chapter(
+            provider,
+            &config,
+            &prompt,
+            transcript,
+            chaptered,
+            context_length,
+            budget,
+        )
+        .await?
+    } else {
+        // Timestamped passages read better on their own lines
+        let separator = if config.timestamps { "\n" } else { " " };
+        let pieces = transcript.pieces(config.timestamps);
+        let chunks = chunking::pack(pieces.iter().map(String::as_str), separator, budget);
+
+        let estimate = Estimate::new(&prompt, &chunks, MAX_TOKENS);
+        let prices = check_budget(provider, &config, &estimate).await?;
+
+        let summary =
+            summarize_chunks(provider, &config, &prompt, context_length, chunks, budget).await?;
+        (summary, prices)
+    };
+
+    add_usage(&mut summary.usage, usage);
+    report_cost(&config, prices, &mut summary);
+    Ok(summary)
+}
+
+/// Summarizes transcript chunks: in a single request if there is only
+/// one, otherwise chunk by chunk and then combined
+async fn summarize_chunks<P: Provider>(
+    provider: &P,
+    config: &Config,
+    prompt: &str,
+    context_length: u64,
+    chunks: Vec<String>,
+    budget: usize,
+) -> Result<Summary> {
+    if chunks.len() > 1 {
+        return map_reduce(provider, config, prompt, context_length, chunks, budget).await;
+    }
+
+    let user_content = format!(
+        "{}\n\n---\n\nTranscript:\n{}",
+        prompt,
+        chunks.into_iter().next().unwrap_or_default()
+    );
+    let completion = complete(
+        provider,
+        config,
+        context_length,
+        user_content,
+        config.stream,
+    )
+    .await?;
+    Ok(Summary {
+        model: answering_model(config, &completion),
+        text: completion.text,
+        usage: completion.usage,
+    })
+}
+
+/// Chapters proposed by the model
+struct Proposal {
+    chapters: Vec<Chapter>,
+    usage: Option<Usage>,
+}
+
+/// Has the model divide a video without chapters into chapters, section
+/// by section if the transcript doesn't fit into one request. Returns
+/// the model's prices along with the proposal.
+async fn propose_chapters<P: Provider>(
+    provider: &P,
+    config: &Config,
+    transcript: &Transcript,
+    context_length: u64,
+) -> Result<(Proposal, Option<Prices>)> {
+    let budget = chunking::chunk_budget(context_length, PROPOSE_INSTRUCTIONS, MAX_TOKENS);
+    let pieces = transcript.pieces(true);
+    let chunks = chunking::pack(pieces.iter().map(String::as_str), "\n", budget);
+
+    // Each section is answered on its own, there is no combining request
+    let mut estimate = Estimate::default();
+    for chunk in &chunks {
+        estimate.add(Estimate::new(
+            PROPOSE_INSTRUCTIONS,
+            std::slice::from_ref(chunk),
+            MAX_TOKENS,
+        ));
+    }
+    let prices = check_budget(provider, config, &estimate).await?;
+
+    if config.verbose {
+        eprintln!(
+            "[verbose] Asking {} to propose chapters in {} request(s)",
+            config.model,
+            chunks.len()
+        );
+    }
+
+    let mut lines = String::new();
+    let mut usage = None;
+    for chunk in chunks {
+        let user_content = format!("{}\n\n---\n\nTranscript:\n{}", PROPOSE_INSTRUCTIONS, chunk);
+        let completion = complete(provider, config, context_length, user_content, false).await?;
+        add_usage(&mut usage, completion.usage);
+        lines.push_str(&completion.text);
+        lines.push('\n');
+    }
+
+    let mut chapters = metadata::parse_chapter_lines(&lines);
+    // The first chapter always starts with the video
+    if let Some(first) = chapters.first_mut() {
+        first.start = 0;
+    }
+    chapters.retain(|chapter| chapter.start as f64 <= transcript.duration());
+
+    if config.verbose {
+        eprintln!("[verbose] Model proposed {} chapters", chapters.len());
+    }
+
+    Ok((Proposal { chapters, usage }, prices))
+}
+
+/// The chapters a summary is organized by
+#[derive(Clone, Copy)]
+struct Chaptered<'a> {
+    chapters: &'a [Chapter],
+    /// Whether the model proposed the chapters rather than the creator
+    proposed: bool,
+}
+
+/// Summarizes each chapter of a video on its own, then writes an overall
+/// summary from the chapter summaries. The result has a section per
+/// chapter, headed by its title and a link to its start, followed by the
+/// overall summary. Returns the model's prices along with the summary.
+async fn summarize_by_chapter<P: Provider>(
+    provider: &P,
+    config: &Config,
+    prompt: &str,
+    transcript: &Transcript,
+    chaptered: Chaptered<'_>,
+    context_length: u64,
+    budget: usize,
+) -> Result<(Summary, Option<Prices>)> {
+    let chapters = chaptered.chapters;
+    let starts: Vec<u64> = chapters.iter().map(|chapter| chapter.start).collect();
+    let parts = transcript.split_at(&starts);
+
+    // Timestamped passages read better on their own lines
+    let separator = if config.timestamps { "\n" } else { " " };
+    let total = chapters.len();
+    let mut estimate = Estimate::new(prompt, &[], MAX_TOKENS);
+    estimate.prompt_tokens += total as u64 * u64::from(MAX_TOKENS);
+    let mut work = Vec::with_capacity(total);
+    for (i, (chapter, part)) in chapters.iter().zip(&parts).enumerate() {
+        let chapter_prompt = chapter_prompt(prompt, chapter, i + 1, total);
+        let pieces = part.pieces(config.timestamps);
+        let chunks = chunking::pack(pieces.iter().map(String::as_str), separator, budget);
+        if !chunks.is_empty() {
+            estimate.add(Estimate::new(&chapter_prompt, &chunks, MAX_TOKENS));
+        }
+        work.push((chapter, chapter_prompt, chunks));
+    }
+    let prices = check_budget(provider, config, &estimate).await?;
+
+    if config.verbose {
+        eprintln!("[verbose] Summarizing {} chapters", total);
+    }
+
+    let heading = if chaptered.proposed {
+        "## Chapters (proposed by the model)"
+    } else {
+        "## Chapters"
+    };
+    let mut text = format!("{}\n\n", heading);
+    if config.stream {
+        print!("{}", text);
+    }
+
+    // Chapter summaries are printed whole; only the overall one streams
+    let chapter_config = Config {
+        stream: false,
+        ..config.clone()
+    };
+    let mut usage = None;
+    let mut partials = Vec::with_capacity(total);
+    for (i, (chapter, chapter_prompt, chunks)) in work.into_iter().enumerate() {
+        if config.verbose {
+            eprintln!(
+                "[verbose] Summarizing chapter {}/{}: {}",
+                i + 1,
+                total,
+                chapter.title
+            );
+        }
+
+        let body = if chunks.is_empty() {
+            "No captions cover this chapter.".to_string()
+        } else {
+            let summary = summarize_chunks(
+                provider,
+                &chapter_config,
+                &chapter_prompt,
+                context_length,
+                chunks,
+                budget,
+            )
+            .await?;
+            add_usage(&mut usage, summary.usage);
+            summary.text.trim().to_string()
+        };
+
+        let start = timestamps::format_timestamp(chapter.start);
+        let marker = match transcript.youtube_id() {
+            Some(video_id) => format!(
+                "[{}]({})",
+                start,
+                timestamps::video_link(video_id, chapter.start)
+            ),
+            None => format!("[{}]", start),
+        };
+        let section = format!("### {} {}\n\n{}\n\n", marker, chapter.title, body);
+        if config.stream {
+            print!("{}", section);
+        }
+        text.push_str(&section);
+        partials.push(format!(
+            "Chapter {} \"{}\" [{}]:\n{}",
+            i + 1,
+            chapter.title,
+            start,
+            body
+        ));
+    }
+
+    text.push_str("## Summary\n\n");
+    if config.stream {
+        print!("## Summary\n\n");
+    }
+
+    let overall = reduce(
+        provider,
+        config,
+        PartialKind::Chapters,
+        prompt,
+        context_length,
+        partials,
+        budget,
+    )
+    .await?;
+    add_usage(&mut usage, overall.usage);
+    text.push_str(&overall.text);
+
+    Ok((
+        Summary {
+            text,
+            model: overall.model,
+            usage,
+        },
+        prices,
+    ))
+}
+
+/// Builds the instruction used to summarize one chapter of a video
+fn chapter_prompt(prompt: &str, chapter: &Chapter, number: usize, total: usize) -> String {
+    format!(
+        "The following is chapter {} of {} of a YouTube video, titled \"{}\" and starting at \
+         {}. Summarize this chapter in a short paragraph or a few bullet points, keeping what \
+         matters for this request about the whole video:\n\n{}",
+        number,
+        total,
+        chapter.title,
+        timestamps::format_timestamp(chapter.start),
+        prompt
+    )
+}
+
+/// Summarizes each chunk on its own, then combines the partial summaries
+async fn map_reduce<P: Provider>(
+    provider: &P,
+    config: &Config,
+    prompt: &str,
+    context_length: u64,
+    chunks: Vec<String>,
+    budget: usize,
+) -> Result<Summary> {
+    if config.verbose {
+        eprintln!(
+            "[verbose] Transcript exceeds context budget ({} chars), splitting into {} chunks",
+            budget,
+            chunks.len()
+        );
+    }
+
+    // Map: summarize each chunk independently
+    let total = chunks.len();
+    let mut partials = Vec::with_capacity(total);
+    let mut usage = None;
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        if config.verbose {
+            eprintln!("[verbose] Summarizing chunk {}/{}...", i + 1, total);
+        }
+        let user_content = format!(
+            "{}\n\n---\n\nTranscript section:\n{}",
+            map_prompt(prompt, i + 1, total),
+            chunk
+        );
+        let completion = complete(provider, config, context_length, user_content, false).await?;
+        add_usage(&mut usage, completion.usage);
+        partials.push(completion.text);
+    }
+
+    let mut summary = reduce(
+        provider,
+        config,
+        PartialKind::Sections,
+        prompt,
+        context_length,
+        partials,
+        budget,
+    )
+    .await?;
+    add_usage(&mut summary.usage, usage);
+    Ok(summary)
+}
+
+/// Writes an overview of a playlist or channel from the summaries of its
+/// videos, combining them in rounds if they don't fit into one request
+pub async fn overview<P: Provider>(
+    provider: &P,
+    config: &Config,
+    title: &str,
+    summaries: Vec<String>,
+) -> Result<Summary> {
+    let mut prompt = format!(
+        "Write an overview of the YouTube collection \"{}\" based on summaries of its videos. \
+         Describe the overall themes, how the videos relate to each other, and the most \
+         important points across all of them.",
+        title
+    );
+    if let Some(ref language) = config.output_language {
+        prompt = format!("{}\n\nWrite your response in {}.", prompt, language);
+    }
+
+    if config.verbose {
+        eprintln!(
+            "[verbose] Writing overview of '{}' from {} summaries",
+            title,
+            summaries.len()
+        );
+    }
+
+    with_fallbacks(config, |config| {
+        let prompt = &prompt;
+        let summaries = summaries.clone();
+        async move {
+            let context_length = fetch_context_length(provider, &config).await;
+            let budget = chunking::chunk_budget(
+                context_length,
+                &format!("{}\n\n{}", PartialKind::Videos.merge_note(), prompt),
+                MAX_TOKENS,
+            );
+
+            let estimate = Estimate::new(prompt, &[summaries.join("\n\n")], MAX_TOKENS);
+            let prices = check_budget(provider, &config, &estimate).await?;
+
+            let mut summary = reduce(
+                provider,
+                &config,
+                PartialKind::Videos,
+                prompt,
+                context_length,
+                summaries,
+                budget,
+            )
+            .await?;
+
+            report_cost(&config, prices, &mut summary);
+            Ok(summary)
+        }
+    })
+    .await
+}
+
+/// Runs `attempt` with the configured model, moving on to the next
+/// fallback model whenever it fails in a way another model may not
+async fn with_fallbacks<F, Fut>(config: &Config, mut attempt: F) -> Result<Summary>
+where
+    F: FnMut(Config) -> Fut,
+    Fut: Future<Output = Result<Summary>>,
+{
+    let chain: Vec<&String> = iter::once(&config.model)
+        .chain(&config.fallback_models)
+        .collect();
+
+    let mut index = 0;
+    loop {
+        let mut model_config = config.clone();
+        model_config.model = chain[index].clone();
+        model_config.fallback_models = chain[index + 1..].iter().map(|m| m.to_string()).collect();
+
+        let error = match attempt(model_config).await {
+            Ok(summary) => return Ok(summary),
+            Err(e) => e,
+        };
+
+        match fallback_reason(&error) {
+            Some(reason) if index + 1 < chain.len() => {
+                eprintln!(
+                    "Warning: model {} {}, falling back to {}",
+                    chain[index],
+                    reason,
+                    chain[index + 1]
+                );
+                if config.verbose {
+                    eprintln!("[verbose] {}", error);
+                }
+                index += 1;
+            }
+            _ => return Err(error),
+        }
+    }
+}
+
+/// Classifies the failures after which a different model may succeed.
+/// Other errors, such as an invalid API key, would fail for every model.
+fn fallback_reason(error: &Error) -> Option<&'static str> {
+    let Error::ApiStatus { status, message } = error else {
+        return None;
+    };
+    let message = message.to_lowercase();
+
+    match status {
+        429 => Some("is rate-limited"),
+        502 | 503 | 529 => Some("is over capacity"),
+        500..=599 => Some("is failing"),
+        404 | 410 => Some("is unavailable"),
+        400 | 413 | 422
+            if message.contains("context")
+                || message.contains("too long")
+                || (message.contains("maximum") && message.contains("token")) =>
+        {
+            Some("can't fit the request into its context window")
+        }
+        400 | 422
+            if message.contains("model")
+                && [
+                    "not found",
+                    "not a valid",
+                    "does not exist",
+                    "deprecated",
+                    "no endpoints",
+                ]
+                .iter()
+                .any(|phrase| message.contains(phrase)) =>
+        {
+            Some("is unavailable")
+        }
+        _ => None,
+    }
+}
+
+/// The model that produced a completion. Backends that route natively may
+/// answer with one of the fallback models; other reported names (such as
+/// versioned snapshots or local file names) are ignored.
+fn answering_model(config: &Config, completion: &Completion) -> String {
+    completion
+        .model
+        .as_ref()
+        .filter(|model| config.fallback_models.contains(model))
+        .unwrap_or(&config.model)
+        .clone()
+}
+
+/// What the partial summaries combined by `reduce` cover
+#[derive(Clone, Copy)]
+enum PartialKind {
+    /// Consecutive sections of one transcript
+    Sections,
+    /// Individual videos of a playlist or channel
+    Videos,
+    /// Consecutive chapters of one video
+    Chapters,
+}
+
+impl PartialKind {
+    /// Explains the partial summaries in the final request
+    fn final_note(self) -> &'static str {
+        match self {
+            PartialKind::Sections => {
+                "The transcript was too long to process at once, so it was split into \
+                 consecutive sections which were summarized separately. Base your answer on \
+                 these section summaries."
+            }
+            PartialKind::Videos => "Base your answer on these summaries of the individual videos.",
+            PartialKind::Chapters => {
+                "The video is divided into chapters, which were summarized separately and are \
+                 shown to the reader above your answer. Based on these chapter summaries, write \
+                 an overall summary of the video rather than going through it chapter by \
+                 chapter again."
+            }
+        }
+    }
+
+    /// Introduces an intermediate request merging a group of summaries
+    fn merge_note(self) -> &'static str {
+        match self {
+            PartialKind::Sections => {
+                "The following are summaries of consecutive sections of a YouTube video \
+                 transcript. Merge them into a single summary of the covered sections, keeping \
+                 all key points, facts and conclusions needed to answer this request about the \
+                 whole video:"
+            }
+            PartialKind::Videos => {
+                "The following are summaries of videos from a YouTube playlist or channel. \
+                 Merge them into a single summary of these videos, keeping all key points \
+                 needed to answer this request about the whole collection:"
+            }
+            PartialKind::Chapters => {
+                "The following are summaries of consecutive chapters of a YouTube video. Merge \
+                 them into a single summary of the covered chapters, keeping all key points, \
+                 facts and conclusions needed to answer this request about the whole video:"
+            }
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            PartialKind::Sections => "Section summaries",
+            PartialKind::Videos => "Video summaries",
+            PartialKind::Chapters => "Chapter summaries",
+        }
+    }
+}
+
+/// Combines partial summaries into the final summary.
+///
+/// If the partial summaries don't fit into a single request they are
+/// combined in groups first, repeating until a single request suffices.
+async fn reduce<P: Provider>(
+    provider: &P,
+    config: &Config,
+    kind: PartialKind,
+    prompt: &str,
+    context_length: u64,
+    mut partials: Vec<String>,
+    budget: usize,
+) -> Result<Summary> {
+    let mut round = 1;
+    let mut usage = None;
+
+    loop {
+        let groups = chunking::pack(partials.iter().map(String::as_str), "\n\n", budget);
+
+        if groups.len() <= 1 {
+            if config.verbose {
+                eprintln!(
+                    "[verbose] Combining {} partial summaries into final summary...",
+                    partials.len()
+                );
+            }
+            let user_content = format!(
+                "{}\n\n{}\n\n---\n\n{}:\n{}",
+                prompt,
+                kind.final_note(),
+                kind.heading(),
+                groups.into_iter().next().unwrap_or_default()
+            );
+            let completion = complete(
+                provider,
+                config,
+                context_length,
+                user_content,
+                config.stream,
+            )
+            .await?;
+            add_usage(&mut usage, completion.usage);
+            return Ok(Summary {
+                model: answering_model(config, &completion),
+                text: completion.text,
+                usage,
+            });
+        }
+
+        if groups.len() >= partials.len() {
+            return Err(Error::ApiRequest(format!(
+                "Model '{}' context window is too small to combine partial summaries",
+                config.model
+            )));
+        }
+
+        if config.verbose {
+            eprintln!(
+                "[verbose] Reduce round {}: combining {} partial summaries into {}",
+                round,
+                partials.len(),
+                groups.len()
+            );
+        }
+
+        let mut combined = Vec::with_capacity(groups.len());
+        for group in groups {
+            let user_content = format!(
+                "{}\n\n{}\n\n---\n\n{}:\n{}",
+                kind.merge_note(),
+                prompt,
+                kind.heading(),
+                group
+            );
+            let completion =
+                complete(provider, config, context_length, user_content, false).await?;
+            add_usage(&mut usage, completion.usage);
+            combined.push(completion.text);
+        }
+
+        partials = combined;
+        round += 1;
+    }
+}
+
+/// Refuses to send requests whose projected cost exceeds `--max-cost`.
+/// Returns the model's prices, if known, for reporting the actual cost.
+pub async fn check_budget<P: Provider>(
+    provider: &P,
+    config: &Config,
+    estimate: &Estimate,
+) -> Result<Option<Prices>> {
+    let prices = match models::model_info(provider, config, &config.model).await {
+        Ok(info) => info
+            .and_then(|info| info.pricing)
+            .and_then(|pricing| Prices::parse(&pricing)),
+        Err(e) => {
+            if config.verbose {
+                eprintln!("[verbose] Could not look up pricing: {}", e);
+            }
+            None
+        }
+    };
+    let projected = prices.map(|prices| estimate.cost(&prices));
+
+    if config.verbose {
+        eprintln!(
+            "[verbose] Estimate: {} request(s), ~{} prompt tokens, up to {} completion tokens, {}",
+            estimate.requests,
+            estimate.prompt_tokens,
+            estimate.completion_tokens,
+            projected
+                .map(|cost| format!("up to {}", cost::format_cost(cost)))
+                .unwrap_or_else(|| "cost unknown".to_string())
+        );
+    }
+
+    if let Some(max_cost) = config.max_cost {
+        match projected {
+            Some(cost) if cost > max_cost => {
+                return Err(Error::Budget(format!(
+                    "summarizing with {} may cost up to {}, more than the limit of {}",
+                    config.model,
+                    cost::format_cost(cost),
+                    cost::format_cost(max_cost)
+                )));
+            }
+            Some(_) => {}
+            None => {
+                return Err(Error::Budget(format!(
+                    "no pricing is known for model {}, so the cost limit can't be enforced",
+                    config.model
+                )));
+            }
+        }
+    }
+
+    Ok(prices)
+}
+
+/// Fills in the cost from the model's prices if the provider didn't report
+/// it, and prints it when a cost limit is set or in verbose mode
+fn report_cost(config: &Config, prices: Option<Prices>, summary: &mut Summary) {
+    let Some(ref mut usage) = summary.usage else {
+        return;
+    };
+    if let Some(prices) = prices {
+        usage.cost = Some(prices.usage_cost(usage));
+    }
+
+    if let Some(cost) = usage.cost
+        && (config.verbose || config.max_cost.is_some())
+    {
+        eprintln!(
+            "Cost: {} ({} prompt + {} completion tokens)",
+            cost::format_cost(cost),
+            usage.prompt_tokens,
+            usage.completion_tokens
+        );
+    }
+}
+
+/// Builds the instruction used to summarize one section of a long transcript
+fn map_prompt(prompt: &str, part: usize, total: usize) -> String {
+    format!(
+        "The following is part {} of {} of a YouTube video transcript. Summarize this part, \
+         keeping all key points, facts and conclusions needed to answer this request about \
+         the whole video:\n\n{}",
+        part, total, prompt
+    )
+}
+
+/// Sends a single chat completion request and returns the response.
+/// When `stream` is set, tokens are also written to stdout as they arrive.
+async fn complete<P: Provider>(
+    provider: &P,
+    config: &Config,
+    context_length: u64,
+    user_content: String,
+    stream: bool,
+) -> Result<Completion> {
+    let request = ChatRequest {
+        model: config.model.clone(),
+        fallback_models: config.fallback_models.clone(),
+        messages: vec![ChatMessage::user(user_content)],
+        max_tokens: MAX_TOKENS,
+        context_length,
+    };
+
+    if config.verbose {
+        eprintln!("[verbose] Sending request to {}...", provider.name());
+    }
+
+    let completion = provider.complete(&request, stream).await?;
+
+    if config.verbose {
+        if stream {
+            eprintln!();
+        }
+        eprintln!(
+            "[verbose] Response received: {} chars",
+            completion.text.len()
+        );
+        if let Some(usage) = completion.usage {
+            eprintln!(
+                "[verbose] Tokens: {} prompt, {} completion",
+                usage.prompt_tokens, usage.completion_tokens
+            );
+        }
+    }
+
+    Ok(completion)
+}
+
+/// Adds the usage of a completion to a running total
+fn add_usage(total: &mut Option<Usage>, usage: Option<Usage>) {
+    if let Some(usage) = usage {
+        total.get_or_insert_with(Usage::default).add(usage);
+    }
+}
+
+/// Looks up the context length of the configured model.
+/// Falls back to a conservative default if the model list can't be fetched.
+pub async fn fetch_context_length<P: Provider>(provider: &P, config: &Config) -> u64 {
+    // The cached model list usually knows it; Ollama only reports it per model
+    let context_length = match models::model_info(provider, config, &config.model).await {
+        Ok(Some(info)) if info.context_length.is_some() => Ok(info.context_length),
+        _ => provider.context_length(&config.model).await,
+    };
+    let context_length = match context_length {
+        Ok(length) => length,
+        Err(e) => {
+            if config.verbose {
+                eprintln!("[verbose] Could not look up context length: {}", e);
+            }
+            None
+        }
+    };
+
+    match context_length {
+        Some(length) => {
+            if config.verbose {
+                eprintln!("[verbose] Context length: {} tokens", length);
+            }
+            length
+        }
+        None => {
+            if config.verbose {
+                eprintln!(
+                    "[verbose] Context length unknown, assuming {} tokens",
+                    chunking::DEFAULT_CONTEXT_LENGTH
+                );
+            }
+            chunking::DEFAULT_CONTEXT_LENGTH
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    use crate::provider::ProviderKind;
+    use crate::summarizer::Summarizer;
+
+    fn status(status: u16, message: &str) -> Error {
+        Error::ApiStatus {
+            status,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn classifies_fallback_errors() {
+        let fall_back = [
+            (429, "Rate limit exceeded"),
+            (500, "Internal server error"),
+            (502, "Bad gateway"),
+            (503, "Service unavailable"),
+            (504, "Gateway timeout"),
+            (529, "Overloaded"),
+            (404, "Not found"),
+            (410, "Gone"),
+            (400, "The model `gpt-9` does not exist"),
+            (400, "m1 is not a valid model ID"),
+            (422, "Model not found"),
+            (400, "No endpoints found for model m1"),
+            (400, "This model's maximum context length is 8192 tokens"),
+            (413, "Prompt is too long"),
+        ];
+        for (code, message) in fall_back {
+            assert!(
+                fallback_reason(&status(code, message)).is_some(),
+                "{} {}",
+                code,
+                message
+            );
+        }
+
+        let fail = [
+            status(401, "Invalid API key"),
+            status(403, "Forbidden"),
+            status(402, "Insufficient credits"),
+            status(400, "Invalid request body"),
+            status(400, "model is required"),
+            status(422, "messages must not be empty"),
+            Error::ApiRequest("Failed to send request: connection refused".to_string()),
+            Error::Budget("Over the limit".to_string()),
+        ];
+        for error in fail {
+            assert_eq!(fallback_reason(&error), None, "{}", error);
+        }
+    }
+
+    /// Runs `with_fallbacks` over models a, b and c, failing each attempt
+    /// with the next of `errors`. Returns the outcome and the models tried.
+    async fn run_chain(errors: Vec<Error>) -> (Result<Summary>, Vec<String>) {
+        let config = Summarizer::builder()
+            .provider(ProviderKind::OpenAi)
+            .base_url("http://127.0.0.1:9")
+            .model("a")
+            .model("b")
+            .model("c")
+            .build()
+            .unwrap()
+            .config()
+            .clone();
+
+        let tried = RefCell::new(Vec::new());
+        let errors = RefCell::new(errors.into_iter());
+        let result = with_fallbacks(&config, |config| {
+            tried.borrow_mut().push(config.model.clone());
+            let outcome = match errors.borrow_mut().next() {
+                Some(error) => Err(error),
+                None => Ok(Summary {
+                    text: "done".to_string(),
+                    model: config.model.clone(),
+                    usage: None,
+                }),
+            };
+            async move { outcome }
+        })
+        .await;
+        (result, tried.into_inner())
+    }
+
+    #[tokio::test]
+    async fn falls_back_in_order() {
+        let (result, tried) = run_chain(vec![]).await;
+        assert_eq!(result.unwrap().model, "a");
+        assert_eq!(tried, ["a"]);
+
+        let (result, tried) = run_chain(vec![status(429, ""), status(404, "")]).await;
+        assert_eq!(result.unwrap().model, "c");
+        assert_eq!(tried, ["a", "b", "c"]);
+
+        // The last model's error is returned
+        let errors = vec![status(503, ""), status(503, ""), status(502, "last")];
+        let (result, tried) = run_chain(errors).await;
+        assert!(matches!(result, Err(Error::ApiStatus { status: 502, .. })));
+        assert_eq!(tried, ["a", "b", "c"]);
+
+        for error in [status(401, "Invalid API key"), status(400, "Bad request")] {
+            let (result, tried) = run_chain(vec![error]).await;
+            assert!(result.is_err());
+            assert_eq!(tried, ["a"]);
+        }
+    }
+}
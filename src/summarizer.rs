@@ -0,0 +1,291 @@
+use crate::cache::{Cache, CachePolicy};
+use crate::collection::ExpandOptions;
+use crate::config::{Config, ProviderConfig};
+use crate::error::{Error, Result};
+use crate::output::OutputFormat;
+use crate::pipeline::{self, VideoSummary};
+use crate::provider::{AnyProvider, ModelInfo, Provider, ProviderKind};
+use crate::retry::RetryPolicy;
+use crate::summarize::ChapterMode;
+use crate::templates;
+use crate::transcript::{Transcript, TranscriptOptions};
+use crate::video_id::VideoId;
+
+/// Summarizes YouTube videos with a language model
+pub struct Summarizer {
+    config: Config,
+    provider: AnyProvider,
+    cache: Cache,
+}
+
+impl Summarizer {
+    /// Starts building a summarizer that uses OpenRouter, no cache and no
+    /// history
+    pub fn builder() -> SummarizerBuilder {
+        SummarizerBuilder::default()
+    }
+
+    /// Creates a summarizer from a complete configuration, such as the one
+    /// the command line loads from its config file
+    pub fn from_config(config: Config) -> Self {
+        Summarizer {
+            provider: AnyProvider::new(&config.provider),
+            cache: Cache::new(config.cache),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Fetches the transcript of a video in the configured languages
+    pub async fn fetch_transcript(&self, video: &VideoId) -> Result<Transcript> {
+        pipeline::load_transcript(
+            video.as_str(),
+            &self.config.transcript,
+            &self.cache,
+            self.config.verbose,
+        )
+        .await
+    }
+
+    /// Fetches the transcript and metadata of a video and summarizes it
+    pub async fn summarize(&self, video: &VideoId) -> Result<VideoSummary> {
+        pipeline::summarize_video(&video.url(), &self.config, &self.cache, &self.provider).await
+    }
+
+    /// Lists the models of the provider
+    pub async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.provider.list_models().await
+    }
+}
+
+/// Settings of a `Summarizer`, created by `Summarizer::builder`
+#[derive(Debug, Clone)]
+pub struct SummarizerBuilder {
+    provider: ProviderKind,
+    base_url: Option<String>,
+    api_key: Option<String>,
+    /// The model followed by its fallbacks
+    models: Vec<String>,
+    prompt: Option<String>,
+    template: Option<String>,
+    variables: Vec<(String, String)>,
+    transcript: TranscriptOptions,
+    output_language: Option<String>,
+    timestamps: bool,
+    chapters: ChapterMode,
+    retry: RetryPolicy,
+    cache: CachePolicy,
+    history: bool,
+    max_cost: Option<f64>,
+    verbose: bool,
+}
+
+impl Default for SummarizerBuilder {
+    fn default() -> Self {
+        SummarizerBuilder {
+            provider: ProviderKind::OpenRouter,
+            base_url: None,
+            api_key: None,
+            models: Vec::new(),
+            prompt: None,
+            template: None,
+            variables: Vec::new(),
+            transcript: TranscriptOptions::default(),
+            output_language: None,
+            timestamps: false,
+            chapters: ChapterMode::default(),
+            retry: RetryPolicy::default(),
+            cache: CachePolicy::Bypass,
+            history: false,
+            max_cost: None,
+            verbose: false,
+        }
+    }
+}
+
+impl SummarizerBuilder {
+    /// The LLM backend [default: OpenRouter]
+    pub fn provider(mut self, provider: ProviderKind) -> Self {
+        self.provider = provider;
+        self
+    }
+
+    /// The backend's base URL, required for OpenAI-compatible servers
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// The API key, required for OpenRouter
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// The model [default: the provider's default model]. Calling this
+    /// again adds fallback models, tried in order when a model is
+    /// rate-limited or unavailable.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.models.push(model.into());
+        self
+    }
+
+    /// Instructions for the summary, which may use `{{variables}}`
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    /// A built-in or user template to take the prompt from [default:
+    /// default]
+    pub fn template(mut self, name: impl Into<String>) -> Self {
+        self.template = Some(name.into());
+        self
+    }
+
+    /// Sets a template variable
+    pub fn var(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.push((key.into(), value.into()));
+        self
+    }
+
+    /// Preferred transcript languages, most preferred first [default: en]
+    pub fn lang<I, S>(mut self, languages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.transcript.languages = languages.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Has YouTube translate the transcript into a language
+    pub fn translate(mut self, language: impl Into<String>) -> Self {
+        self.transcript.translate_to = Some(language.into());
+        self
+    }
+
+    /// The language to write summaries in [default: the model's choice]
+    pub fn output_language(mut self, language: impl Into<String>) -> Self {
+        self.output_language = Some(language.into());
+        self
+    }
+
+    /// Links the key points of summaries to their position in the video
+    pub fn timestamps(mut self, timestamps: bool) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Whether summaries follow the chapters of a video [default: auto]
+    pub fn chapters(mut self, chapters: ChapterMode) -> Self {
+        self.chapters = chapters;
+        self
+    }
+
+    /// How failed provider requests are retried
+    pub fn retries(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Whether the on-disk cache of the command line is used [default:
+    /// bypass]
+    pub fn cache(mut self, cache: CachePolicy) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Records summaries in the history database of the command line
+    pub fn history(mut self, history: bool) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Refuses requests projected to cost more than this many dollars
+    pub fn max_cost(mut self, dollars: f64) -> Self {
+        self.max_cost = Some(dollars);
+        self
+    }
+
+    /// Logs progress to stderr
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Checks the settings and creates the summarizer
+    pub fn build(self) -> Result<Summarizer> {
+        Ok(Summarizer::from_config(self.into_config()?))
+    }
+
+    fn into_config(self) -> Result<Config> {
+        if self.provider == ProviderKind::OpenRouter && self.api_key.is_none() {
+            return Err(Error::Config("OpenRouter requires an API key".to_string()));
+        }
+        if self.provider == ProviderKind::OpenAi && self.base_url.is_none() {
+            return Err(Error::Config(
+                "The openai provider requires a base URL".to_string(),
+            ));
+        }
+
+        let mut models = self.models.into_iter();
+        let model = models
+            .next()
+            .or_else(|| self.provider.default_model().map(str::to_string))
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "No model given for the {} provider",
+                    self.provider.name()
+                ))
+            })?;
+
+        let (prompt, template) = match (self.prompt, self.template) {
+            (Some(_), Some(_)) => {
+                return Err(Error::Config(
+                    "Give either a prompt or a template, not both".to_string(),
+                ));
+            }
+            (Some(prompt), None) => (prompt, None),
+            (None, template) => {
+                let name = template.unwrap_or_else(|| templates::DEFAULT_TEMPLATE.to_string());
+                (templates::find(&name)?.body, Some(name))
+            }
+        };
+        templates::validate(&prompt, &self.variables)?;
+
+        Ok(Config {
+            provider: ProviderConfig {
+                kind: self.provider,
+                base_url: self.base_url,
+                api_key: self.api_key,
+                retry: self.retry,
+                verbose: self.verbose,
+            },
+            model,
+            fallback_models: models.collect(),
+            prompt,
+            template,
+            variables: self.variables,
+            verbose: self.verbose,
+            // Streamed tokens would be written to stdout
+            stream: false,
+            timestamps: self.timestamps,
+            transcript: self.transcript,
+            output_language: self.output_language,
+            chapters: self.chapters,
+            cache: self.cache,
+            history: self.history,
+            // Only batches on the command line summarize several videos
+            jobs: 1,
+            expand: ExpandOptions::default(),
+            overview: false,
+            format: OutputFormat::Text,
+            max_cost: self.max_cost,
+            serve_token: None,
+        })
+    }
+}
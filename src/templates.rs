@@ -0,0 +1,470 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use crate::error::{Error, Result};
+
+/// Template used when neither `--prompt` nor `--template` is given
+pub const DEFAULT_TEMPLATE: &str = "default";
+
+/// Variables filled in from the video being summarized
+pub const VIDEO_VARIABLES: &[&str] = &[
+    "title",
+    "channel",
+    "upload_date",
+    "duration",
+    "language",
+    "video_id",
+    "url",
+    "start",
+    "playlist",
+];
+
+const BUILTINS: &[(&str, &str)] = &[
+    (
+        "default",
+        "Please provide a comprehensive summary of the following YouTube video transcript. \
+         Include the main topics discussed, key points, and any important conclusions.",
+    ),
+    (
+        "tldr",
+        "Summarize the YouTube video \"{{title}}\" in two or three sentences. State the single \
+         most important takeaway first.",
+    ),
+    (
+        "bullet",
+        "Summarize the YouTube video \"{{title}}\" by {{channel}} as a bulleted list of its key \
+         points, in the order they come up. Keep each bullet to one sentence.",
+    ),
+    (
+        "study-notes",
+        "Turn the transcript of the {{duration}} YouTube video \"{{title}}\" into study notes. \
+         Organize them under headings by topic, define the key terms and concepts, include \
+         examples given in the video, and end with five review questions.",
+    ),
+    (
+        "action-items",
+        "List the concrete action items, recommendations and next steps from the YouTube video \
+         \"{{title}}\". Give each as an imperative sentence, followed by any tools, numbers or \
+         deadlines mentioned. If the video contains none, say so.",
+    ),
+    (
+        "eli5",
+        "Explain what the YouTube video \"{{title}}\" is about as if to a curious ten-year-old. \
+         Use short sentences, everyday words and a comparison to something familiar.",
+    ),
+];
+
+/// Where a template comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Builtin,
+    User,
+    /// A user template replacing the built-in of the same name
+    Override,
+}
+
+impl Source {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::Builtin => "built-in",
+            Source::User => "user",
+            Source::Override => "user (overrides built-in)",
+        }
+    }
+}
+
+/// A named prompt that may reference `{{variables}}`. A literal `{{` is
+/// written as `\{{`.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub body: String,
+    pub source: Source,
+}
+
+impl Template {
+    /// The first line of the body, shortened for `templates list`
+    pub fn description(&self) -> String {
+        let line = self.body.lines().next().unwrap_or_default().trim();
+        let mut chars = line.chars();
+        let short: String = chars.by_ref().take(60).collect();
+        if chars.next().is_some() {
+            format!("{}...", short)
+        } else {
+            short
+        }
+    }
+}
+
+/// Returns `~/.config/youtube-summary/templates`
+pub fn templates_dir() -> PathBuf {
+    let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".config/youtube-summary/templates")
+}
+
+fn template_path(name: &str) -> PathBuf {
+    templates_dir().join(format!("{}.txt", name))
+}
+
+/// Template names may only use characters that are safe in file names
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+    {
+        return Err(Error::Config(format!(
+            "Invalid template name '{}' (use letters, digits, - and _)",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn builtin(name: &str) -> Option<&'static str> {
+    BUILTINS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, body)| *body)
+}
+
+/// Looks up a template, preferring user templates over built-ins
+pub fn find(name: &str) -> Result<Template> {
+    validate_name(name)?;
+
+    let path = template_path(name);
+    if path.exists() {
+        let body = fs::read_to_string(&path).map_err(|e| {
+            Error::Config(format!("Failed to read template {}: {}", path.display(), e))
+        })?;
+        let source = if builtin(name).is_some() {
+            Source::Override
+        } else {
+            Source::User
+        };
+        return Ok(Template {
+            name: name.to_string(),
+            body: body.trim().to_string(),
+            source,
+        });
+    }
+
+    builtin(name)
+        .map(|body| Template {
+            name: name.to_string(),
+            body: body.to_string(),
+            source: Source::Builtin,
+        })
+        .ok_or_else(|| {
+            Error::Config(format!(
+                "Unknown template '{}' (see `youtube-summary templates list`)",
+                name
+            ))
+        })
+}
+
+/// Lists built-in and user templates by name
+pub fn list() -> Result<Vec<Template>> {
+    let mut names: Vec<String> = BUILTINS.iter().map(|(name, _)| name.to_string()).collect();
+
+    if let Ok(read_dir) = fs::read_dir(templates_dir()) {
+        for item in read_dir.flatten() {
+            let path = item.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("txt") {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str())
+                && validate_name(name).is_ok()
+                && !names.iter().any(|known| known == name)
+            {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names.sort();
+    names.iter().map(|name| find(name)).collect()
+}
+
+/// Creates a user template, starting from `base` (a template name) or the
+/// default template, and returns its path
+pub fn create(name: &str, base: Option<&str>) -> Result<PathBuf> {
+    validate_name(name)?;
+
+    let path = template_path(name);
+    if path.exists() {
+        return Err(Error::Config(format!(
+            "Template '{}' already exists at {}",
+            name,
+            path.display()
+        )));
+    }
+
+    let body = find(base.unwrap_or(DEFAULT_TEMPLATE))?.body;
+    write(&path, &body)?;
+    Ok(path)
+}
+
+/// Opens a user template in `$VISUAL` or `$EDITOR`. Editing a built-in
+/// template creates a user copy that overrides it.
+pub fn edit(name: &str) -> Result<PathBuf> {
+    let template = find(name)?;
+    let path = template_path(name);
+    if template.source == Source::Builtin {
+        write(&path, &template.body)?;
+    }
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    // The editor setting may carry arguments, e.g. "code --wait"
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = process::Command::new(program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .map_err(|e| Error::Config(format!("Failed to start editor '{}': {}", editor, e)))?;
+
+    if !status.success() {
+        return Err(Error::Config(format!(
+            "Editor '{}' failed: {}",
+            editor, status
+        )));
+    }
+
+    // Catch mistakes right away rather than on the next summary
+    let edited = find(name)?;
+    parse(&edited.body)?;
+    Ok(path)
+}
+
+fn write(path: &PathBuf, body: &str) -> Result<()> {
+    let dir = templates_dir();
+    fs::create_dir_all(&dir)
+        .map_err(|e| Error::Config(format!("Failed to create {}: {}", dir.display(), e)))?;
+    fs::write(path, format!("{}\n", body))
+        .map_err(|e| Error::Config(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// A piece of a parsed template
+enum Part<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+}
+
+/// Splits a template into text and `{{variable}}` references
+fn parse(body: &str) -> Result<Vec<Part<'_>>> {
+    let mut parts = Vec::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        // `\{{` stands for the braces themselves, e.g. in code examples
+        if let Some(text) = rest[..start].strip_suffix('\\') {
+            parts.push(Part::Text(text));
+            parts.push(Part::Text("{{"));
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        parts.push(Part::Text(&rest[..start]));
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| Error::Config("Unclosed '{{' in prompt template".to_string()))?;
+
+        let name = after[..end].trim();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+        {
+            return Err(Error::Config(format!(
+                "Invalid variable '{{{{{}}}}}' in prompt template",
+                &after[..end]
+            )));
+        }
+        parts.push(Part::Variable(name));
+        rest = &after[end + 2..];
+    }
+    parts.push(Part::Text(rest));
+
+    Ok(parts)
+}
+
+/// Names of the variables a template references, without duplicates
+pub fn variables(body: &str) -> Result<Vec<String>> {
+    let mut names: Vec<String> = Vec::new();
+    for part in parse(body)? {
+        if let Part::Variable(name) = part
+            && !names.iter().any(|known| known == name)
+        {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// Checks that a template only references video variables and the
+/// user-supplied `extra` ones
+pub fn validate(body: &str, extra: &[(String, String)]) -> Result<()> {
+    let unknown: Vec<String> = variables(body)?
+        .into_iter()
+        .filter(|name| {
+            !VIDEO_VARIABLES.contains(&name.as_str()) && !extra.iter().any(|(key, _)| key == name)
+        })
+        .collect();
+
+    if unknown.is_empty() {
+        return Ok(());
+    }
+
+    Err(Error::Config(format!(
+        "Unknown template variable(s): {} (available: {}; set others with --var KEY=VALUE)",
+        unknown.join(", "),
+        VIDEO_VARIABLES.join(", ")
+    )))
+}
+
+/// Fills in the variables of a template. Every referenced variable must
+/// have a value, which `validate` ensures for configured prompts.
+pub fn render(body: &str, values: &BTreeMap<String, String>) -> Result<String> {
+    let mut text = String::new();
+    for part in parse(body)? {
+        match part {
+            Part::Text(s) => text.push_str(s),
+            Part::Variable(name) => {
+                let value = values.get(name).ok_or_else(|| {
+                    Error::Config(format!("No value for template variable '{}'", name))
+                })?;
+                text.push_str(value);
+            }
+        }
+    }
+    Ok(text)
+}
+
+/// Parses a `--var KEY=VALUE` argument
+pub fn parse_var(arg: &str) -> std::result::Result<(String, String), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", arg))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("missing variable name in '{}'", arg));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("title".to_string(), "Talk".to_string()),
+            ("channel".to_string(), "Chan".to_string()),
+        ])
+    }
+
+    #[test]
+    fn renders_variables() {
+        let cases = [
+            ("Plain prompt", "Plain prompt"),
+            ("{{title}} by {{ channel }}!", "Talk by Chan!"),
+            ("{{title}}{{title}}", "TalkTalk"),
+            ("Braces } and }} stay", "Braces } and }} stay"),
+            (r"Code: \{{x}} in {{title}}", "Code: {{x}} in Talk"),
+            (r"\{{\{{", "{{{{"),
+            ("{ {title} }", "{ {title} }"),
+        ];
+        for (body, expected) in cases {
+            assert_eq!(render(body, &values()).unwrap(), expected, "{:?}", body);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        for body in [
+            "{{title",
+            "{{}}",
+            "{{ }}",
+            "{{a b}}",
+            "{{a.b}}",
+            "x {{title}} {{",
+        ] {
+            assert!(render(body, &values()).is_err(), "{:?}", body);
+            assert!(variables(body).is_err(), "{:?}", body);
+        }
+        match render("{{missing}}", &values()) {
+            Err(Error::Config(message)) => assert!(message.contains("'missing'")),
+            other => panic!("expected a missing value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn lists_variables_once() {
+        assert_eq!(
+            variables(r"{{a}} {{b}} {{ a }} \{{c}}").unwrap(),
+            ["a", "b"]
+        );
+        assert!(variables("none").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_variables() {
+        assert!(validate("{{title}} {{start}} {{playlist}}", &[]).is_ok());
+        assert!(validate(r"\{{unknown}}", &[]).is_ok());
+
+        match validate("{{title}} {{audience}} {{tone}}", &[]) {
+            Err(Error::Config(message)) => {
+                assert!(message.contains("audience, tone"), "{}", message)
+            }
+            other => panic!("expected unknown variables, got {:?}", other),
+        }
+
+        let extra = [("audience".to_string(), "kids".to_string())];
+        assert!(validate("{{audience}}", &extra).is_ok());
+        assert!(validate("{{audience}} {{tone}}", &extra).is_err());
+    }
+
+    #[test]
+    fn builtins_are_valid() {
+        for (name, body) in BUILTINS {
+            assert!(validate_name(name).is_ok(), "{}", name);
+            assert!(validate(body, &[]).is_ok(), "{}", name);
+        }
+        assert_eq!(builtin(DEFAULT_TEMPLATE), Some(BUILTINS[0].1));
+    }
+
+    #[test]
+    fn parses_vars() {
+        let cases = [
+            ("key=value", Some(("key", "value"))),
+            (" key =a=b ", Some(("key", "a=b "))),
+            ("key=", Some(("key", ""))),
+            ("=value", None),
+            ("  =value", None),
+            ("novalue", None),
+        ];
+        for (arg, expected) in cases {
+            let parsed = parse_var(arg).ok();
+            let parsed = parsed.as_ref().map(|(k, v)| (k.as_str(), v.as_str()));
+            assert_eq!(parsed, expected, "{:?}", arg);
+        }
+    }
+
+    #[test]
+    fn validates_names() {
+        for name in ["tldr", "my-template_2"] {
+            assert!(validate_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", "../x", "a b", "a.txt", "caf\u{e9}"] {
+            assert!(validate_name(name).is_err(), "{:?}", name);
+        }
+    }
+}
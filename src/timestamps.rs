@@ -0,0 +1,213 @@
+/// Formats seconds as `mm:ss`, or `h:mm:ss` for videos longer than an hour
+pub fn format_timestamp(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+    let secs = seconds % 60;
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}:{:02}", minutes, secs)
+    }
+}
+
+/// Parses a `mm:ss` or `h:mm:ss` timestamp into seconds
+pub fn parse_timestamp(s: &str) -> Option<u64> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return None;
+    }
+
+    let mut seconds = 0u64;
+    for (i, part) in parts.iter().enumerate() {
+        if part.is_empty() || part.len() > 2 || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let value: u64 = part.parse().ok()?;
+        // Everything but the leading component is two digits below 60
+        if i > 0 && (part.len() != 2 || value >= 60) {
+            return None;
+        }
+        seconds = seconds * 60 + value;
+    }
+
+    Some(seconds)
+}
+
+/// Builds a link that opens the video at the given second
+pub fn video_link(video_id: &str, seconds: u64) -> String {
+    format!("https://youtu.be/{}?t={}", video_id, seconds)
+}
+
+/// Result of post-processing a timestamped summary
+#[derive(Debug)]
+pub struct LinkedSummary {
+    pub text: String,
+    /// Cited timestamps that don't exist within the video
+    pub invalid: Vec<String>,
+}
+
+/// Replaces every `[mm:ss]` citation in a summary with a markdown link into
+/// the video. Citations beyond the video duration are removed and reported.
+///
+/// Citations the model already turned into markdown links are rewritten
+/// too, so every link in the output points at the right video. Without a
+/// video, as for local subtitle files, citations are left unlinked.
+pub fn link_timestamps(summary: &str, video_id: Option<&str>, duration: f64) -> LinkedSummary {
+    let mut text = String::with_capacity(summary.len());
+    let mut invalid = Vec::new();
+    let mut rest = summary;
+
+    while let Some(open) = rest.find('[') {
+        let after_open = &rest[open + 1..];
+        let citation = after_open
+            .find(']')
+            .filter(|&close| close <= 8)
+            .and_then(|close| parse_timestamp(&after_open[..close]).map(|secs| (close, secs)));
+
+        let Some((close, seconds)) = citation else {
+            text.push_str(&rest[..=open]);
+            rest = after_open;
+            continue;
+        };
+
+        let label = &after_open[..close];
+        let mut end = open + 1 + close + 1;
+
+        // Swallow an existing markdown link target
+        if rest[end..].starts_with('(')
+            && let Some(paren) = rest[end..].find(')')
+        {
+            end += paren + 1;
+        }
+
+        // Allow for rounding of the final snippet's end time
+        if seconds as f64 <= duration.ceil() {
+            text.push_str(&rest[..open]);
+            match video_id {
+                Some(video_id) => {
+                    text.push_str(&format!("[{}]({})", label, video_link(video_id, seconds)))
+                }
+                None => text.push_str(&format!("[{}]", label)),
+            }
+        } else {
+            text.push_str(rest[..open].trim_end_matches(' '));
+            invalid.push(label.to_string());
+        }
+
+        rest = &rest[end..];
+    }
+
+    text.push_str(rest);
+
+    LinkedSummary { text, invalid }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "dQw4w9WgXcQ";
+
+    fn link(seconds: u64) -> String {
+        video_link(ID, seconds)
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        let cases = [
+            ("00:00", Some(0)),
+            ("1:30", Some(90)),
+            ("01:30", Some(90)),
+            ("59:59", Some(3599)),
+            ("1:02:03", Some(3723)),
+            ("10:00:00", Some(36_000)),
+            ("1:2", None),
+            ("1:60", None),
+            ("1:02:60", None),
+            ("123:00", None),
+            (":30", None),
+            ("1:30:", None),
+            ("1:02:03:04", None),
+            ("90", None),
+            ("a:bc", None),
+            ("+1:30", None),
+            (" 1:30", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_timestamp(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        let cases = [
+            (0, "00:00"),
+            (59, "00:59"),
+            (90, "01:30"),
+            (3599, "59:59"),
+            (3600, "1:00:00"),
+            (3723, "1:02:03"),
+            (36_000, "10:00:00"),
+        ];
+        for (seconds, expected) in cases {
+            assert_eq!(format_timestamp(seconds), expected);
+            assert_eq!(parse_timestamp(expected), Some(seconds));
+        }
+    }
+
+    #[test]
+    fn links_citations() {
+        let linked = link_timestamps("See [01:30] and [1:02:03].", Some(ID), 4000.0);
+        assert_eq!(
+            linked.text,
+            format!("See [01:30]({}) and [1:02:03]({}).", link(90), link(3723))
+        );
+        assert!(linked.invalid.is_empty());
+
+        // The end of the last snippet may be rounded down
+        let linked = link_timestamps("[01:30]", Some(ID), 89.4);
+        assert_eq!(linked.text, format!("[01:30]({})", link(90)));
+    }
+
+    #[test]
+    fn removes_citations_beyond_the_video() {
+        let linked = link_timestamps("Intro [00:10], end [99:00].", Some(ID), 600.0);
+        assert_eq!(linked.text, format!("Intro [00:10]({}), end.", link(10)));
+        assert_eq!(linked.invalid, ["99:00"]);
+
+        let linked = link_timestamps(
+            "Late [1:00:00](https://youtu.be/x?t=3600) point",
+            Some(ID),
+            60.0,
+        );
+        assert_eq!(linked.text, "Late point");
+        assert_eq!(linked.invalid, ["1:00:00"]);
+    }
+
+    #[test]
+    fn rewrites_existing_links() {
+        let summary = "At [01:30](https://youtu.be/other?t=5), then [02:00](https://example.com).";
+        let linked = link_timestamps(summary, Some(ID), 600.0);
+        assert_eq!(
+            linked.text,
+            format!("At [01:30]({}), then [02:00]({}).", link(90), link(120))
+        );
+    }
+
+    #[test]
+    fn leaves_local_transcripts_unlinked() {
+        let summary = "At [01:30], then [02:00](https://youtu.be/other?t=120) and [99:00].";
+        let linked = link_timestamps(summary, None, 600.0);
+        assert_eq!(linked.text, "At [01:30], then [02:00] and.");
+        assert_eq!(linked.invalid, ["99:00"]);
+    }
+
+    #[test]
+    fn ignores_other_brackets() {
+        let summary = "A [note], [1:2], [12345678:00], [ 01:30 ] and [";
+        let linked = link_timestamps(summary, Some(ID), 600.0);
+        assert_eq!(linked.text, summary);
+        assert!(linked.invalid.is_empty());
+    }
+}
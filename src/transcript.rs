@@ -1,117 +1,286 @@
 use crate::error::{Error, Result};
+use crate::timestamps;
+use crate::video_id::VideoId;
+use serde::{Deserialize, Serialize};
 use yt_transcript_rs::api::YouTubeTranscriptApi;
+use yt_transcript_rs::transcript::Transcript as Track;
+use yt_transcript_rs::transcript_list::TranscriptList;
 
-pub async fn fetch_transcript(url: &str) -> Result<String> {
-    let video_id = extract_video_id(url)?;
+/// Browser user agent; YouTube serves degraded pages to unknown clients
+pub(crate) const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
 
-    let api = YouTubeTranscriptApi::new(None, None, None)
-        .map_err(|e| Error::TranscriptFetch(format!("Failed to create API client: {}", e)))?;
+/// Skips the cookie consent interstitial served to EU visitors
+pub(crate) const CONSENT_COOKIE: &str = "CONSENT=YES+cb; SOCS=CAI";
 
-    let transcripts = api
-        .fetch_transcript(&video_id, &["en"], true)
-        .await
-        .map_err(|e| Error::TranscriptFetch(format!("Failed to fetch transcript: {}", e)))?;
+/// Minimum number of seconds between two `[mm:ss]` markers in the
+/// timestamped transcript text
+const MARKER_INTERVAL_SECS: f64 = 30.0;
 
-    // Combine all transcript segments into a single string
-    let text: String = transcripts
-        .snippets
-        .iter()
-        .map(|segment| segment.text.as_str())
-        .collect::<Vec<_>>()
-        .join(" ");
-
-    if text.is_empty() {
-        return Err(Error::TranscriptFetch("Transcript is empty".to_string()));
-    }
+/// A single caption snippet with its position in the video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snippet {
+    pub text: String,
+    /// Start time in seconds
+    pub start: f64,
+    /// Time in seconds the snippet stays on screen
+    pub duration: f64,
+}
 
-    Ok(text)
+/// A fetched transcript, kept as individual caption snippets so that long
+/// transcripts can be split on snippet boundaries and summaries can point
+/// back into the video.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    pub video_id: String,
+    /// Language code of the fetched track, e.g. "en" or "pt-BR"
+    pub language: String,
+    pub snippets: Vec<Snippet>,
+    /// The subtitle file a local transcript was read from, "-" for stdin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
 }
 
-fn extract_video_id(url: &str) -> Result<String> {
-    // Handle various YouTube URL formats:
-    // - https://www.youtube.com/watch?v=VIDEO_ID
-    // - https://youtube.com/watch?v=VIDEO_ID
-    // - https://youtu.be/VIDEO_ID
-    // - https://www.youtube.com/embed/VIDEO_ID
-    // - VIDEO_ID (direct ID)
-
-    let url = url.trim();
-
-    // Check if it's already just a video ID (11 characters, alphanumeric + - + _)
-    if url.len() == 11
-        && url
-            .chars()
-            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
-    {
-        return Ok(url.to_string());
+impl Transcript {
+    /// The ID of the YouTube video, or `None` for local subtitle files,
+    /// which have no video to link to
+    pub fn youtube_id(&self) -> Option<&str> {
+        match self.file {
+            Some(_) => None,
+            None => Some(&self.video_id),
+        }
     }
 
-    // Try to extract from various URL formats
-    if let Some(id) = extract_from_watch_url(url) {
-        return Ok(id);
+    /// Returns the length of the full transcript text
+    pub fn text_len(&self) -> usize {
+        let separators = self.snippets.len().saturating_sub(1);
+        self.snippets.iter().map(|s| s.text.len()).sum::<usize>() + separators
     }
 
-    if let Some(id) = extract_from_short_url(url) {
-        return Ok(id);
+    /// Returns the video duration in seconds, as covered by the transcript
+    pub fn duration(&self) -> f64 {
+        self.snippets
+            .iter()
+            .map(|s| s.start + s.duration)
+            .fold(0.0, f64::max)
     }
 
-    if let Some(id) = extract_from_embed_url(url) {
-        return Ok(id);
+    /// Splits the transcript at the given start times, in seconds and in
+    /// ascending order, into one part per start time. Snippets before the
+    /// first start time belong to the first part.
+    pub fn split_at(&self, starts: &[u64]) -> Vec<Transcript> {
+        let mut parts: Vec<Transcript> = starts
+            .iter()
+            .map(|_| Transcript {
+                video_id: self.video_id.clone(),
+                language: self.language.clone(),
+                snippets: Vec::new(),
+                file: self.file.clone(),
+            })
+            .collect();
+
+        for snippet in &self.snippets {
+            let index = starts
+                .iter()
+                .rposition(|&start| snippet.start >= start as f64)
+                .unwrap_or(0);
+            if let Some(part) = parts.get_mut(index) {
+                part.snippets.push(snippet.clone());
+            }
+        }
+
+        parts
     }
 
-    Err(Error::InvalidYoutubeUrl(format!(
-        "Could not extract video ID from: {}",
-        url
-    )))
-}
+    /// Splits the transcript into pieces that chunking may not break apart.
+    ///
+    /// Without timestamps every snippet is its own piece. With timestamps,
+    /// consecutive snippets are grouped into passages that each start with
+    /// a `[mm:ss]` marker, at most one marker every 30 seconds.
+    pub fn pieces(&self, with_timestamps: bool) -> Vec<String> {
+        if !with_timestamps {
+            return self.snippets.iter().map(|s| s.text.clone()).collect();
+        }
 
-fn extract_from_watch_url(url: &str) -> Option<String> {
-    // https://www.youtube.com/watch?v=VIDEO_ID&other=params
-    if url.contains("youtube.com/watch")
-        && let Some(v_pos) = url.find("v=")
-    {
-        let start = v_pos + 2;
-        let id: String = url[start..]
-            .chars()
-            .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
-            .collect();
-        if id.len() == 11 {
-            return Some(id);
+        let mut pieces: Vec<String> = Vec::new();
+        let mut marker_start = f64::NEG_INFINITY;
+
+        for snippet in &self.snippets {
+            match pieces.last_mut() {
+                Some(piece) if snippet.start - marker_start < MARKER_INTERVAL_SECS => {
+                    piece.push(' ');
+                    piece.push_str(&snippet.text);
+                }
+                _ => {
+                    marker_start = snippet.start;
+                    pieces.push(format!(
+                        "[{}] {}",
+                        timestamps::format_timestamp(snippet.start as u64),
+                        snippet.text
+                    ));
+                }
+            }
         }
+
+        pieces
     }
-    None
 }
 
-fn extract_from_short_url(url: &str) -> Option<String> {
-    // https://youtu.be/VIDEO_ID
-    if url.contains("youtu.be/")
-        && let Some(pos) = url.find("youtu.be/")
-    {
-        let start = pos + 9;
-        let id: String = url[start..]
-            .chars()
-            .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
-            .collect();
-        if id.len() == 11 {
-            return Some(id);
+/// Which transcript track to fetch
+#[derive(Debug, Clone)]
+pub struct TranscriptOptions {
+    /// Preferred language codes, most preferred first
+    pub languages: Vec<String>,
+    /// Language to have YouTube translate the transcript into
+    pub translate_to: Option<String>,
+}
+
+impl Default for TranscriptOptions {
+    fn default() -> Self {
+        TranscriptOptions {
+            languages: vec!["en".to_string()],
+            translate_to: None,
         }
     }
-    None
 }
 
-fn extract_from_embed_url(url: &str) -> Option<String> {
-    // https://www.youtube.com/embed/VIDEO_ID
-    if url.contains("youtube.com/embed/")
-        && let Some(pos) = url.find("/embed/")
-    {
-        let start = pos + 7;
-        let id: String = url[start..]
-            .chars()
-            .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
-            .collect();
-        if id.len() == 11 {
-            return Some(id);
+/// A transcript track available for a video
+#[derive(Debug)]
+pub struct TrackInfo {
+    pub language: String,
+    pub language_code: String,
+    pub is_generated: bool,
+    pub is_translatable: bool,
+}
+
+pub async fn fetch_transcript(video_id: &str, options: &TranscriptOptions) -> Result<Transcript> {
+    let video_id = video_id.to_string();
+    let client = http_client()?;
+    let list = list_tracks(&client, &video_id).await?;
+
+    let track = select_track(&list, options)?;
+
+    let fetched = track
+        .fetch(&client, false)
+        .await
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to fetch transcript: {}", e)))?;
+
+    // Keep the non-empty transcript snippets
+    let snippets: Vec<Snippet> = fetched
+        .snippets
+        .into_iter()
+        .filter(|snippet| !snippet.text.trim().is_empty())
+        .map(|snippet| Snippet {
+            text: snippet.text.trim().to_string(),
+            start: snippet.start,
+            duration: snippet.duration,
+        })
+        .collect();
+
+    if snippets.is_empty() {
+        return Err(Error::TranscriptFetch("Transcript is empty".to_string()));
+    }
+
+    Ok(Transcript {
+        video_id,
+        language: track.language_code,
+        snippets,
+        file: None,
+    })
+}
+
+/// Lists the transcript tracks available for a video, manually created
+/// tracks first
+pub async fn list_transcripts(url: &str) -> Result<Vec<TrackInfo>> {
+    let video_id = VideoId::parse(url)?;
+    let client = http_client()?;
+    let list = list_tracks(&client, video_id.as_str()).await?;
+
+    let mut tracks: Vec<TrackInfo> = list
+        .transcripts()
+        .map(|track| TrackInfo {
+            language: track.language().to_string(),
+            language_code: track.language_code().to_string(),
+            is_generated: track.is_generated(),
+            is_translatable: track.is_translatable(),
+        })
+        .collect();
+
+    tracks.sort_by(|a, b| {
+        a.is_generated
+            .cmp(&b.is_generated)
+            .then_with(|| a.language_code.cmp(&b.language_code))
+    });
+
+    Ok(tracks)
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to create HTTP client: {}", e)))
+}
+
+async fn list_tracks(client: &reqwest::Client, video_id: &str) -> Result<TranscriptList> {
+    let api = YouTubeTranscriptApi::new(None, None, Some(client.clone()))
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to create API client: {}", e)))?;
+
+    api.list_transcripts(video_id)
+        .await
+        .map_err(|e| Error::TranscriptFetch(format!("Failed to list transcripts: {}", e)))
+}
+
+/// Picks the track to fetch.
+///
+/// Tracks in the preferred languages are tried in order, manually created
+/// before auto-generated. If none exists, a translatable track is
+/// translated into the most preferred language instead. An explicit
+/// translation target is applied to whichever track was selected.
+fn select_track(list: &TranscriptList, options: &TranscriptOptions) -> Result<Track> {
+    let languages: Vec<&str> = options.languages.iter().map(String::as_str).collect();
+
+    let track = match list.find_transcript(&languages) {
+        Ok(track) => track,
+        Err(_) => {
+            let fallback = options
+                .translate_to
+                .as_deref()
+                .or(languages.first().copied());
+
+            match (fallback, translatable_track(list)) {
+                (Some(target), Some(track)) => return translate(&track, target),
+                _ => {
+                    let available: Vec<&str> =
+                        list.transcripts().map(|t| t.language_code()).collect();
+                    return Err(Error::TranscriptFetch(format!(
+                        "No transcript found for languages [{}] (available: [{}])",
+                        languages.join(", "),
+                        available.join(", ")
+                    )));
+                }
+            }
         }
+    };
+
+    match options.translate_to.as_deref() {
+        Some(target) if target != track.language_code() => translate(&track, target),
+        _ => Ok(track),
     }
-    None
+}
+
+/// Finds a track YouTube can translate, preferring manually created ones
+fn translatable_track(list: &TranscriptList) -> Option<Track> {
+    list.transcripts()
+        .filter(|track| track.is_translatable())
+        .min_by_key(|track| track.is_generated())
+        .cloned()
+}
+
+fn translate(track: &Track, language_code: &str) -> Result<Track> {
+    track.translate(language_code).map_err(|e| {
+        Error::TranscriptFetch(format!(
+            "Failed to translate transcript to '{}': {}",
+            language_code, e
+        ))
+    })
 }